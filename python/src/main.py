@@ -1,24 +1,50 @@
 #!/usr/bin/env python3
 """
-Sidecar Python per la trascrizione audio con faster-whisper.
+Sidecar Python per la trascrizione audio con Whisper.
 Supporta trascrizione batch e streaming.
-Comunica con Tauri via stdin/stdout usando JSON.
+
+Il processo resta attivo fra una richiesta e l'altra, così i modelli
+caricati restano in memoria. Comunica con Tauri via stdin/stdout con
+JSON-RPC 2.0, un messaggio per riga:
+
+- richiesta: {"jsonrpc": "2.0", "id": 7, "method": "transcribe", "params": {...}}
+- risposta: {"jsonrpc": "2.0", "id": 7, "result": {...}}
+  oppure {"jsonrpc": "2.0", "id": 7, "error": {"code": -32000, "message": "..."}}
+- notifica: {"jsonrpc": "2.0", "method": "progress", "params": {"id": 7, ...}}
+  dove params.id è la richiesta a cui si riferisce ("progress" per
+  transcribe, "streaming_update" per start_streaming)
+
+La prima richiesta è sempre "initialize", con la versione del protocollo
+attesa dal client (PROTOCOL_VERSION, uguale a quella in
+src-tauri/src/transcription/rpc.rs).
 """
 
 import json
 import logging
+import os
 import sys
 import threading
+from concurrent.futures import ThreadPoolExecutor
 from pathlib import Path
 
 # Import relativi
 try:
     from streaming_transcription import StreamingTranscriber
-    from transcription import transcribe_audio
+    from transcription import TranscriptionCancelled, transcribe_audio
 except ImportError:
     # Se eseguito come modulo
     from .streaming_transcription import StreamingTranscriber
-    from .transcription import transcribe_audio
+    from .transcription import TranscriptionCancelled, transcribe_audio
+
+PROTOCOL_VERSION = 1
+
+# Codici di errore JSON-RPC
+PARSE_ERROR = -32700
+INVALID_REQUEST = -32600
+METHOD_NOT_FOUND = -32601
+INVALID_PARAMS = -32602
+SERVER_ERROR = -32000
+REQUEST_CANCELLED = -32800
 
 # Setup logging to stderr (stdout is used for communication)
 logging.basicConfig(
@@ -28,121 +54,268 @@ logging.basicConfig(
 
 logger = logging.getLogger(__name__)
 
-# Streaming transcriber globale
+# Le risposte arrivano da più thread: una riga alla volta su stdout
+output_lock = threading.Lock()
+
+# Le trascrizioni batch girano in background, così il ciclo principale
+# continua a ricevere enunciati e comandi di streaming
+workers = ThreadPoolExecutor(max_workers=2)
+
+# Richieste di annullamento dei job di trascrizione in corso, per job_id
+cancel_events = {}
+cancel_lock = threading.Lock()
+
+# Streaming transcriber globale; gli eventi sono quelli della sessione
+# attuale, ricreati a ogni start_streaming
 streaming_transcriber: StreamingTranscriber = None
 streaming_thread: threading.Thread = None
 stop_event = threading.Event()
+pause_event = threading.Event()
+
+
+class RpcError(Exception):
+    """Errore da restituire al client con un codice JSON-RPC"""
+
+    def __init__(self, code: int, message: str):
+        super().__init__(message)
+        self.code = code
+        self.message = message
+
+
+def send_message(message: dict):
+    """Scrive un messaggio JSON-RPC su stdout."""
+    message["jsonrpc"] = "2.0"
+    line = json.dumps(message)
+    with output_lock:
+        print(line, flush=True)
+
+
+def send_result(request_id, result):
+    send_message({"id": request_id, "result": result})
+
+
+def send_error(request_id, code: int, message: str):
+    send_message({"id": request_id, "error": {"code": code, "message": message}})
+
+
+def send_notification(method: str, request_id, **params):
+    """Notifica legata alla richiesta `request_id`."""
+    send_message({"method": method, "params": {"id": request_id, **params}})
+
+
+def require(params: dict, name: str):
+    if params.get(name) is None:
+        raise RpcError(INVALID_PARAMS, f"Missing parameter: {name}")
+    return params[name]
+
 
+def initialize(request_id, params):
+    version = params.get("protocol_version")
+    if version != PROTOCOL_VERSION:
+        raise RpcError(
+            INVALID_REQUEST,
+            f"Unsupported protocol version {version}, expected {PROTOCOL_VERSION}",
+        )
+    return {"protocol_version": PROTOCOL_VERSION, "methods": sorted(METHODS)}
 
-def send_response(success: bool, result=None, error=None):
-    """Send JSON response to stdout."""
-    response = {"success": success, "result": result, "error": error}
-    print(json.dumps(response), flush=True)
 
+def transcribe(request_id, params):
+    """Batch transcription"""
+    audio_path = require(params, "audio_path")
+    model_size = params.get("model_size") or "base"
 
-def send_streaming_update(segments):
-    """Send streaming transcription update."""
-    update = {"type": "streaming_update", "segments": segments}
-    print(json.dumps(update), flush=True)
+    def send_progress(progress):
+        send_notification("progress", request_id, **progress)
 
+    job_id = params.get("job_id")
+    cancel_event = None
+    if job_id is not None:
+        with cancel_lock:
+            cancel_event = cancel_events.setdefault(job_id, threading.Event())
 
-def start_streaming_transcription(audio_path, model_size, language):
+    logger.info(f"Transcribing: {audio_path} with model {model_size}")
+    try:
+        result = transcribe_audio(
+            audio_path=audio_path,
+            model_size=model_size,
+            language=params.get("language"),
+            speech_segments=params.get("speech_segments"),
+            on_progress=send_progress,
+            cancel_event=cancel_event,
+        )
+    except TranscriptionCancelled:
+        logger.info(f"Transcription cancelled: {audio_path}")
+        raise RpcError(REQUEST_CANCELLED, "Transcription cancelled")
+    finally:
+        if job_id is not None:
+            with cancel_lock:
+                cancel_events.pop(job_id, None)
+    logger.info("Transcription completed successfully")
+    return result
+
+
+def cancel(request_id, params):
+    """Interrompe le trascrizioni del job, anche quelle non ancora partite"""
+    job_id = require(params, "job_id")
+    # L'annullamento può arrivare prima della richiesta transcribe: l'evento
+    # resta registrato e la trascrizione si ferma appena parte
+    with cancel_lock:
+        running = job_id in cancel_events
+        cancel_event = cancel_events.setdefault(job_id, threading.Event())
+    logger.info(f"Cancelling transcription job {job_id}")
+    cancel_event.set()
+    return {"cancelled": running}
+
+
+def start_streaming(request_id, params):
     """Start streaming transcription in background"""
-    global streaming_transcriber, streaming_thread, stop_event
+    global streaming_transcriber, streaming_thread, stop_event, pause_event
+
+    audio_path = require(params, "audio_path")
+    model_size = params.get("model_size") or "base"
+    logger.info(f"Starting streaming transcription: {audio_path}")
 
-    # Reset stop event
-    stop_event.clear()
+    # Una sola sessione alla volta: la precedente viene chiusa. La si
+    # attende nel thread della nuova sessione, con eventi propri, così il
+    # ciclo principale non resta bloccato sull'enunciato in corso
+    stop_event.set()
+    previous_thread = streaming_thread
+    stop_event = threading.Event()
+    pause_event = threading.Event()
+    session_stop, session_pause = stop_event, pause_event
 
+    # Il modello viene caricato nel thread: il costruttore non fa lavoro
     streaming_transcriber = StreamingTranscriber(
-        model_size=model_size, device="cpu", language=language
+        model_size=model_size, device="cpu", language=params.get("language")
     )
+    transcriber = streaming_transcriber
+
+    def send_update(segments):
+        send_notification("streaming_update", request_id, segments=segments)
 
     def transcribe_loop():
         try:
-            streaming_transcriber.monitor_and_transcribe(
+            if previous_thread is not None:
+                previous_thread.join()
+            transcriber.load_model()
+            transcriber.monitor_and_transcribe(
                 audio_path=Path(audio_path),
-                callback=send_streaming_update,
-                stop_event=stop_event,
-                check_interval=3.0,  # Check every 3 seconds
-                min_chunk_duration=8.0,  # Transcribe when 8s of new audio available
+                callback=send_update,
+                stop_event=session_stop,
+                pause_event=session_pause,
+                check_interval=0.5,
             )
         except Exception as e:
             logger.error(f"Streaming transcription error: {e}", exc_info=True)
-            send_response(success=False, error=f"Streaming error: {str(e)}")
+            send_notification("streaming_error", request_id, message=str(e))
 
     streaming_thread = threading.Thread(target=transcribe_loop, daemon=True)
     streaming_thread.start()
 
-    send_response(success=True, result={"status": "streaming_started"})
+    return {"status": "streaming_started"}
 
 
-def stop_streaming_transcription():
-    """Stop streaming transcription"""
-    global stop_event
-    stop_event.set()
-    send_response(success=True, result={"status": "streaming_stopped"})
+def pause_streaming(request_id, params):
+    """Idle streaming transcription while recording is paused"""
+    logger.info("Pausing streaming transcription")
+    pause_event.set()
+    return {"status": "streaming_paused"}
 
 
-def main():
-    """Main loop - read commands from stdin and execute them."""
-    logger.info("Transcription sidecar started")
+def resume_streaming(request_id, params):
+    """Resume streaming transcription after a pause"""
+    logger.info("Resuming streaming transcription")
+    pause_event.clear()
+    return {"status": "streaming_resumed"}
 
-    try:
-        # Read one line from stdin (the command)
-        for line in sys.stdin:
-            line = line.strip()
-            if not line:
-                continue
 
-            try:
-                request = json.loads(line)
-                command = request.get("command")
+def utterance(request_id, params):
+    """Enunciato concluso, rilevato dal VAD durante la registrazione"""
+    if streaming_transcriber is None or stop_event.is_set():
+        raise RpcError(SERVER_ERROR, "Streaming transcription not started")
+    streaming_transcriber.queue_utterance(
+        float(require(params, "start_seconds")),
+        float(require(params, "end_seconds")),
+        params.get("audio_path"),
+        float(params.get("segment_start_seconds") or 0.0),
+    )
+    return {"status": "utterance_queued"}
 
-                if command == "transcribe":
-                    # Batch transcription
-                    audio_path = request.get("audio_path")
-                    model_size = request.get("model_size", "base")
-                    language = request.get("language")
 
-                    logger.info(f"Transcribing: {audio_path} with model {model_size}")
+def stop_streaming(request_id, params):
+    """Stop streaming transcription"""
+    logger.info("Stopping streaming transcription")
+    stop_event.set()
+    return {"status": "streaming_stopped"}
 
-                    result = transcribe_audio(
-                        audio_path=audio_path, model_size=model_size, language=language
-                    )
 
-                    send_response(success=True, result=result)
-                    logger.info("Transcription completed successfully")
+# Metodi disponibili e se vanno eseguiti in background
+METHODS = {
+    "initialize": (initialize, False),
+    "transcribe": (transcribe, True),
+    "cancel": (cancel, False),
+    "start_streaming": (start_streaming, False),
+    "pause_streaming": (pause_streaming, False),
+    "resume_streaming": (resume_streaming, False),
+    "utterance": (utterance, False),
+    "stop_streaming": (stop_streaming, False),
+}
 
-                elif command == "start_streaming":
-                    # Start streaming transcription
-                    audio_path = request.get("audio_path")
-                    model_size = request.get("model_size", "base")
-                    language = request.get("language")
 
-                    logger.info(f"Starting streaming transcription: {audio_path}")
-                    start_streaming_transcription(audio_path, model_size, language)
+def handle_request(request_id, handler, params):
+    """Esegue un metodo e invia la risposta, anche in caso di errore."""
+    try:
+        send_result(request_id, handler(request_id, params))
+    except RpcError as e:
+        send_error(request_id, e.code, e.message)
+    except Exception as e:
+        logger.error(f"Error processing request {request_id}: {e}", exc_info=True)
+        send_error(request_id, SERVER_ERROR, str(e))
 
-                elif command == "stop_streaming":
-                    # Stop streaming transcription
-                    logger.info("Stopping streaming transcription")
-                    stop_streaming_transcription()
 
-                else:
-                    send_response(success=False, error=f"Unknown command: {command}")
+def dispatch(line: str):
+    try:
+        request = json.loads(line)
+    except json.JSONDecodeError as e:
+        logger.error(f"JSON decode error: {e}")
+        send_error(None, PARSE_ERROR, f"Invalid JSON: {str(e)}")
+        return
+
+    request_id = request.get("id") if isinstance(request, dict) else None
+    method = request.get("method") if isinstance(request, dict) else None
+    if request_id is None or not isinstance(method, str):
+        send_error(request_id, INVALID_REQUEST, "Invalid request")
+        return
+
+    if method not in METHODS:
+        send_error(request_id, METHOD_NOT_FOUND, f"Unknown method: {method}")
+        return
+
+    handler, background = METHODS[method]
+    params = request.get("params") or {}
+    if background:
+        workers.submit(handle_request, request_id, handler, params)
+    else:
+        handle_request(request_id, handler, params)
 
-            except json.JSONDecodeError as e:
-                logger.error(f"JSON decode error: {e}")
-                send_response(success=False, error=f"Invalid JSON: {str(e)}")
 
-            except Exception as e:
-                logger.error(f"Error processing request: {e}", exc_info=True)
-                send_response(success=False, error=str(e))
+def main():
+    """Main loop - read requests from stdin until it is closed."""
+    logger.info(f"Transcription sidecar started (protocol {PROTOCOL_VERSION})")
 
+    try:
+        for line in sys.stdin:
+            line = line.strip()
+            if line:
+                dispatch(line)
     except KeyboardInterrupt:
         logger.info("Sidecar interrupted")
     except Exception as e:
         logger.error(f"Fatal error: {e}", exc_info=True)
-        send_response(success=False, error=str(e))
+
+    # stdin chiuso: l'app è terminata, inutile finire le trascrizioni in corso
+    stop_event.set()
+    os._exit(0)
 
 
 if __name__ == "__main__":
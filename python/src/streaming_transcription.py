@@ -3,20 +3,27 @@ Modulo per la trascrizione in streaming mentre avviene la registrazione.
 """
 
 import logging
+import queue
 import threading
 import time
 import wave
 from pathlib import Path
-from typing import Callable, Dict, List, Optional
+from typing import Callable, Dict, List, Optional, Tuple
+import numpy as np
 import whisper
 
+try:
+    from transcription import get_model, inference_lock
+except ImportError:
+    from .transcription import get_model, inference_lock
+
 logger = logging.getLogger(__name__)
 
 
 class StreamingTranscriber:
     """
-    Trascrittore che monitora un file audio in crescita e trascrive
-    in tempo reale man mano che arrivano nuovi dati.
+    Trascrittore che monitora un file audio in crescita e trascrive in
+    tempo reale gli enunciati conclusi segnalati dalla registrazione.
     """
 
     def __init__(
@@ -29,14 +36,14 @@ class StreamingTranscriber:
         self.device = device
         self.language = language
         self.model: Optional[whisper.Whisper] = None
-        self.last_transcribed_position = 0
+        self.utterances: "queue.Queue[Tuple[float, float, Optional[str], float]]" = (
+            queue.Queue()
+        )
 
     def load_model(self):
-        """Carica il modello Whisper"""
+        """Carica il modello Whisper, condiviso con la trascrizione batch"""
         if self.model is None:
-            logger.info(f"Loading Whisper model: {self.model_size}")
-            self.model = whisper.load_model(self.model_size, device=self.device)
-            logger.info("Model loaded successfully")
+            self.model = get_model(self.model_size, self.device)
 
     def get_audio_duration(self, audio_path: Path) -> float:
         """Ottiene la durata corrente del file audio in secondi"""
@@ -50,9 +57,42 @@ class StreamingTranscriber:
             logger.error(f"Error getting audio duration: {e}")
             return 0.0
 
-    def transcribe_chunk(self, audio_path: Path, start_time: float = 0.0) -> List[Dict]:
+    def queue_utterance(
+        self,
+        start_seconds: float,
+        end_seconds: float,
+        audio_path: Optional[str] = None,
+        segment_start: float = 0.0,
+    ):
+        """
+        Accoda un enunciato concluso, rilevato dal VAD della registrazione.
+        Le registrazioni lunghe sono divise in segmenti: `audio_path` è il
+        file che contiene l'enunciato e `segment_start` il suo inizio.
         """
-        Trascrive un chunk del file audio a partire da start_time.
+        self.utterances.put((start_seconds, end_seconds, audio_path, segment_start))
+
+    def read_span(self, audio_path: Path, start: float, end: float) -> np.ndarray:
+        """Legge un intervallo del WAV come float32 mono"""
+        with wave.open(str(audio_path), "rb") as wav_file:
+            rate = wav_file.getframerate()
+            channels = wav_file.getnchannels()
+            first = int(start * rate)
+            wav_file.setpos(min(first, wav_file.getnframes()))
+            frames = wav_file.readframes(max(int(end * rate) - first, 0))
+
+        samples = np.frombuffer(frames, dtype=np.int16).astype(np.float32) / 32768.0
+        if channels > 1:
+            samples = samples[: len(samples) // channels * channels]
+            samples = samples.reshape(-1, channels).mean(axis=1)
+        return samples
+
+    def transcribe_utterance(
+        self, audio_path: Path, start: float, end: float, segment_start: float = 0.0
+    ) -> List[Dict]:
+        """
+        Trascrive solo l'enunciato [start, end] dal segmento `audio_path`,
+        iniziato a `segment_start`: i timestamp restituiti sono relativi
+        all'inizio della registrazione.
         """
         self.load_model()
 
@@ -64,20 +104,25 @@ class StreamingTranscriber:
             if self.language:
                 transcribe_params["language"] = self.language
 
-            # Trascrive il file completo
-            result = self.model.transcribe(str(audio_path), **transcribe_params)
+            audio = self.read_span(audio_path, start - segment_start, end - segment_start)
+            if len(audio) == 0:
+                return segments_list
+
+            with inference_lock(self.model_size, self.device):
+                result = self.model.transcribe(audio, **transcribe_params)
 
-            # Filtra solo i segmenti dopo start_time
             for segment in result.get("segments", []):
-                if segment["start"] >= start_time:
-                    segments_list.append({
-                        "start": segment["start"],
-                        "end": segment["end"],
-                        "text": segment["text"].strip(),
-                    })
+                text = segment["text"].strip()
+                if not text:
+                    continue
+                segments_list.append({
+                    "start": start + segment["start"],
+                    "end": min(start + segment["end"], end),
+                    "text": text,
+                })
 
         except Exception as e:
-            logger.error(f"Error transcribing chunk: {e}")
+            logger.error(f"Error transcribing utterance: {e}")
 
         return segments_list
 
@@ -86,53 +131,48 @@ class StreamingTranscriber:
         audio_path: Path,
         callback: Callable[[List[Dict]], None],
         stop_event: threading.Event,
-        check_interval: float = 5.0,
-        min_chunk_duration: float = 10.0,
+        pause_event: Optional[threading.Event] = None,
+        check_interval: float = 0.5,
     ):
         """
-        Monitora il file audio e trascrive incrementalmente.
+        Trascrive gli enunciati segnalati dal VAD man mano che arrivano.
+        Il silenzio non viene mai inviato al modello.
 
         Args:
             audio_path: Percorso del file audio in registrazione
             callback: Funzione chiamata con i nuovi segmenti trascritti
             stop_event: Event per terminare il monitoraggio
+            pause_event: Event impostato mentre la registrazione è in pausa
             check_interval: Intervallo in secondi tra i controlli
-            min_chunk_duration: Durata minima del chunk per avviare trascrizione
         """
-        self.last_transcribed_position = 0.0
-
         logger.info(f"Starting streaming transcription of {audio_path}")
 
+        pending: Optional[Tuple[float, float, Optional[str], float]] = None
+
         while not stop_event.is_set():
-            # Verifica se il file esiste
-            if not audio_path.exists():
+            if pending is None:
+                try:
+                    pending = self.utterances.get(timeout=check_interval)
+                except queue.Empty:
+                    continue
+
+            start, end, segment_path, segment_start = pending
+            source = Path(segment_path) if segment_path else audio_path
+
+            # L'header del WAV viene aggiornato periodicamente: si attende
+            # che l'enunciato sia interamente su disco. In pausa il file non
+            # cresce, quindi l'attesa si allunga senza costi.
+            paused = pause_event is not None and pause_event.is_set()
+            if paused or segment_start + self.get_audio_duration(source) < end:
                 time.sleep(check_interval)
                 continue
 
-            # Ottieni durata corrente
-            current_duration = self.get_audio_duration(audio_path)
-
-            # Calcola quanto audio nuovo è disponibile
-            new_audio_duration = current_duration - self.last_transcribed_position
-
-            if new_audio_duration >= min_chunk_duration:
-                logger.info(
-                    f"Transcribing chunk from {self.last_transcribed_position}s to {current_duration}s"
-                )
-
-                # Trascrivi il nuovo chunk
-                new_segments = self.transcribe_chunk(
-                    audio_path, start_time=self.last_transcribed_position
-                )
-
-                if new_segments:
-                    # Invia i nuovi segmenti tramite callback
-                    callback(new_segments)
-
-                    # Aggiorna la posizione
-                    self.last_transcribed_position = current_duration
-                    logger.info(f"Transcribed {len(new_segments)} new segments")
+            logger.info(f"Transcribing utterance from {start:.1f}s to {end:.1f}s")
+            new_segments = self.transcribe_utterance(source, start, end, segment_start)
+            pending = None
 
-            time.sleep(check_interval)
+            if new_segments:
+                callback(new_segments)
+                logger.info(f"Transcribed {len(new_segments)} new segments")
 
         logger.info("Streaming transcription stopped")
@@ -3,14 +3,23 @@ Modulo per la trascrizione audio usando openai-whisper.
 """
 
 import logging
+import threading
+import time
 from pathlib import Path
-from typing import Dict, List, Optional
+from typing import Callable, Dict, List, Optional
+
 import whisper
 
 logger = logging.getLogger(__name__)
 
-# Cache dei modelli caricati
+# Cache dei modelli caricati: il sidecar resta attivo, quindi restano in
+# memoria fra una richiesta e l'altra
 _models_cache: Dict[str, whisper.Whisper] = {}
+_models_lock = threading.Lock()
+
+# Un modello non può trascrivere due audio contemporaneamente (Whisper
+# installa hook sul modello durante la decodifica)
+_inference_locks: Dict[str, threading.Lock] = {}
 
 
 def get_model(model_size: str = "base", device: str = "cpu") -> whisper.Whisper:
@@ -27,13 +36,71 @@ def get_model(model_size: str = "base", device: str = "cpu") -> whisper.Whisper:
     """
     cache_key = f"{model_size}_{device}"
 
-    if cache_key not in _models_cache:
-        logger.info(f"Loading Whisper model: {model_size} on {device}")
-        model = whisper.load_model(model_size, device=device)
-        _models_cache[cache_key] = model
-        logger.info(f"Model {model_size} loaded successfully")
+    with _models_lock:
+        if cache_key not in _models_cache:
+            logger.info(f"Loading Whisper model: {model_size} on {device}")
+            model = whisper.load_model(model_size, device=device)
+            _models_cache[cache_key] = model
+            _inference_locks[cache_key] = threading.Lock()
+            logger.info(f"Model {model_size} loaded successfully")
+
+        return _models_cache[cache_key]
+
+
+def inference_lock(model_size: str = "base", device: str = "cpu") -> threading.Lock:
+    """Lock da tenere mentre si usa il modello restituito da get_model."""
+    get_model(model_size, device)
+    return _inference_locks[f"{model_size}_{device}"]
+
+
+class TranscriptionCancelled(Exception):
+    """La trascrizione è stata annullata prima della fine"""
+
 
-    return _models_cache[cache_key]
+# Durata massima di ogni blocco trascritto con una chiamata a Whisper: fra
+# un blocco e l'altro si riporta l'avanzamento e si controlla l'annullamento
+CHUNK_SECONDS = 60.0
+
+
+def _chunks(total_seconds: float, speech_segments: Optional[List[Dict]]) -> List[Dict]:
+    """
+    Divide l'audio in blocchi consecutivi di al più CHUNK_SECONDS.
+    Con le regioni di parlato i blocchi raggruppano regioni vicine e
+    conservano i loro intervalli ("clips"), così il silenzio resta escluso.
+    """
+    if speech_segments:
+        spans = [
+            (max(0.0, float(s["start_seconds"])), min(float(s["end_seconds"]), total_seconds))
+            for s in speech_segments
+        ]
+    else:
+        spans = [(0.0, total_seconds)]
+
+    chunks = []
+    for span_start, span_end in spans:
+        start = span_start
+        while start < span_end:
+            end = min(start + CHUNK_SECONDS, span_end)
+            clip = (start, end)
+            if speech_segments and chunks and end - chunks[-1]["start"] <= CHUNK_SECONDS:
+                chunks[-1]["end"] = end
+                chunks[-1]["clips"].append(clip)
+            else:
+                chunks.append({
+                    "start": start,
+                    "end": end,
+                    "clips": [clip] if speech_segments else None,
+                })
+            start = end
+    return chunks
+
+
+def _convert_segment(segment: Dict) -> Dict:
+    return {
+        "start": segment["start"],
+        "end": segment["end"],
+        "text": segment["text"].strip(),
+    }
 
 
 def transcribe_audio(
@@ -41,6 +108,9 @@ def transcribe_audio(
     model_size: str = "base",
     language: Optional[str] = None,
     device: str = "cpu",
+    speech_segments: Optional[List[Dict]] = None,
+    on_progress: Optional[Callable[[Dict], None]] = None,
+    cancel_event: Optional[threading.Event] = None,
 ) -> Dict:
     """
     Trascrizione di un file audio.
@@ -50,6 +120,16 @@ def transcribe_audio(
         model_size: Dimensione del modello Whisper
         language: Lingua del audio (es. 'it', 'en'), None per auto-detect
         device: Device da usare
+        speech_segments: Regioni di parlato rilevate dal VAD
+            ({"start_seconds", "end_seconds"}); se presenti viene trascritto
+            solo il parlato, None per trascrivere tutto il file
+        on_progress: Chiamata dopo ogni blocco (al più CHUNK_SECONDS) con
+            percent, processed_seconds, total_seconds, eta_seconds e i
+            segments trascritti dall'aggiornamento precedente. Ogni
+            richiesta passa la propria callback, così l'avanzamento di
+            trascrizioni in parallelo non si mescola
+        cancel_event: Se impostato la trascrizione si interrompe con
+            TranscriptionCancelled alla fine del blocco in corso
 
     Returns:
         Dizionario con:
@@ -62,30 +142,98 @@ def transcribe_audio(
     if not audio_file.exists():
         raise FileNotFoundError(f"Audio file not found: {audio_path}")
 
+    # Registrazione di solo silenzio: niente da trascrivere (e niente
+    # testo inventato dal modello)
+    if speech_segments is not None and not speech_segments:
+        logger.info(f"No speech detected in {audio_path}, skipping transcription")
+        return {"text": "", "language": language, "segments": []}
+
     # Ottieni il modello
     model = get_model(model_size, device)
+    lock = inference_lock(model_size, device)
 
     logger.info(f"Starting transcription of {audio_path}")
 
-    # Esegui la trascrizione
-    transcribe_params = {}
-    if language:
-        transcribe_params["language"] = language
-
-    result_raw = model.transcribe(str(audio_file), **transcribe_params)
-
-    # Converti i segmenti
-    segments = []
-    for segment in result_raw.get("segments", []):
-        segments.append({
-            "start": segment["start"],
-            "end": segment["end"],
-            "text": segment["text"].strip()
+    audio = whisper.load_audio(str(audio_file))
+    sample_rate = whisper.audio.SAMPLE_RATE
+    total_seconds = len(audio) / sample_rate
+    chunks = _chunks(total_seconds, speech_segments)
+    if speech_segments:
+        logger.info(
+            f"Transcribing {len(speech_segments)} speech segments in {len(chunks)} chunks"
+        )
+
+    started = time.monotonic()
+    segments: List[Dict] = []
+    texts: List[str] = []
+    detected_language = language
+
+    # Ogni blocco è una chiamata a Whisper sul proprio tratto di audio: i
+    # timestamp restituiti partono dall'inizio del blocco
+    for chunk in chunks:
+        if cancel_event is not None and cancel_event.is_set():
+            raise TranscriptionCancelled()
+
+        first_sample = int(chunk["start"] * sample_rate)
+        last_sample = int(chunk["end"] * sample_rate)
+        transcribe_params = {}
+        if detected_language:
+            transcribe_params["language"] = detected_language
+        if chunk["clips"]:
+            # Whisper salta tutto ciò che è fuori dagli intervalli
+            transcribe_params["clip_timestamps"] = [
+                seconds - chunk["start"] for clip in chunk["clips"] for seconds in clip
+            ]
+        if texts:
+            # Continuità con il blocco precedente (nomi, punteggiatura)
+            transcribe_params["initial_prompt"] = texts[-1]
+
+        # Il lock è tenuto per un blocco alla volta: fra un blocco e
+        # l'altro può passare la trascrizione in tempo reale
+        with lock:
+            result_raw = model.transcribe(
+                audio[first_sample:last_sample], **transcribe_params
+            )
+
+        detected_language = detected_language or result_raw.get("language")
+        text = result_raw.get("text", "").strip()
+        if text:
+            texts.append(text)
+
+        new_segments = []
+        for segment in result_raw.get("segments", []):
+            converted = _convert_segment(segment)
+            converted["start"] += chunk["start"]
+            converted["end"] += chunk["start"]
+            new_segments.append(converted)
+        segments.extend(new_segments)
+
+        if on_progress:
+            processed = chunk["end"]
+            elapsed = time.monotonic() - started
+            on_progress({
+                "percent": 100.0 * processed / total_seconds if total_seconds else 100.0,
+                "processed_seconds": processed,
+                "total_seconds": total_seconds,
+                "eta_seconds": (
+                    elapsed * (total_seconds - processed) / processed if processed else None
+                ),
+                "segments": new_segments,
+            })
+
+    # Con le regioni di parlato l'ultimo blocco può finire prima del file
+    if on_progress and (not chunks or chunks[-1]["end"] < total_seconds):
+        on_progress({
+            "percent": 100.0,
+            "processed_seconds": total_seconds,
+            "total_seconds": total_seconds,
+            "eta_seconds": 0.0,
+            "segments": [],
         })
 
     result = {
-        "text": result_raw["text"].strip(),
-        "language": result_raw.get("language", language),
+        "text": " ".join(texts),
+        "language": detected_language,
         "segments": segments,
     }
 
@@ -1,209 +1,1284 @@
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use cpal::{Sample, SampleFormat};
-use hound::{WavSpec, WavWriter};
-use std::path::PathBuf;
+use super::devices::InputConfig;
+use super::enhance::{EnhancementSettings, Enhancer};
+use super::events::{EventDispatcher, RecorderEvent, RecorderEventHandler};
+use super::input::{open_stream, resolve_input, InputSource, InputStream, StreamShared};
+use super::mixer::{Mixer, RecordingLayout};
+use super::preroll::{PreRollBuffer, MAX_PRE_ROLL_SECONDS};
+use super::quality::{QualityAnalyzer, QualityReport, QualityWarning};
+use super::resample::{downmix_to_mono, to_i16, TARGET_SAMPLE_RATE};
+use super::segments::SegmentedWavWriter;
+use super::vad::{SilenceMap, SpeechSegment, VoiceActivityDetector};
+use super::waveform::{SharedWaveform, WaveformPeaks};
+use hound::WavSpec;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::{Arc, Mutex};
-use std::thread;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
+/// Dopo quanto tempo senza dati una sorgente viene considerata persa
+const DEVICE_TIMEOUT: Duration = Duration::from_secs(3);
+/// Intervallo fra i tentativi di riaprire una sorgente persa
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+/// Ogni quanto aggiornare l'header WAV su disco: in caso di crash si perde
+/// al massimo questo intervallo di audio
+const HEADER_FLUSH_INTERVAL: Duration = Duration::from_secs(5);
+/// Intervallo fra due eventi di livello (20 Hz)
+const LEVEL_INTERVAL: Duration = Duration::from_millis(50);
+
 #[derive(Error, Debug)]
 pub enum AudioError {
     #[error("Nessun dispositivo di input trovato")]
     NoInputDevice,
+    #[error("Dispositivo non trovato: {0}")]
+    DeviceNotFound(String),
     #[error("Errore dispositivo: {0}")]
     DeviceError(String),
     #[error("Errore stream: {0}")]
     StreamError(String),
     #[error("Errore file: {0}")]
     FileError(String),
+    #[error("Errore decodifica: {0}")]
+    DecodeError(String),
+}
+
+/// Opzioni di registrazione derivate dalle impostazioni dell'app
+#[derive(Debug, Clone, Default)]
+pub struct RecordingOptions {
+    /// Conserva una copia alla qualità originale di ogni dispositivo
+    pub keep_master_copy: bool,
+    /// Mix mono o una traccia per dispositivo
+    pub layout: RecordingLayout,
+    /// Frequenza e canali scelti dall'utente, per ID dispositivo
+    pub stream_configs: HashMap<String, InputConfig>,
+    /// Silenzio dopo il quale viene segnalato `SilenceTimeout`
+    pub auto_stop_silence: Option<Duration>,
+    /// Filtri applicati all'audio prima della scrittura
+    pub enhancement: EnhancementSettings,
+}
+
+/// Percorso della copia master della traccia `track` di una registrazione
+pub fn master_copy_path(output_path: &Path, track: usize) -> PathBuf {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    if track == 0 {
+        output_path.with_file_name(format!("{}_master.wav", stem))
+    } else {
+        output_path.with_file_name(format!("{}_master_{}.wav", stem, track + 1))
+    }
+}
+
+/// Indica se `path` è una copia master di `output_path` (o un suo segmento)
+pub fn is_master_copy(output_path: &Path, path: &Path) -> bool {
+    let stem = output_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    path.file_name()
+        .map(|name| {
+            name.to_string_lossy()
+                .starts_with(&format!("{}_master", stem))
+        })
+        .unwrap_or(false)
+}
+
+/// File generati accanto a una registrazione (copie master, ecc.)
+pub fn companion_files(output_path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(stem)) = (output_path.parent(), output_path.file_stem()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}_", stem.to_string_lossy());
+
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.file_name()
+                        .map(|n| n.to_string_lossy().starts_with(&prefix))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Riceve i buffer di tutte le sorgenti, li allinea tramite il `Mixer` e
+/// li scrive a 16 kHz; le copie master conservano il PCM originale. Tutti i
+/// file passano a un nuovo segmento prima del limite dei 4 GB. Il PCM di
+/// ogni traccia passa dalla diagnostica di qualità; il mix passa dalla
+/// catena di miglioramento (se attiva) e poi dal VAD, che ne segna le
+/// regioni di parlato, e dal calcolo dei picchi della forma d'onda.
+struct CaptureWriter {
+    writer: SegmentedWavWriter,
+    masters: Vec<Option<SegmentedWavWriter>>,
+    mixer: Mixer,
+    mixed: Vec<f32>,
+    enhancer: Option<Enhancer>,
+    vad: VoiceActivityDetector,
+    mono: Vec<f32>,
+    /// Enunciati conclusi non ancora segnalati
+    utterances: Vec<SpeechSegment>,
+    waveform: SharedWaveform,
+    quality: Vec<QualityAnalyzer>,
+    /// Avvisi di qualità non ancora segnalati, con la traccia
+    quality_warnings: Vec<(usize, QualityWarning)>,
+    new_warnings: Vec<QualityWarning>,
+}
+
+impl CaptureWriter {
+    fn create(
+        output_path: &Path,
+        configs: &[cpal::StreamConfig],
+        options: &RecordingOptions,
+    ) -> Result<Self, AudioError> {
+        let inputs: Vec<(u32, u16)> = configs
+            .iter()
+            .map(|c| (c.sample_rate.0, c.channels))
+            .collect();
+        let mixer = Mixer::new(&inputs, options.layout);
+
+        let spec = WavSpec {
+            channels: mixer.output_channels(),
+            sample_rate: TARGET_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let writer = SegmentedWavWriter::create(output_path, spec)
+            .map_err(|e| AudioError::FileError(e.to_string()))?;
+
+        let mut masters = Vec::with_capacity(configs.len());
+        for (track, config) in configs.iter().enumerate() {
+            if !options.keep_master_copy {
+                masters.push(None);
+                continue;
+            }
+
+            let master_spec = WavSpec {
+                channels: config.channels,
+                sample_rate: config.sample_rate.0,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let master =
+                SegmentedWavWriter::create(&master_copy_path(output_path, track), master_spec)
+                    .map_err(|e| AudioError::FileError(e.to_string()))?;
+            masters.push(Some(master));
+        }
+
+        let enhancer = options.enhancement.is_enabled().then(|| {
+            Enhancer::new(
+                TARGET_SAMPLE_RATE,
+                mixer.output_channels() as usize,
+                &options.enhancement,
+            )
+        });
+
+        let quality = configs
+            .iter()
+            .map(|c| QualityAnalyzer::new(c.sample_rate.0, c.channels))
+            .collect();
+
+        Ok(Self {
+            writer,
+            masters,
+            mixer,
+            mixed: Vec::new(),
+            enhancer,
+            vad: VoiceActivityDetector::new(TARGET_SAMPLE_RATE),
+            mono: Vec::new(),
+            utterances: Vec::new(),
+            waveform: Arc::new(Mutex::new(WaveformPeaks::new(TARGET_SAMPLE_RATE))),
+            quality,
+            quality_warnings: Vec::new(),
+            new_warnings: Vec::new(),
+        })
+    }
+
+    /// Secondi di audio già scritti nel file principale
+    fn position_seconds(&self) -> f64 {
+        self.writer.duration() as f64 / TARGET_SAMPLE_RATE as f64
+    }
+
+    /// Collega la traccia a un nuovo dispositivo. La copia master continua
+    /// solo se il formato del nuovo dispositivo è compatibile.
+    fn replace_source(&mut self, track: usize, source: &InputSource) {
+        self.mixer
+            .replace_source(track, source.config.sample_rate.0, source.config.channels);
+        if let Some(analyzer) = self.quality.get_mut(track) {
+            analyzer.set_format(source.config.sample_rate.0, source.config.channels);
+        }
+
+        if let Some(slot) = self.masters.get_mut(track) {
+            let compatible = slot.as_ref().map(|m| {
+                let spec = m.spec();
+                spec.channels == source.config.channels
+                    && spec.sample_rate == source.config.sample_rate.0
+            });
+
+            if compatible == Some(false) {
+                if let Some(master) = slot.take() {
+                    let _ = master.finalize();
+                }
+                eprintln!(
+                    "Copia master della traccia {} interrotta: formato del dispositivo cambiato",
+                    track
+                );
+            }
+        }
+    }
+
+    fn insert_gap(&mut self, track: usize, seconds: f64) {
+        self.mixer.insert_gap(track, seconds);
+    }
+
+    fn push(&mut self, track: usize, data: &[f32]) {
+        if let Some(Some(master)) = self.masters.get_mut(track) {
+            for &sample in data {
+                let _ = master.write_sample(to_i16(sample));
+            }
+        }
+
+        if let Some(analyzer) = self.quality.get_mut(track) {
+            analyzer.process(data, &mut self.new_warnings);
+            self.quality_warnings
+                .extend(self.new_warnings.drain(..).map(|w| (track, w)));
+        }
+
+        self.mixer.push(track, data);
+    }
+
+    fn write_mixed(&mut self) {
+        self.mixed.clear();
+        self.mixer.mix(&mut self.mixed);
+        if let Some(enhancer) = self.enhancer.as_mut() {
+            enhancer.process(&mut self.mixed);
+        }
+        for &sample in &self.mixed {
+            let _ = self.writer.write_sample(to_i16(sample));
+        }
+        self.analyze_block();
+    }
+
+    /// Passa al VAD e ai picchi l'ultimo blocco scritto, ridotto a mono
+    fn analyze_block(&mut self) {
+        self.mono.clear();
+        downmix_to_mono(
+            &self.mixed,
+            self.mixer.output_channels() as usize,
+            &mut self.mono,
+        );
+        self.vad.process(&self.mono, &mut self.utterances);
+        if let Ok(mut waveform) = self.waveform.lock() {
+            waveform.push(&self.mono);
+        }
+    }
+
+    /// Riepilogo della qualità di ogni traccia, a fine registrazione
+    fn quality_report(&mut self, device_ids: &[String]) -> QualityReport {
+        let tracks = self
+            .quality
+            .iter_mut()
+            .enumerate()
+            .map(|(track, analyzer)| analyzer.finish(track, device_ids.get(track).cloned()))
+            .collect();
+        QualityReport { tracks }
+    }
+
+    /// Secondi di registrazione senza parlato
+    fn silence_seconds(&self) -> f64 {
+        self.vad.silence_seconds()
+    }
+
+    /// Aggiorna gli header e svuota i buffer, così il file resta leggibile
+    /// anche se l'app si chiude senza passare da `finalize`
+    fn flush(&mut self) -> Result<(), hound::Error> {
+        for master in self.masters.iter_mut().flatten() {
+            master.flush()?;
+        }
+        self.writer.flush()
+    }
+
+    /// Chiude i file e restituisce la mappa del silenzio dell'intera
+    /// registrazione
+    fn finalize(mut self) -> Result<SilenceMap, hound::Error> {
+        self.mixed.clear();
+        self.mixer.flush(&mut self.mixed);
+        if let Some(enhancer) = self.enhancer.as_mut() {
+            enhancer.process(&mut self.mixed);
+            let mut tail = Vec::new();
+            enhancer.flush(&mut tail);
+            self.mixed.extend_from_slice(&tail);
+        }
+        for &sample in &self.mixed {
+            self.writer.write_sample(to_i16(sample))?;
+        }
+        self.analyze_block();
+        let silence_map = self.vad.finish(&mut self.utterances);
+        if let Ok(mut waveform) = self.waveform.lock() {
+            waveform.finish();
+        }
+
+        for master in self.masters.into_iter().flatten() {
+            master.finalize()?;
+        }
+        self.writer.finalize()?;
+        Ok(silence_map)
+    }
+}
+
+/// Pausa in corso della registrazione
+struct Pause {
+    since: Instant,
+}
+
+/// Interruzione in corso di una sorgente
+struct Outage {
+    since: Instant,
+    last_attempt: Instant,
+    /// Ricerca di un dispositivo sostitutivo in corso (`find_replacement`)
+    lookup: Option<mpsc::Receiver<Vec<InputSource>>>,
+}
+
+/// Stato di una traccia durante la registrazione
+struct TrackState {
+    source: InputSource,
+    stream: Option<InputStream>,
+    /// Lato di lettura del ring buffer riempito dalla callback
+    consumer: Option<rtrb::Consumer<f32>>,
+    shared: StreamShared,
+    last_data: Instant,
+    outage: Option<Outage>,
+}
+
+impl TrackState {
+    fn new(source: InputSource, is_recording: Arc<AtomicBool>) -> Self {
+        Self {
+            source,
+            stream: None,
+            consumer: None,
+            shared: StreamShared::new(is_recording),
+            last_data: Instant::now(),
+            outage: None,
+        }
+    }
+}
+
+/// Apre gli stream di tutte le tracce
+fn open_tracks(tracks: &mut [TrackState]) -> Result<(), AudioError> {
+    for track in tracks {
+        match open_stream(&track.source, &track.shared) {
+            Ok((stream, consumer)) => {
+                track.stream = Some(stream);
+                track.consumer = Some(consumer);
+            }
+            Err(e) => {
+                eprintln!("Errore creazione stream {}: {}", track.source.id, e);
+                return Err(e);
+            }
+        }
+        track.last_data = Instant::now();
+    }
+    Ok(())
+}
+
+/// Thread di scrittura: possiede gli stream cpal (che non sono `Send`),
+/// svuota i ring buffer riempiti dalle callback, scrive su disco e gestisce
+/// il failover dei dispositivi che spariscono durante la registrazione.
+/// Tutto l'I/O avviene qui, mai sul thread audio real-time.
+struct CaptureSession {
+    tracks: Vec<TrackState>,
+    writer: CaptureWriter,
+    is_recording: Arc<AtomicBool>,
+    is_paused: Arc<AtomicBool>,
+    pause: Option<Pause>,
+    /// Ultima ripresa dopo una pausa: il tempo in pausa non conta come buco
+    resumed_at: Option<Instant>,
+    events: Option<EventDispatcher>,
+    pending: Vec<f32>,
+    last_flush: Instant,
+    last_level: Instant,
+    auto_stop_silence: Option<Duration>,
+    /// Il timeout di silenzio viene segnalato una sola volta
+    silence_timeout_sent: bool,
+    /// Picchi del livello 0 già inviati con `RecorderEvent::Waveform`
+    emitted_peaks: usize,
+}
+
+impl CaptureSession {
+    fn new(
+        tracks: Vec<TrackState>,
+        writer: CaptureWriter,
+        is_recording: Arc<AtomicBool>,
+        is_paused: Arc<AtomicBool>,
+        events: Option<EventDispatcher>,
+        auto_stop_silence: Option<Duration>,
+    ) -> Self {
+        Self {
+            tracks,
+            writer,
+            is_recording,
+            is_paused,
+            pause: None,
+            resumed_at: None,
+            events,
+            pending: Vec::new(),
+            last_flush: Instant::now(),
+            last_level: Instant::now(),
+            auto_stop_silence,
+            silence_timeout_sent: false,
+            emitted_peaks: 0,
+        }
+    }
+
+    /// Apre gli stream e registra fino allo stop. L'esito dell'apertura
+    /// viene inviato su `ready` prima di iniziare a scrivere.
+    fn run(mut self, ready: mpsc::Sender<Result<(), AudioError>>) {
+        println!("Inizializzazione stream audio...");
+
+        if let Err(e) = open_tracks(&mut self.tracks) {
+            let _ = ready.send(Err(e));
+            return;
+        }
+        let _ = ready.send(Ok(()));
+
+        self.record();
+    }
+
+    /// Registra con gli stream già aperti fino allo stop, poi chiude i file
+    fn record(mut self) {
+        while self.is_recording.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(20));
+            self.drain_buffers();
+            self.writer.write_mixed();
+            self.emit_speech();
+            self.emit_quality_warnings();
+            self.update_pause();
+            self.check_devices();
+
+            if self.last_level.elapsed() >= LEVEL_INTERVAL {
+                self.emit_levels();
+                self.emit_waveform();
+                self.last_level = Instant::now();
+            }
+
+            if self.last_flush.elapsed() >= HEADER_FLUSH_INTERVAL {
+                if let Err(e) = self.writer.flush() {
+                    eprintln!("Errore aggiornamento header WAV: {}", e);
+                }
+                self.last_flush = Instant::now();
+            }
+        }
+
+        println!("Chiusura stream...");
+        for track in &mut self.tracks {
+            track.stream = None;
+        }
+        self.drain_buffers();
+
+        let stats = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| track.shared.counters.snapshot(index, &track.source.id))
+            .collect::<Vec<_>>();
+        for track in stats.iter().filter(|t| t.dropped_samples > 0) {
+            eprintln!(
+                "Traccia {}: {} campioni persi in {} overrun",
+                track.track, track.dropped_samples, track.overruns
+            );
+        }
+        self.emit(RecorderEvent::CaptureStats { tracks: stats });
+
+        self.emit_quality_warnings();
+        let device_ids: Vec<String> = self.tracks.iter().map(|t| t.source.id.clone()).collect();
+        let report = self.writer.quality_report(&device_ids);
+        self.emit(RecorderEvent::QualityReport { report });
+
+        match self.writer.finalize() {
+            Ok(silence_map) => {
+                println!(
+                    "File audio finalizzato ({:.0}s di parlato su {:.0}s)",
+                    silence_map.speech_seconds(),
+                    silence_map.total_seconds
+                );
+                // `finalize` consuma il writer: resta solo il gestore eventi
+                if let Some(events) = &self.events {
+                    events.send(RecorderEvent::SpeechAnalysis { silence_map });
+                }
+            }
+            Err(e) => eprintln!("Errore finalizzazione file audio: {}", e),
+        }
+    }
+
+    /// Segnala gli enunciati conclusi e, se richiesto, il silenzio
+    /// prolungato che deve fermare la registrazione
+    fn emit_speech(&mut self) {
+        for segment in std::mem::take(&mut self.writer.utterances) {
+            let (audio_path, segment_start_seconds) =
+                self.writer.writer.segment_at(segment.start_seconds);
+            let event = RecorderEvent::Utterance {
+                start_seconds: segment.start_seconds,
+                end_seconds: segment.end_seconds,
+                audio_path: audio_path.to_path_buf(),
+                segment_start_seconds,
+            };
+            self.emit(event);
+        }
+
+        let Some(limit) = self.auto_stop_silence else {
+            return;
+        };
+        let silence_seconds = self.writer.silence_seconds();
+        if !self.silence_timeout_sent && silence_seconds >= limit.as_secs_f64() {
+            println!("Nessun parlato da {:.0}s: stop automatico", silence_seconds);
+            self.silence_timeout_sent = true;
+            self.emit(RecorderEvent::SilenceTimeout {
+                offset_seconds: self.writer.position_seconds(),
+                silence_seconds,
+            });
+        }
+    }
+
+    /// Segnala subito i problemi di qualità, così un microfono muto si
+    /// nota nel primo minuto e non a fine riunione
+    fn emit_quality_warnings(&mut self) {
+        for (track, warning) in std::mem::take(&mut self.writer.quality_warnings) {
+            eprintln!(
+                "Traccia {}: problema di qualità {:?} a {:.0}s",
+                track, warning.issue, warning.offset_seconds
+            );
+            self.emit(RecorderEvent::QualityWarning { track, warning });
+        }
+    }
+
+    /// In pausa gli stream restano aperti (così la perdita di un dispositivo
+    /// viene comunque rilevata) ma i campioni vengono scartati.
+    fn drain_buffers(&mut self) {
+        let paused = self.pause.is_some();
+
+        for (index, track) in self.tracks.iter_mut().enumerate() {
+            let Some(consumer) = track.consumer.as_mut() else {
+                continue;
+            };
+
+            // Copia in un buffer contiguo: il chunk può essere spezzato in
+            // due slice a metà di un frame
+            if let Ok(chunk) = consumer.read_chunk(consumer.slots()) {
+                let (first, second) = chunk.as_slices();
+                self.pending.extend_from_slice(first);
+                self.pending.extend_from_slice(second);
+                chunk.commit_all();
+            }
+
+            if !self.pending.is_empty() {
+                track.last_data = Instant::now();
+                if !paused {
+                    self.writer.push(index, &self.pending);
+                }
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// Applica le richieste di pausa e ripresa arrivate da `AudioRecorder`
+    fn update_pause(&mut self) {
+        let paused = self.is_paused.load(Ordering::SeqCst);
+        let offset_seconds = self.writer.position_seconds();
+
+        match (paused, self.pause.take()) {
+            (true, None) => {
+                println!("Registrazione in pausa a {:.1}s", offset_seconds);
+                self.pause = Some(Pause {
+                    since: Instant::now(),
+                });
+                self.emit(RecorderEvent::Paused { offset_seconds });
+            }
+            (false, Some(pause)) => {
+                let paused_seconds = pause.since.elapsed().as_secs_f64();
+                println!("Registrazione ripresa dopo {:.1}s di pausa", paused_seconds);
+                self.resumed_at = Some(Instant::now());
+                self.emit(RecorderEvent::Resumed {
+                    offset_seconds,
+                    paused_seconds,
+                });
+            }
+            (_, pause) => self.pause = pause,
+        }
+    }
+
+    /// Rileva i dispositivi persi e prova a riaprirli o a passare al
+    /// dispositivo predefinito, continuando a scrivere nello stesso file.
+    fn check_devices(&mut self) {
+        for index in 0..self.tracks.len() {
+            let offset_seconds = self.writer.position_seconds();
+            let track = &mut self.tracks[index];
+
+            let Some(outage) = track.outage.as_mut() else {
+                let lost = track.shared.device_lost.swap(false, Ordering::SeqCst)
+                    || track.last_data.elapsed() > DEVICE_TIMEOUT;
+
+                if lost {
+                    eprintln!("Dispositivo perso: {}", track.source.id);
+                    track.stream = None;
+                    track.consumer = None;
+                    track.outage = Some(Outage {
+                        since: Instant::now(),
+                        last_attempt: Instant::now(),
+                        lookup: None,
+                    });
+
+                    let event = RecorderEvent::DeviceLost {
+                        track: index,
+                        device_id: track.source.id.clone(),
+                        offset_seconds,
+                    };
+                    self.emit(event);
+                }
+                continue;
+            };
+
+            let candidates = match outage.lookup.as_ref().map(|lookup| lookup.try_recv()) {
+                Some(Ok(candidates)) => {
+                    outage.lookup = None;
+                    candidates
+                }
+                Some(Err(mpsc::TryRecvError::Empty)) => continue,
+                Some(Err(mpsc::TryRecvError::Disconnected)) => {
+                    outage.lookup = None;
+                    continue;
+                }
+                None => {
+                    if outage.last_attempt.elapsed() >= RECONNECT_INTERVAL {
+                        outage.last_attempt = Instant::now();
+                        outage.lookup = Some(find_replacement(&track.source));
+                    }
+                    continue;
+                }
+            };
+
+            // Il tempo trascorso in pausa non va riempito con silenzio
+            let gap_seconds = if self.pause.is_some() {
+                0.0
+            } else {
+                let since = match self.resumed_at {
+                    Some(resumed_at) => outage.since.max(resumed_at),
+                    None => outage.since,
+                };
+                since.elapsed().as_secs_f64()
+            };
+
+            let Some((source, stream, consumer)) = self.reopen(index, candidates) else {
+                continue;
+            };
+
+            self.writer.replace_source(index, &source);
+            self.writer.insert_gap(index, gap_seconds);
+
+            let track = &mut self.tracks[index];
+            let from_device_id = std::mem::replace(&mut track.source, source).id;
+            track.stream = Some(stream);
+            track.consumer = Some(consumer);
+            track.last_data = Instant::now();
+            track.outage = None;
+
+            println!(
+                "Traccia {} passata da {} a {} (buco di {:.1}s)",
+                index, from_device_id, track.source.id, gap_seconds
+            );
+
+            let event = RecorderEvent::DeviceSwitched {
+                track: index,
+                from_device_id,
+                to_device_id: track.source.id.clone(),
+                offset_seconds,
+                gap_seconds,
+            };
+            self.emit(event);
+        }
+    }
+
+    fn emit_levels(&self) {
+        let levels = self
+            .tracks
+            .iter()
+            .enumerate()
+            .map(|(index, track)| track.shared.meter.take(index))
+            .collect();
+        self.emit(RecorderEvent::Level { levels });
+    }
+
+    /// Invia i picchi completati dall'ultimo aggiornamento
+    fn emit_waveform(&mut self) {
+        let (peaks, seconds_per_peak) = match self.writer.waveform.lock() {
+            Ok(waveform) => (
+                waveform.base_peaks(self.emitted_peaks),
+                waveform.seconds_per_peak(),
+            ),
+            Err(_) => return,
+        };
+        if peaks.is_empty() {
+            return;
+        }
+
+        let offset_seconds = self.emitted_peaks as f64 * seconds_per_peak;
+        self.emitted_peaks += peaks.len();
+        self.emit(RecorderEvent::Waveform {
+            offset_seconds,
+            seconds_per_peak,
+            peaks,
+        });
+    }
+
+    /// Apre lo stream del primo dispositivo trovato da `find_replacement`.
+    /// Solo questo passo avviene qui: gli stream cpal non sono `Send`.
+    fn reopen(
+        &self,
+        index: usize,
+        candidates: Vec<InputSource>,
+    ) -> Option<(InputSource, InputStream, rtrb::Consumer<f32>)> {
+        let track = &self.tracks[index];
+
+        for source in candidates {
+            track.shared.device_lost.store(false, Ordering::SeqCst);
+            match open_stream(&source, &track.shared) {
+                Ok((stream, consumer)) => return Some((source, stream, consumer)),
+                Err(e) => eprintln!("Impossibile riaprire {}: {}", source.id, e),
+            }
+        }
+
+        None
+    }
+
+    fn emit(&self, event: RecorderEvent) {
+        if let Some(events) = &self.events {
+            events.send(event);
+        }
+    }
+}
+
+/// Cerca su un thread a parte i dispositivi con cui riaprire una traccia
+/// persa: prima lo stesso (se è stato ricollegato), poi quello predefinito
+/// del sistema. L'enumerazione dei dispositivi (e `pactl` per le sorgenti
+/// monitor) può durare centinaia di millisecondi, durante i quali il thread
+/// di scrittura deve continuare a svuotare i ring buffer delle altre tracce.
+fn find_replacement(source: &InputSource) -> mpsc::Receiver<Vec<InputSource>> {
+    let device_id = source.id.clone();
+    let requested_config = source.requested_config;
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        let host = cpal::default_host();
+        let candidates = [(Some(device_id.as_str()), requested_config), (None, None)]
+            .into_iter()
+            .filter_map(|(device_id, config)| resolve_input(&host, device_id, config).ok())
+            .collect();
+        let _ = sender.send(candidates);
+    });
+
+    receiver
+}
+
+/// Richiesta di avvio inviata alla cattura armata
+struct StartRequest {
+    writer: CaptureWriter,
+    is_paused: Arc<AtomicBool>,
+    auto_stop_silence: Option<Duration>,
+}
+
+/// Cattura armata: gli stream sono già aperti e gli ultimi secondi di ogni
+/// sorgente restano in memoria. Allo start lo stesso thread scrive il
+/// pre-roll e prosegue come `CaptureSession` con gli stessi stream, così
+/// fra il pre-roll e il resto della registrazione non manca nessun campione.
+struct ArmedSession {
+    tracks: Vec<TrackState>,
+    buffers: Vec<PreRollBuffer>,
+    is_active: Arc<AtomicBool>,
+    start: mpsc::Receiver<StartRequest>,
+    events: Option<EventDispatcher>,
+    pending: Vec<f32>,
+}
+
+impl ArmedSession {
+    fn run(mut self, ready: mpsc::Sender<Result<(), AudioError>>) {
+        if let Err(e) = open_tracks(&mut self.tracks) {
+            let _ = ready.send(Err(e));
+            return;
+        }
+        let _ = ready.send(Ok(()));
+
+        let mut last_level = Instant::now();
+        while self.is_active.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(20));
+            self.drain_buffers();
+
+            if let Ok(request) = self.start.try_recv() {
+                self.start_recording(request);
+                return;
+            }
+
+            // Il VU meter resta attivo, così si vede che i dispositivi
+            // stanno già ascoltando
+            if last_level.elapsed() >= LEVEL_INTERVAL {
+                let levels = self
+                    .tracks
+                    .iter()
+                    .enumerate()
+                    .map(|(index, track)| track.shared.meter.take(index))
+                    .collect();
+                if let Some(events) = &self.events {
+                    events.send(RecorderEvent::Level { levels });
+                }
+                last_level = Instant::now();
+            }
+        }
+
+        println!("Registrazione disarmata");
+    }
+
+    fn drain_buffers(&mut self) {
+        for (track, buffer) in self.tracks.iter_mut().zip(&mut self.buffers) {
+            let Some(consumer) = track.consumer.as_mut() else {
+                continue;
+            };
+
+            // Come in `CaptureSession`: i frame vanno aggiunti interi
+            if let Ok(chunk) = consumer.read_chunk(consumer.slots()) {
+                let (first, second) = chunk.as_slices();
+                self.pending.extend_from_slice(first);
+                self.pending.extend_from_slice(second);
+                chunk.commit_all();
+            }
+
+            if !self.pending.is_empty() {
+                track.last_data = Instant::now();
+                buffer.push(&self.pending);
+                self.pending.clear();
+            }
+        }
+    }
+
+    /// Scrive il pre-roll di ogni traccia e continua a registrare
+    fn start_recording(mut self, request: StartRequest) {
+        let mut writer = request.writer;
+        let pre_roll_seconds = self.buffers.first().map(|b| b.seconds()).unwrap_or(0.0);
+        for (index, buffer) in self.buffers.iter_mut().enumerate() {
+            writer.push(index, &buffer.take());
+        }
+        println!(
+            "Registrazione avviata con {:.1}s di pre-roll",
+            pre_roll_seconds
+        );
+
+        CaptureSession::new(
+            self.tracks,
+            writer,
+            self.is_active,
+            request.is_paused,
+            self.events,
+            request.auto_stop_silence,
+        )
+        .record();
+    }
+}
+
+/// Cattura armata in attesa dello start, vista da `AudioRecorder`
+struct ArmedCapture {
+    device_ids: Vec<String>,
+    /// Configurazione richiesta per ciascun dispositivo di `device_ids`
+    requested_configs: Vec<Option<InputConfig>>,
+    /// Formato effettivo di ogni traccia
+    configs: Vec<cpal::StreamConfig>,
+    is_active: Arc<AtomicBool>,
+    start: mpsc::Sender<StartRequest>,
+    worker: JoinHandle<()>,
+}
+
+impl ArmedCapture {
+    /// La registrazione richiesta usa gli stessi dispositivi, con le stesse
+    /// configurazioni
+    fn matches(
+        &self,
+        device_ids: &[String],
+        stream_configs: &HashMap<String, InputConfig>,
+    ) -> bool {
+        self.device_ids == device_ids
+            && self.requested_configs == requested_configs(device_ids, stream_configs)
+    }
+
+    fn stop(self) {
+        self.is_active.store(false, Ordering::SeqCst);
+        let _ = self.worker.join();
+    }
+}
+
+fn requested_configs(
+    device_ids: &[String],
+    stream_configs: &HashMap<String, InputConfig>,
+) -> Vec<Option<InputConfig>> {
+    device_ids
+        .iter()
+        .map(|id| stream_configs.get(id).copied())
+        .collect()
+}
+
+/// Risolve i dispositivi richiesti; senza ID quello di input predefinito
+fn resolve_sources(
+    device_ids: &[String],
+    stream_configs: &HashMap<String, InputConfig>,
+) -> Result<Vec<InputSource>, AudioError> {
+    let host = cpal::default_host();
+
+    if device_ids.is_empty() {
+        return Ok(vec![resolve_input(&host, None, None)?]);
+    }
+    device_ids
+        .iter()
+        .map(|id| resolve_input(&host, Some(id), stream_configs.get(id).copied()))
+        .collect()
+}
+
+/// Elimina i file creati per una registrazione che non è partita
+fn remove_recording_files(output_path: &Path) {
+    for path in std::iter::once(output_path.to_path_buf()).chain(companion_files(output_path)) {
+        let _ = std::fs::remove_file(path);
+    }
 }
 
 pub struct AudioRecorder {
     pub is_recording: Arc<AtomicBool>,
+    pub is_paused: Arc<AtomicBool>,
     pub output_path: Option<PathBuf>,
     stop_signal: Option<Arc<AtomicBool>>,
+    /// Picchi della registrazione in corso
+    waveform: Option<SharedWaveform>,
+    worker: Option<JoinHandle<()>>,
+    events: Option<EventDispatcher>,
+    armed: Option<ArmedCapture>,
 }
 
 impl AudioRecorder {
     pub fn new() -> Self {
         Self {
             is_recording: Arc::new(AtomicBool::new(false)),
+            is_paused: Arc::new(AtomicBool::new(false)),
             output_path: None,
             stop_signal: None,
+            waveform: None,
+            worker: None,
+            events: None,
+            armed: None,
         }
     }
 
-    pub fn start_recording(
+    /// Registra chi riceve gli eventi del thread di registrazione. Il
+    /// gestore gira su un thread proprio, mai su quello di scrittura.
+    pub fn set_event_handler(&mut self, handler: RecorderEventHandler) {
+        self.events = Some(EventDispatcher::spawn(handler));
+    }
+
+    /// Apre i dispositivi senza registrare e tiene in memoria gli ultimi
+    /// `pre_roll` di audio: la prossima registrazione con gli stessi
+    /// dispositivi inizia da lì, così le prime parole non vanno perse.
+    pub fn arm(
         &mut self,
-        output_path: PathBuf,
-        device_id: Option<String>,
+        device_ids: Vec<String>,
+        stream_configs: HashMap<String, InputConfig>,
+        pre_roll: Duration,
     ) -> Result<(), AudioError> {
-        if self.is_recording.load(Ordering::SeqCst) {
+        if self.is_recording() {
             return Err(AudioError::StreamError(
                 "Registrazione già in corso".to_string(),
             ));
         }
+        self.disarm();
 
-        let host = cpal::default_host();
+        let sources = resolve_sources(&device_ids, &stream_configs)?;
+        let seconds = pre_roll.as_secs_f64().min(MAX_PRE_ROLL_SECONDS as f64);
+        let configs: Vec<cpal::StreamConfig> = sources.iter().map(|s| s.config.clone()).collect();
+        let buffers = configs
+            .iter()
+            .map(|c| PreRollBuffer::new(seconds, c.sample_rate.0, c.channels))
+            .collect();
 
-        let device = if let Some(id) = device_id {
-            let idx: usize = id.replace("input_", "").parse().unwrap_or(0);
-            host.input_devices()
-                .map_err(|e| AudioError::DeviceError(e.to_string()))?
-                .nth(idx)
-                .ok_or(AudioError::NoInputDevice)?
-        } else {
-            host.default_input_device()
-                .ok_or(AudioError::NoInputDevice)?
+        let is_active = Arc::new(AtomicBool::new(true));
+        let (start_tx, start_rx) = mpsc::channel();
+        let events = self.events.clone();
+
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let worker = {
+            let is_active = is_active.clone();
+            thread::spawn(move || {
+                // Gli stream cpal non sono `Send`: le tracce nascono qui
+                let tracks = sources
+                    .into_iter()
+                    .map(|source| TrackState::new(source, is_active.clone()))
+                    .collect();
+                ArmedSession {
+                    tracks,
+                    buffers,
+                    is_active,
+                    start: start_rx,
+                    events,
+                    pending: Vec::new(),
+                }
+                .run(ready_tx);
+            })
         };
+        let opened = ready_rx.recv().unwrap_or_else(|_| {
+            Err(AudioError::StreamError(
+                "Thread di registrazione terminato inaspettatamente".to_string(),
+            ))
+        });
+        if let Err(e) = opened {
+            let _ = worker.join();
+            return Err(e);
+        }
 
-        let config = device
-            .default_input_config()
-            .map_err(|e| AudioError::DeviceError(e.to_string()))?;
+        println!("Registrazione armata con {:.0}s di pre-roll", seconds);
+        self.armed = Some(ArmedCapture {
+            requested_configs: requested_configs(&device_ids, &stream_configs),
+            device_ids,
+            configs,
+            is_active,
+            start: start_tx,
+            worker,
+        });
+        Ok(())
+    }
 
-        let sample_format = config.sample_format();
-        let config: cpal::StreamConfig = config.into();
+    /// Chiude i dispositivi aperti da `arm` e scarta il pre-roll
+    pub fn disarm(&mut self) {
+        if let Some(armed) = self.armed.take() {
+            armed.stop();
+        }
+    }
 
-        let spec = WavSpec {
-            channels: config.channels,
-            sample_rate: config.sample_rate.0,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
+    pub fn is_armed(&self) -> bool {
+        self.armed.is_some()
+    }
 
-        let writer = WavWriter::create(&output_path, spec)
-            .map_err(|e| AudioError::FileError(e.to_string()))?;
-        let writer = Arc::new(Mutex::new(Some(writer)));
+    /// Avvia la registrazione da uno o più dispositivi. Senza ID viene
+    /// usato il dispositivo di input predefinito. Se la registrazione era
+    /// armata con gli stessi dispositivi il file inizia con il pre-roll.
+    pub fn start_recording(
+        &mut self,
+        output_path: PathBuf,
+        device_ids: Vec<String>,
+        options: RecordingOptions,
+    ) -> Result<(), AudioError> {
+        if self.is_recording.load(Ordering::SeqCst) {
+            return Err(AudioError::StreamError(
+                "Registrazione già in corso".to_string(),
+            ));
+        }
+
+        if let Some(armed) = self.armed.take() {
+            if armed.matches(&device_ids, &options.stream_configs) {
+                return self.start_armed(armed, output_path, options);
+            }
+            // Dispositivi diversi da quelli armati: si parte senza pre-roll
+            armed.stop();
+        }
+
+        let sources = resolve_sources(&device_ids, &options.stream_configs)?;
+        let configs: Vec<cpal::StreamConfig> = sources.iter().map(|s| s.config.clone()).collect();
+
+        let writer = CaptureWriter::create(&output_path, &configs, &options)?;
+        let waveform = writer.waveform.clone();
 
         let is_recording = Arc::new(AtomicBool::new(true));
         let stop_signal = is_recording.clone();
+        let events = self.events.clone();
+        let auto_stop_silence = options.auto_stop_silence;
 
-        // Clone per ciascuna closure
-        let is_recording_i16 = is_recording.clone();
-        let writer_i16 = writer.clone();
-        let is_recording_f32 = is_recording.clone();
-        let writer_f32 = writer.clone();
-        let is_recording_loop = is_recording.clone();
-        let writer_finalize = writer.clone();
-
-        thread::spawn(move || {
-            let err_fn = |err| {
-                eprintln!("Errore stream audio: {}", err);
-                println!("Errore stream audio: {}", err); // Print to stdout too
-            };
+        self.is_paused.store(false, Ordering::SeqCst);
+        let is_paused = self.is_paused.clone();
 
-            println!("Inizializzazione stream audio...");
-            println!("Formato sample: {:?}", sample_format);
-
-            let stream = match sample_format {
-                SampleFormat::I16 => {
-                    println!("Usando formato I16");
-                    device.build_input_stream(
-                        &config,
-                        move |data: &[i16], _: &_| {
-                            if is_recording_i16.load(Ordering::SeqCst) {
-                                if data.len() > 0 {
-                                    println!("Ricevuti {} samples, primo: {}", data.len(), data[0]);
-                                }
-                                if let Ok(mut guard) = writer_i16.lock() {
-                                    if let Some(ref mut writer) = *guard {
-                                        for &sample in data {
-                                            let _ = writer.write_sample(sample);
-                                        }
-                                    }
-                                }
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                }
-                SampleFormat::F32 => {
-                    println!("Usando formato F32");
-                    device.build_input_stream(
-                        &config,
-                        move |data: &[f32], _: &_| {
-                            if is_recording_f32.load(Ordering::SeqCst) {
-                                if data.len() > 0 {
-                                    println!("Ricevuti {} samples, primo: {}", data.len(), data[0]);
-                                }
-                                if let Ok(mut guard) = writer_f32.lock() {
-                                    if let Some(ref mut writer) = *guard {
-                                        for &sample in data {
-                                            let sample_i16: i16 = Sample::from_sample(sample);
-                                            let _ = writer.write_sample(sample_i16);
-                                        }
-                                    }
-                                }
-                            }
-                        },
-                        err_fn,
-                        None,
-                    )
-                }
-                _ => {
-                    eprintln!("Formato sample non supportato: {:?}", sample_format);
-                    println!("Formato sample non supportato: {:?}", sample_format);
-                    return;
-                }
-            };
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let worker = thread::spawn(move || {
+            let tracks = sources
+                .into_iter()
+                .map(|source| TrackState::new(source, is_recording.clone()))
+                .collect();
+            CaptureSession::new(
+                tracks,
+                writer,
+                is_recording,
+                is_paused,
+                events,
+                auto_stop_silence,
+            )
+            .run(ready_tx);
+        });
 
-            match stream {
-                Ok(stream) => {
-                    println!("Stream creato, avvio in corso...");
-                    if let Err(e) = stream.play() {
-                        eprintln!("Errore avvio stream: {}", e);
-                        println!("Errore avvio stream: {}", e);
-                        return;
-                    }
-                    println!("Stream avviato con successo!");
+        // Gli stream vengono aperti dal thread di registrazione: si attende
+        // l'esito per poter restituire l'errore al chiamante
+        let opened = ready_rx.recv().unwrap_or_else(|_| {
+            Err(AudioError::StreamError(
+                "Thread di registrazione terminato inaspettatamente".to_string(),
+            ))
+        });
+        if let Err(e) = opened {
+            let _ = worker.join();
+            remove_recording_files(&output_path);
+            return Err(e);
+        }
 
-                    while is_recording_loop.load(Ordering::SeqCst) {
-                        thread::sleep(std::time::Duration::from_millis(100));
-                    }
+        self.is_recording.store(true, Ordering::SeqCst);
+        self.output_path = Some(output_path);
+        self.stop_signal = Some(stop_signal);
+        self.waveform = Some(waveform);
+        self.worker = Some(worker);
 
-                    println!("Chiusura stream...");
-                    if let Ok(mut guard) = writer_finalize.lock() {
-                        if let Some(writer) = guard.take() {
-                            let _ = writer.finalize();
-                            println!("File audio finalizzato");
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Errore creazione stream: {}", e);
-                    println!("Errore creazione stream: {}", e);
-                }
+        Ok(())
+    }
+
+    /// Passa la registrazione al thread della cattura armata, che ha già
+    /// gli stream aperti
+    fn start_armed(
+        &mut self,
+        armed: ArmedCapture,
+        output_path: PathBuf,
+        options: RecordingOptions,
+    ) -> Result<(), AudioError> {
+        let writer = match CaptureWriter::create(&output_path, &armed.configs, &options) {
+            Ok(writer) => writer,
+            Err(e) => {
+                self.armed = Some(armed);
+                return Err(e);
             }
-        });
+        };
+        let waveform = writer.waveform.clone();
+
+        self.is_paused.store(false, Ordering::SeqCst);
+        let request = StartRequest {
+            writer,
+            is_paused: self.is_paused.clone(),
+            auto_stop_silence: options.auto_stop_silence,
+        };
+        if armed.start.send(request).is_err() {
+            let _ = armed.worker.join();
+            remove_recording_files(&output_path);
+            return Err(AudioError::StreamError(
+                "Cattura armata interrotta, riprova ad avviare la registrazione".to_string(),
+            ));
+        }
 
         self.is_recording.store(true, Ordering::SeqCst);
         self.output_path = Some(output_path);
-        self.stop_signal = Some(stop_signal);
+        self.stop_signal = Some(armed.is_active);
+        self.waveform = Some(waveform);
+        self.worker = Some(armed.worker);
 
         Ok(())
     }
 
-    pub fn stop_recording(&mut self) -> Result<PathBuf, AudioError> {
+    /// Ferma la registrazione. I file vengono chiusi dal thread di
+    /// scrittura: `StoppingRecording::finish` ne attende la fine, e va
+    /// chiamata dopo aver rilasciato il recorder.
+    pub fn stop_recording(&mut self) -> Result<StoppingRecording, AudioError> {
+        let output_path = self.output_path.take().ok_or(AudioError::FileError(
+            "Nessuna registrazione attiva".to_string(),
+        ))?;
+
         if let Some(stop_signal) = &self.stop_signal {
             stop_signal.store(false, Ordering::SeqCst);
         }
 
         self.is_recording.store(false, Ordering::SeqCst);
+        self.stop_signal = None;
+        self.is_paused.store(false, Ordering::SeqCst);
 
-        thread::sleep(std::time::Duration::from_millis(200));
+        Ok(StoppingRecording {
+            output_path,
+            worker: self.worker.take(),
+            waveform: self.waveform.take(),
+            events: self.events.clone(),
+        })
+    }
 
-        self.stop_signal = None;
+    /// Sospende la scrittura mantenendo aperti file e stream: alla ripresa
+    /// l'audio prosegue nello stesso file
+    pub fn pause_recording(&self) -> Result<(), AudioError> {
+        if !self.is_recording() {
+            return Err(AudioError::StreamError(
+                "Nessuna registrazione attiva".to_string(),
+            ));
+        }
+        if self.is_paused.swap(true, Ordering::SeqCst) {
+            return Err(AudioError::StreamError(
+                "Registrazione già in pausa".to_string(),
+            ));
+        }
+        Ok(())
+    }
 
-        self.output_path.take().ok_or(AudioError::FileError(
-            "Nessuna registrazione attiva".to_string(),
-        ))
+    pub fn resume_recording(&self) -> Result<(), AudioError> {
+        if !self.is_recording() {
+            return Err(AudioError::StreamError(
+                "Nessuna registrazione attiva".to_string(),
+            ));
+        }
+        if !self.is_paused.swap(false, Ordering::SeqCst) {
+            return Err(AudioError::StreamError(
+                "La registrazione non è in pausa".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Picchi in costruzione, per la forma d'onda della registrazione in
+    /// corso
+    pub fn waveform(&self) -> Option<SharedWaveform> {
+        self.waveform.clone()
     }
 
     pub fn is_recording(&self) -> bool {
         self.is_recording.load(Ordering::SeqCst)
     }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused.load(Ordering::SeqCst)
+    }
+}
+
+/// Registrazione fermata il cui thread di scrittura sta chiudendo i file
+pub struct StoppingRecording {
+    output_path: PathBuf,
+    worker: Option<JoinHandle<()>>,
+    waveform: Option<SharedWaveform>,
+    events: Option<EventDispatcher>,
+}
+
+impl StoppingRecording {
+    /// Attende che i file vengano finalizzati e restituisce il percorso
+    /// dell'audio con i picchi completi della registrazione. Anche gli
+    /// eventi di fine cattura (qualità, mappa del silenzio) sono già stati
+    /// gestiti, mentre la riunione è ancora quella corrente.
+    pub fn finish(self) -> (PathBuf, Option<WaveformPeaks>) {
+        if let Some(worker) = self.worker {
+            let _ = worker.join();
+        }
+        if let Some(events) = &self.events {
+            events.flush();
+        }
+
+        let waveform = self
+            .waveform
+            .and_then(|shared| match Arc::try_unwrap(shared) {
+                Ok(waveform) => waveform.into_inner().ok(),
+                // Un comando sta ancora leggendo la forma d'onda live
+                Err(shared) => shared.lock().ok().map(|waveform| waveform.clone()),
+            });
+        (self.output_path, waveform)
+    }
 }
 
 impl Default for AudioRecorder {
@@ -1,5 +1,8 @@
+use super::capture::AudioError;
+use super::input::locate_device;
 use cpal::traits::{DeviceTrait, HostTrait};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioDevice {
@@ -7,6 +10,106 @@ pub struct AudioDevice {
     pub name: String,
     pub is_input: bool,
     pub is_default: bool,
+    pub is_loopback: bool,
+}
+
+/// Configurazione di stream scelta dall'utente per un dispositivo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InputConfig {
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Intervallo di configurazioni supportate da un dispositivo
+#[derive(Debug, Clone, Serialize)]
+pub struct SupportedInputConfig {
+    pub channels: u16,
+    pub min_sample_rate: u32,
+    pub max_sample_rate: u32,
+    pub sample_format: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceCapabilities {
+    pub device_id: String,
+    pub default_config: Option<InputConfig>,
+    pub supported_configs: Vec<SupportedInputConfig>,
+}
+
+/// Prefisso degli ID dei dispositivi di loopback (sorgenti monitor di sistema)
+pub const MONITOR_ID_PREFIX: &str = "monitor:";
+
+/// Elenca i dispositivi di input con un ID stabile.
+///
+/// L'ID è composto da host e nome del dispositivo (`alsa:USB Audio`), così
+/// non cambia quando altri dispositivi vengono collegati o scollegati. Nomi
+/// duplicati ricevono un suffisso progressivo (`alsa:USB Audio#2`).
+pub fn input_devices_with_ids(host: &cpal::Host) -> Vec<(String, cpal::Device)> {
+    let host_name = host.id().name().to_lowercase();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    let mut devices = Vec::new();
+
+    if let Ok(input_devices) = host.input_devices() {
+        for device in input_devices {
+            let Ok(name) = device.name() else {
+                continue;
+            };
+
+            let count = seen.entry(name.clone()).or_insert(0);
+            *count += 1;
+
+            let id = if *count == 1 {
+                format!("{}:{}", host_name, name)
+            } else {
+                format!("{}:{}#{}", host_name, name, count)
+            };
+            devices.push((id, device));
+        }
+    }
+
+    devices
+}
+
+/// Cerca un dispositivo di input tramite il suo ID stabile
+pub fn find_input_device(host: &cpal::Host, id: &str) -> Option<cpal::Device> {
+    input_devices_with_ids(host)
+        .into_iter()
+        .find(|(device_id, _)| device_id == id)
+        .map(|(_, device)| device)
+}
+
+/// Configurazioni di input supportate dal dispositivo `device_id` (quello
+/// predefinito se `None`)
+pub fn device_capabilities(device_id: Option<&str>) -> Result<DeviceCapabilities, AudioError> {
+    let host = cpal::default_host();
+    let located = locate_device(&host, device_id)?;
+
+    let default_config = located
+        .device
+        .default_input_config()
+        .ok()
+        .map(|config| InputConfig {
+            sample_rate: config.sample_rate().0,
+            channels: config.channels(),
+        });
+
+    let supported_configs = located
+        .device
+        .supported_input_configs()
+        .map_err(|e| AudioError::DeviceError(e.to_string()))?
+        .map(|range| SupportedInputConfig {
+            channels: range.channels(),
+            min_sample_rate: range.min_sample_rate().0,
+            max_sample_rate: range.max_sample_rate().0,
+            sample_format: range.sample_format().to_string(),
+        })
+        .collect();
+
+    Ok(DeviceCapabilities {
+        device_id: located.id,
+        default_config,
+        supported_configs,
+    })
 }
 
 pub fn list_audio_devices() -> Vec<AudioDevice> {
@@ -17,24 +120,34 @@ pub fn list_audio_devices() -> Vec<AudioDevice> {
     let default_input_name = host.default_input_device().and_then(|d| d.name().ok());
 
     // List input devices (microphones)
-    if let Ok(input_devices) = host.input_devices() {
-        for (idx, device) in input_devices.enumerate() {
-            if let Ok(name) = device.name() {
-                let is_default = default_input_name.as_ref() == Some(&name);
-                devices.push(AudioDevice {
-                    id: format!("input_{}", idx),
-                    name: name.clone(),
-                    is_input: true,
-                    is_default,
-                });
-            }
+    for (id, device) in input_devices_with_ids(&host) {
+        if let Ok(name) = device.name() {
+            let is_default = default_input_name.as_ref() == Some(&name);
+            devices.push(AudioDevice {
+                id,
+                name,
+                is_input: true,
+                is_default,
+                is_loopback: false,
+            });
         }
     }
 
-    // Note: System audio capture requires platform-specific handling
+    // Linux: sorgenti monitor di PulseAudio/PipeWire
+    #[cfg(target_os = "linux")]
+    for source in super::pulse::list_monitor_sources() {
+        devices.push(AudioDevice {
+            id: format!("{}{}", MONITOR_ID_PREFIX, source.name),
+            name: source.description,
+            is_input: true,
+            is_default: false,
+            is_loopback: true,
+        });
+    }
+
+    // Note: System audio capture on other platforms requires platform-specific handling
     // macOS: ScreenCaptureKit or virtual audio device (BlackHole)
     // Windows: WASAPI loopback
-    // Linux: PulseAudio monitor
 
     devices
 }
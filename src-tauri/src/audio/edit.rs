@@ -0,0 +1,475 @@
+//! Estrazione di clip e oscuramento di passaggi di una registrazione.
+//!
+//! Entrambe le operazioni decodificano il file (WAV, FLAC o Opus) e
+//! riscrivono un WAV a 16 bit con la stessa frequenza e gli stessi canali,
+//! così anche le registrazioni multitraccia restano intatte.
+
+use super::capture::{companion_files, is_master_copy, AudioError};
+use super::import::{decode_file_from, SampleSink};
+use super::resample::to_i16;
+use super::segments::{is_segment_part, recording_parts};
+use super::storage::{compress_recording, StorageCodec};
+use hound::{WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::TAU;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Frequenza del tono che copre i passaggi oscurati
+const BLEEP_HZ: f64 = 1000.0;
+/// Ampiezza del tono (circa -14 dBFS)
+const BLEEP_AMPLITUDE: f64 = 0.2;
+/// Rampa all'inizio e alla fine del tono, per evitare click
+const BLEEP_FADE_SECONDS: f64 = 0.005;
+
+/// Intervallo di tempo, in secondi dall'inizio della registrazione.
+///
+/// È la timeline dell'audio salvato: i segmenti (`recording_parts`) si
+/// susseguono senza spazi e le pause non occupano tempo, perché durante la
+/// pausa i campioni vengono scartati. La trascrizione usa la stessa
+/// timeline, quindi gli intervalli valgono anche per i suoi segmenti.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TimeRange {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+
+impl TimeRange {
+    fn contains(&self, seconds: f64) -> bool {
+        seconds >= self.start_seconds && seconds < self.end_seconds
+    }
+
+    fn overlaps(&self, start_seconds: f64, end_seconds: f64) -> bool {
+        self.start_seconds < end_seconds && self.end_seconds > start_seconds
+    }
+}
+
+/// Come coprire i passaggi oscurati
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RedactionMode {
+    Silence,
+    Bleep,
+}
+
+enum EditOperation {
+    Clip(TimeRange),
+    Redact(Vec<TimeRange>, RedactionMode),
+}
+
+/// Riscrive i campioni decodificati applicando l'operazione richiesta
+struct EditSink {
+    output_path: PathBuf,
+    writer: Option<WavWriter<BufWriter<File>>>,
+    operation: EditOperation,
+    /// Inizio del file in decodifica nella registrazione, in secondi
+    file_offset_seconds: f64,
+    /// Istante del primo frame contato da `position`, in secondi dall'inizio
+    /// della registrazione
+    base_seconds: f64,
+    /// Frame decodificati da `base_seconds`
+    position: u64,
+    sample_rate: u32,
+}
+
+impl EditSink {
+    fn new(output_path: &Path, operation: EditOperation, offset_seconds: f64) -> Self {
+        Self {
+            output_path: output_path.to_path_buf(),
+            writer: None,
+            operation,
+            file_offset_seconds: offset_seconds,
+            base_seconds: offset_seconds,
+            position: 0,
+            sample_rate: 0,
+        }
+    }
+
+    /// Posizione corrente nella registrazione, in secondi
+    fn current_seconds(&self) -> f64 {
+        if self.position == 0 {
+            return self.base_seconds;
+        }
+        self.base_seconds + self.position as f64 / self.sample_rate as f64
+    }
+
+    /// Il file corrente è finito: il prossimo inizia dove si è arrivati
+    fn next_file(&mut self) {
+        self.file_offset_seconds = self.current_seconds();
+        self.base_seconds = self.file_offset_seconds;
+        self.position = 0;
+    }
+
+    /// Secondi da saltare all'inizio del file corrente: le clip non hanno
+    /// bisogno dell'audio che precede l'intervallo
+    fn skip_seconds(&self) -> f64 {
+        match &self.operation {
+            EditOperation::Clip(range) => (range.start_seconds - self.file_offset_seconds).max(0.0),
+            EditOperation::Redact(..) => 0.0,
+        }
+    }
+
+    /// Chiude il file e restituisce la durata scritta, in secondi
+    fn finish(self) -> Result<f64, AudioError> {
+        let writer = match (self.writer, &self.operation) {
+            (Some(writer), _) => writer,
+            // Clip iniziata oltre la fine: nessun campione decodificato
+            (None, EditOperation::Clip(_)) => return Ok(0.0),
+            (None, EditOperation::Redact(..)) => {
+                return Err(AudioError::DecodeError(
+                    "Nessun campione audio nel file".to_string(),
+                ))
+            }
+        };
+        let duration = writer.duration() as f64 / writer.spec().sample_rate as f64;
+        writer
+            .finalize()
+            .map_err(|e| AudioError::FileError(e.to_string()))?;
+        Ok(duration)
+    }
+}
+
+impl SampleSink for EditSink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        let spec = WavSpec {
+            channels: channels as u16,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        match &self.writer {
+            Some(writer) if writer.spec() != spec => {
+                return Err(AudioError::DecodeError(
+                    "Formato audio cambiato durante la decodifica".to_string(),
+                ))
+            }
+            Some(_) => {}
+            None => {
+                let writer = WavWriter::create(&self.output_path, spec)
+                    .map_err(|e| AudioError::FileError(e.to_string()))?;
+                self.writer = Some(writer);
+            }
+        }
+        let Some(writer) = self.writer.as_mut() else {
+            return Ok(());
+        };
+        self.sample_rate = sample_rate;
+
+        for frame in data.chunks_exact(channels.max(1)) {
+            let seconds = self.base_seconds + self.position as f64 / sample_rate as f64;
+            self.position += 1;
+
+            match &self.operation {
+                EditOperation::Clip(range) => {
+                    if !range.contains(seconds) {
+                        continue;
+                    }
+                    for &sample in frame {
+                        writer
+                            .write_sample(to_i16(sample))
+                            .map_err(|e| AudioError::FileError(e.to_string()))?;
+                    }
+                }
+                EditOperation::Redact(ranges, mode) => {
+                    let range = ranges.iter().find(|r| r.contains(seconds));
+                    for &sample in frame {
+                        let sample = match (range, mode) {
+                            (None, _) => sample,
+                            (Some(_), RedactionMode::Silence) => 0.0,
+                            (Some(range), RedactionMode::Bleep) => bleep(range, seconds),
+                        };
+                        writer
+                            .write_sample(to_i16(sample))
+                            .map_err(|e| AudioError::FileError(e.to_string()))?;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn seeked(&mut self, seconds: f64) {
+        self.base_seconds = self.file_offset_seconds + seconds;
+        self.position = 0;
+    }
+
+    fn is_done(&self) -> bool {
+        match &self.operation {
+            EditOperation::Clip(range) => self.current_seconds() >= range.end_seconds,
+            EditOperation::Redact(..) => false,
+        }
+    }
+}
+
+fn bleep(range: &TimeRange, seconds: f64) -> f32 {
+    let fade = ((seconds - range.start_seconds) / BLEEP_FADE_SECONDS)
+        .min((range.end_seconds - seconds) / BLEEP_FADE_SECONDS)
+        .clamp(0.0, 1.0);
+    (BLEEP_AMPLITUDE * fade * (TAU * BLEEP_HZ * seconds).sin()) as f32
+}
+
+/// Decodifica `inputs` in sequenza, come un unico flusso che inizia a
+/// `offset_seconds` nella registrazione, e scrive il risultato in
+/// `output_path`. Le clip saltano l'audio che precede l'intervallo e si
+/// fermano appena lo superano.
+fn run_edit(
+    inputs: &[PathBuf],
+    output_path: &Path,
+    operation: EditOperation,
+    offset_seconds: f64,
+) -> Result<f64, AudioError> {
+    let mut sink = EditSink::new(output_path, operation, offset_seconds);
+    let mut decode = || -> Result<(), AudioError> {
+        for input in inputs {
+            decode_file_from(input, sink.skip_seconds(), &mut sink)?;
+            if sink.is_done() {
+                break;
+            }
+            sink.next_file();
+        }
+        Ok(())
+    };
+    let result = decode().and_then(|_| sink.finish());
+    if result.is_err() {
+        let _ = std::fs::remove_file(output_path);
+    }
+    result
+}
+
+/// Copia l'intervallo `range` di `input_path` (segmenti compresi) in un
+/// nuovo WAV. Restituisce la durata della clip in secondi.
+pub fn extract_clip(
+    input_path: &Path,
+    output_path: &Path,
+    range: TimeRange,
+) -> Result<f64, AudioError> {
+    let duration = run_edit(
+        &recording_parts(input_path),
+        output_path,
+        EditOperation::Clip(range),
+        0.0,
+    )?;
+    if duration == 0.0 {
+        let _ = std::fs::remove_file(output_path);
+        return Err(AudioError::FileError(
+            "L'intervallo è oltre la fine della registrazione".to_string(),
+        ));
+    }
+    Ok(duration)
+}
+
+/// Oscura gli intervalli `ranges` direttamente nel file, mantenendone il
+/// formato (WAV, FLAC o Opus). Nelle registrazioni divise in segmenti
+/// vengono riscritti solo i segmenti che contengono un intervallo.
+pub fn redact_recording(
+    path: &Path,
+    ranges: &[TimeRange],
+    mode: RedactionMode,
+) -> Result<(), AudioError> {
+    let mut offset_seconds = 0.0;
+    for part in recording_parts(path) {
+        offset_seconds += redact_part(&part, offset_seconds, ranges, mode)?;
+    }
+    Ok(())
+}
+
+/// Oscura la registrazione `path` e le copie sulla sua timeline (come
+/// l'originale conservato prima del miglioramento), ed elimina le copie
+/// master: sono scritte alla frequenza e con il clock del dispositivo, senza
+/// il ritardo iniziale né i buchi di un cambio di dispositivo che il mix
+/// riceve, quindi gli intervalli non vi corrispondono e lascerebbero udibile
+/// il passaggio. Restituisce i file eliminati.
+pub fn redact_recording_files(
+    path: &Path,
+    ranges: &[TimeRange],
+    mode: RedactionMode,
+) -> Result<Vec<PathBuf>, AudioError> {
+    let companions = companion_files(path);
+    redact_recording(path, ranges, mode)?;
+
+    let mut removed = Vec::new();
+    for companion in companions {
+        if is_master_copy(path, &companion) {
+            std::fs::remove_file(&companion).map_err(|e| {
+                AudioError::FileError(format!(
+                    "Impossibile eliminare {}: {}",
+                    companion.display(),
+                    e
+                ))
+            })?;
+            removed.push(companion);
+            continue;
+        }
+
+        let is_audio = companion
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(|ext| StorageCodec::from_setting(&ext.to_lowercase()))
+            .is_some();
+        // I segmenti successivi sono oscurati insieme al loro primo file
+        if is_audio && !is_segment_part(&companion) {
+            redact_recording(&companion, ranges, mode).map_err(|e| {
+                AudioError::FileError(format!("Errore oscuramento {}: {}", companion.display(), e))
+            })?;
+        }
+    }
+    Ok(removed)
+}
+
+/// Oscura un file che inizia a `offset_seconds` nella registrazione.
+/// Restituisce la sua durata in secondi.
+fn redact_part(
+    path: &Path,
+    offset_seconds: f64,
+    ranges: &[TimeRange],
+    mode: RedactionMode,
+) -> Result<f64, AudioError> {
+    let codec = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .and_then(|ext| StorageCodec::from_setting(&ext.to_lowercase()))
+        .ok_or_else(|| {
+            AudioError::FileError(format!("Formato non supportato: {}", path.display()))
+        })?;
+
+    // Si lavora su una copia: il file originale viene sostituito solo a
+    // operazione conclusa
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = path.with_file_name(format!("{}.redacting.wav", stem));
+
+    let duration = run_edit(
+        &[path.to_path_buf()],
+        &temp_path,
+        EditOperation::Redact(ranges.to_vec(), mode),
+        offset_seconds,
+    )?;
+
+    // Segmento non toccato: niente ricompressione
+    if !ranges
+        .iter()
+        .any(|range| range.overlaps(offset_seconds, offset_seconds + duration))
+    {
+        let _ = std::fs::remove_file(&temp_path);
+        return Ok(duration);
+    }
+
+    let redacted = match compress_recording(&temp_path, codec) {
+        Ok(redacted) => redacted,
+        Err(e) => {
+            let _ = std::fs::remove_file(&temp_path);
+            return Err(e);
+        }
+    };
+
+    std::fs::rename(&redacted, path).map_err(|e| {
+        let _ = std::fs::remove_file(&redacted);
+        AudioError::FileError(e.to_string())
+    })?;
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio::capture::master_copy_path;
+    use crate::audio::segments::segment_path;
+    use hound::WavReader;
+
+    fn temp_recording(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("edit-{}-{}.wav", std::process::id(), name))
+    }
+
+    /// WAV mono con un tono costante di `seconds` secondi
+    fn write_tone(path: &Path, sample_rate: u32, seconds: f64) {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for _ in 0..(seconds * sample_rate as f64) as usize {
+            writer.write_sample(8_000i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn read_samples(path: &Path) -> Vec<i16> {
+        WavReader::open(path)
+            .unwrap()
+            .samples::<i16>()
+            .map(|s| s.unwrap())
+            .collect()
+    }
+
+    /// Indici dei campioni silenziati
+    fn silent_range(samples: &[i16]) -> Option<(usize, usize)> {
+        let first = samples.iter().position(|&s| s == 0)?;
+        let last = samples.iter().rposition(|&s| s == 0)?;
+        Some((first, last))
+    }
+
+    #[test]
+    fn redaction_spans_segment_boundary() {
+        let path = temp_recording("segments");
+        let second = segment_path(&path, 1);
+        write_tone(&path, 16_000, 1.0);
+        write_tone(&second, 16_000, 1.0);
+
+        let range = TimeRange {
+            start_seconds: 0.75,
+            end_seconds: 1.25,
+        };
+        redact_recording(&path, &[range], RedactionMode::Silence).unwrap();
+
+        let first_samples = read_samples(&path);
+        let second_samples = read_samples(&second);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&second);
+
+        // Fine del primo file e inizio del secondo, durate invariate
+        assert_eq!(first_samples.len(), 16_000);
+        assert_eq!(second_samples.len(), 16_000);
+        assert_eq!(silent_range(&first_samples), Some((12_000, 15_999)));
+        assert_eq!(silent_range(&second_samples), Some((0, 3_999)));
+    }
+
+    #[test]
+    fn redaction_removes_master_copies() {
+        // Il master è a 48 kHz e più corto del mix, che ha ricevuto mezzo
+        // secondo di silenzio per un cambio di dispositivo: lo stesso
+        // intervallo cadrebbe in un punto diverso dell'audio
+        let path = temp_recording("masters");
+        let master = master_copy_path(&path, 0);
+        let original = path.with_file_name(format!(
+            "{}_original.wav",
+            path.file_stem().unwrap().to_string_lossy()
+        ));
+        write_tone(&path, 16_000, 2.0);
+        write_tone(&master, 48_000, 1.5);
+        write_tone(&original, 16_000, 2.0);
+
+        let range = TimeRange {
+            start_seconds: 0.5,
+            end_seconds: 1.0,
+        };
+        let removed = redact_recording_files(&path, &[range], RedactionMode::Silence).unwrap();
+
+        let master_exists = master.exists();
+        let samples = read_samples(&path);
+        let original_samples = read_samples(&original);
+        let _ = std::fs::remove_file(&path);
+        let _ = std::fs::remove_file(&master);
+        let _ = std::fs::remove_file(&original);
+
+        assert_eq!(removed, vec![master]);
+        assert!(!master_exists);
+        assert_eq!(silent_range(&samples), Some((8_000, 15_999)));
+        assert_eq!(silent_range(&original_samples), Some((8_000, 15_999)));
+    }
+}
@@ -0,0 +1,574 @@
+//! Catena di miglioramento del parlato: filtro passa-alto, riduzione del
+//! rumore spettrale e controllo automatico del guadagno (AGC).
+//!
+//! Lavora sul segnale a 16 kHz, prima che arrivi al `WavWriter` durante la
+//! registrazione oppure su un file già registrato. Ogni stadio è opzionale.
+
+use super::capture::AudioError;
+use super::import::{MonoConverter, SampleSink};
+use super::resample::{to_i16, TARGET_SAMPLE_RATE};
+use super::segments::decode_recording;
+use hound::{WavSpec, WavWriter};
+use realfft::num_complex::Complex;
+use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Durata della finestra di analisi della riduzione del rumore
+const NOISE_FRAME_MS: usize = 32;
+/// Velocità di risalita della stima del rumore (dB al secondo)
+const NOISE_RISE_DB_PER_SECOND: f32 = 2.0;
+/// Il minimo della potenza smussata sottostima il rumore: lo si corregge
+const NOISE_BIAS: f32 = 1.5;
+/// Fattore di sottrazione: >1 rimuove più rumore a costo di artefatti
+const OVER_SUBTRACTION: f32 = 2.0;
+/// Smoothing temporale dei guadagni per bin, contro il "rumore musicale"
+const GAIN_SMOOTHING: f32 = 0.5;
+
+/// Blocco su cui l'AGC misura il livello
+const AGC_BLOCK_MS: usize = 10;
+/// Il guadagno si adatta solo sui blocchi che superano di questo margine il
+/// rumore di fondo: nelle pause resta fermo e il rumore non viene alzato
+const AGC_SPEECH_MARGIN_DB: f32 = 10.0;
+/// Sotto questo livello il blocco è comunque silenzio
+const AGC_GATE_DB: f32 = -50.0;
+/// Velocità di risalita della stima del rumore di fondo dell'AGC
+const AGC_NOISE_RISE_DB_PER_SECOND: f32 = 1.0;
+/// Guadagno massimo e attenuazione massima applicati dall'AGC
+const AGC_MAX_GAIN_DB: f32 = 24.0;
+const AGC_MAX_CUT_DB: f32 = -12.0;
+/// Velocità di variazione del guadagno: lenta in salita, rapida in discesa
+const AGC_RISE_DB_PER_SECOND: f32 = 6.0;
+const AGC_FALL_DB_PER_SECOND: f32 = 30.0;
+/// Costante di tempo della misura del livello del parlato
+const AGC_LEVEL_SMOOTHING_MS: f32 = 300.0;
+/// Picco massimo in uscita: oltre, il guadagno viene ridotto subito
+const AGC_LIMIT: f32 = 0.9;
+
+/// Stadi attivi della catena; `None` disattiva lo stadio
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnhancementSettings {
+    /// Frequenza di taglio del passa-alto (rimbombo, vibrazioni del tavolo)
+    pub high_pass_hz: Option<f32>,
+    /// Attenuazione massima del rumore stazionario (ventole, condizionatori)
+    pub noise_reduction_db: Option<f32>,
+    /// Livello medio del parlato a cui l'AGC porta il segnale (dBFS)
+    pub agc_target_db: Option<f32>,
+}
+
+impl EnhancementSettings {
+    pub fn is_enabled(&self) -> bool {
+        self.high_pass_hz.is_some()
+            || self.noise_reduction_db.is_some()
+            || self.agc_target_db.is_some()
+    }
+}
+
+/// Filtro passa-alto Butterworth del secondo ordine
+struct HighPass {
+    b: [f64; 3],
+    a: [f64; 2],
+    state: [f64; 2],
+}
+
+impl HighPass {
+    fn new(sample_rate: u32, cutoff_hz: f32) -> Self {
+        let nyquist = sample_rate as f64 / 2.0;
+        let cutoff = (cutoff_hz as f64).clamp(10.0, nyquist * 0.9);
+        let w0 = 2.0 * PI * cutoff / sample_rate as f64;
+        let alpha = w0.sin() / (2.0 * std::f64::consts::FRAC_1_SQRT_2);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b: [
+                (1.0 + cos) / 2.0 / a0,
+                -(1.0 + cos) / a0,
+                (1.0 + cos) / 2.0 / a0,
+            ],
+            a: [-2.0 * cos / a0, (1.0 - alpha) / a0],
+            state: [0.0; 2],
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            let x = *sample as f64;
+            let y = self.b[0] * x + self.state[0];
+            self.state[0] = self.b[1] * x - self.a[0] * y + self.state[1];
+            self.state[1] = self.b[2] * x - self.a[1] * y;
+            *sample = y as f32;
+        }
+    }
+}
+
+/// Riduzione del rumore per sottrazione spettrale (STFT con overlap-add).
+///
+/// Il rumore di ogni banda viene stimato seguendo i minimi della potenza,
+/// quindi si adatta da solo a rumori stazionari senza bisogno di una
+/// sezione di solo rumore. Introduce mezza finestra di latenza, compensata
+/// scartando l'inizio dell'uscita.
+struct NoiseSuppressor {
+    frame_len: usize,
+    hop: usize,
+    window: Vec<f32>,
+    fft: Arc<dyn RealToComplex<f32>>,
+    ifft: Arc<dyn ComplexToReal<f32>>,
+    frame: Vec<f32>,
+    spectrum: Vec<Complex<f32>>,
+    /// Campioni in attesa di formare una finestra completa
+    input: Vec<f32>,
+    /// Accumulatore dell'overlap-add
+    output: Vec<f32>,
+    smoothed_power: Vec<f32>,
+    noise: Vec<f32>,
+    gains: Vec<f32>,
+    /// Guadagno minimo per bin (l'attenuazione massima)
+    floor: f32,
+    noise_rise: f32,
+    initialized: bool,
+    /// Campioni di uscita ancora da scartare (latenza)
+    skip: usize,
+    /// Campioni ricevuti e restituiti, per conservare la durata
+    received: usize,
+    emitted: usize,
+}
+
+impl NoiseSuppressor {
+    fn new(sample_rate: u32, reduction_db: f32) -> Self {
+        let frame_len = (sample_rate as usize * NOISE_FRAME_MS / 1000)
+            .next_power_of_two()
+            .max(64);
+        let hop = frame_len / 2;
+
+        let mut planner = RealFftPlanner::<f32>::new();
+        let fft = planner.plan_fft_forward(frame_len);
+        let ifft = planner.plan_fft_inverse(frame_len);
+        let spectrum = fft.make_output_vec();
+        let bins = spectrum.len();
+
+        // Radice della finestra di Hann, in analisi e in sintesi: con
+        // sovrapposizione al 50% il prodotto si somma esattamente a 1
+        let window = (0..frame_len)
+            .map(|n| (std::f32::consts::PI * n as f32 / frame_len as f32).sin())
+            .collect();
+
+        Self {
+            frame_len,
+            hop,
+            window,
+            frame: fft.make_input_vec(),
+            fft,
+            ifft,
+            spectrum,
+            input: vec![0.0; frame_len - hop],
+            output: vec![0.0; frame_len],
+            smoothed_power: vec![0.0; bins],
+            noise: vec![0.0; bins],
+            gains: vec![1.0; bins],
+            floor: 10f32.powf(-reduction_db.abs() / 20.0),
+            noise_rise: 10f32
+                .powf(NOISE_RISE_DB_PER_SECOND / 10.0 * hop as f32 / sample_rate as f32),
+            initialized: false,
+            skip: frame_len - hop,
+            received: 0,
+            emitted: 0,
+        }
+    }
+
+    fn process(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        self.received += samples.len();
+        self.input.extend_from_slice(samples);
+        self.drain_frames(out);
+    }
+
+    /// Completa l'ultima finestra con silenzio, così l'uscita ha la stessa
+    /// durata dell'ingresso
+    fn flush(&mut self, out: &mut Vec<f32>) {
+        self.input.resize(self.input.len() + self.frame_len, 0.0);
+        let start = out.len();
+        self.drain_frames(out);
+        let excess = self.emitted.saturating_sub(self.received);
+        out.truncate(out.len().saturating_sub(excess).max(start));
+        self.emitted -= excess;
+    }
+
+    fn drain_frames(&mut self, out: &mut Vec<f32>) {
+        let mut consumed = 0;
+        while self.input.len() - consumed >= self.frame_len {
+            let block = &self.input[consumed..consumed + self.frame_len];
+            for ((dst, &src), &w) in self.frame.iter_mut().zip(block).zip(&self.window) {
+                *dst = src * w;
+            }
+            self.suppress_frame();
+            consumed += self.hop;
+
+            for ((acc, &y), &w) in self.output.iter_mut().zip(&self.frame).zip(&self.window) {
+                *acc += y * w / self.frame_len as f32;
+            }
+
+            let ready = &self.output[..self.hop];
+            let skipped = self.skip.min(ready.len());
+            self.skip -= skipped;
+            out.extend_from_slice(&ready[skipped..]);
+            self.emitted += ready.len() - skipped;
+
+            self.output.copy_within(self.hop.., 0);
+            let len = self.output.len();
+            self.output[len - self.hop..].fill(0.0);
+        }
+        self.input.drain(..consumed);
+    }
+
+    fn suppress_frame(&mut self) {
+        if self
+            .fft
+            .process(&mut self.frame, &mut self.spectrum)
+            .is_err()
+        {
+            return;
+        }
+
+        for (k, bin) in self.spectrum.iter_mut().enumerate() {
+            let power = bin.norm_sqr();
+
+            if !self.initialized {
+                self.smoothed_power[k] = power;
+                self.noise[k] = power;
+            }
+            self.smoothed_power[k] = 0.7 * self.smoothed_power[k] + 0.3 * power;
+
+            // Il rumore segue i minimi della potenza smussata e risale piano
+            let smoothed = self.smoothed_power[k];
+            self.noise[k] = if smoothed < self.noise[k] {
+                smoothed
+            } else {
+                self.noise[k] * self.noise_rise
+            };
+
+            let noise = self.noise[k] * NOISE_BIAS;
+            let gain = if power > 0.0 {
+                (1.0 - OVER_SUBTRACTION * noise / power).max(self.floor)
+            } else {
+                self.floor
+            };
+            self.gains[k] = GAIN_SMOOTHING * self.gains[k] + (1.0 - GAIN_SMOOTHING) * gain;
+
+            *bin *= self.gains[k];
+        }
+        self.initialized = true;
+
+        if self
+            .ifft
+            .process(&mut self.spectrum, &mut self.frame)
+            .is_err()
+        {
+            self.frame.fill(0.0);
+        }
+    }
+}
+
+/// Controllo automatico del guadagno: porta il parlato a un livello medio
+/// costante senza amplificare le pause
+struct Agc {
+    target_db: f32,
+    block_len: usize,
+    block_power: f64,
+    block_samples: usize,
+    level_db: Option<f32>,
+    noise_db: Option<f32>,
+    gain_db: f32,
+    level_smoothing: f32,
+    rise_per_block: f32,
+    fall_per_block: f32,
+    noise_rise_per_block: f32,
+}
+
+impl Agc {
+    fn new(sample_rate: u32, target_db: f32) -> Self {
+        let block_len = (sample_rate as usize * AGC_BLOCK_MS / 1000).max(1);
+        let block_seconds = block_len as f32 / sample_rate as f32;
+
+        Self {
+            target_db: target_db.min(-1.0),
+            block_len,
+            block_power: 0.0,
+            block_samples: 0,
+            level_db: None,
+            noise_db: None,
+            gain_db: 0.0,
+            level_smoothing: (-block_seconds * 1000.0 / AGC_LEVEL_SMOOTHING_MS).exp(),
+            rise_per_block: AGC_RISE_DB_PER_SECOND * block_seconds,
+            fall_per_block: AGC_FALL_DB_PER_SECOND * block_seconds,
+            noise_rise_per_block: AGC_NOISE_RISE_DB_PER_SECOND * block_seconds,
+        }
+    }
+
+    fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples {
+            let x = *sample;
+            self.block_power += (x as f64).powi(2);
+            self.block_samples += 1;
+            if self.block_samples == self.block_len {
+                self.update_gain();
+            }
+
+            let mut gain = 10f32.powf(self.gain_db / 20.0);
+            if (x * gain).abs() > AGC_LIMIT {
+                // Limitatore: niente clipping, anche a costo di un calo brusco
+                gain = AGC_LIMIT / x.abs();
+                self.gain_db = 20.0 * gain.log10();
+            }
+            *sample = x * gain;
+        }
+    }
+
+    fn update_gain(&mut self) {
+        let power = self.block_power / self.block_samples as f64;
+        let block_db = 10.0 * (power + 1e-10).log10() as f32;
+        self.block_power = 0.0;
+        self.block_samples = 0;
+
+        let noise_db = self.noise_db.unwrap_or(block_db.min(AGC_GATE_DB));
+        self.noise_db = Some(if block_db < noise_db {
+            block_db
+        } else {
+            noise_db + self.noise_rise_per_block
+        });
+
+        if block_db < (noise_db + AGC_SPEECH_MARGIN_DB).max(AGC_GATE_DB) {
+            return;
+        }
+
+        let level_db = match self.level_db {
+            Some(level) => self.level_smoothing * level + (1.0 - self.level_smoothing) * block_db,
+            None => block_db,
+        };
+        self.level_db = Some(level_db);
+
+        let desired = (self.target_db - level_db).clamp(AGC_MAX_CUT_DB, AGC_MAX_GAIN_DB);
+        let delta = (desired - self.gain_db).clamp(-self.fall_per_block, self.rise_per_block);
+        self.gain_db += delta;
+    }
+}
+
+/// Catena completa per un singolo canale: passa-alto, riduzione del
+/// rumore, AGC
+pub struct EnhancementChain {
+    high_pass: Option<HighPass>,
+    noise: Option<NoiseSuppressor>,
+    agc: Option<Agc>,
+    buffer: Vec<f32>,
+}
+
+impl EnhancementChain {
+    pub fn new(sample_rate: u32, settings: &EnhancementSettings) -> Self {
+        Self {
+            high_pass: settings
+                .high_pass_hz
+                .map(|hz| HighPass::new(sample_rate, hz)),
+            noise: settings
+                .noise_reduction_db
+                .map(|db| NoiseSuppressor::new(sample_rate, db)),
+            agc: settings.agc_target_db.map(|db| Agc::new(sample_rate, db)),
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Elabora `samples` e accoda il risultato in `out`. Con la riduzione
+    /// del rumore attiva l'uscita è in ritardo di mezza finestra: il resto
+    /// arriva con `flush`.
+    pub fn process(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        self.buffer.clear();
+        self.buffer.extend_from_slice(samples);
+        if let Some(high_pass) = self.high_pass.as_mut() {
+            high_pass.process(&mut self.buffer);
+        }
+        self.finish_stages(out, false);
+    }
+
+    /// Restituisce i campioni ancora trattenuti dalla catena
+    pub fn flush(&mut self, out: &mut Vec<f32>) {
+        self.buffer.clear();
+        self.finish_stages(out, true);
+    }
+
+    fn finish_stages(&mut self, out: &mut Vec<f32>, flush: bool) {
+        let start = out.len();
+        match self.noise.as_mut() {
+            Some(noise) => {
+                noise.process(&self.buffer, out);
+                if flush {
+                    noise.flush(out);
+                }
+            }
+            None => out.extend_from_slice(&self.buffer),
+        }
+        if let Some(agc) = self.agc.as_mut() {
+            agc.process(&mut out[start..]);
+        }
+    }
+}
+
+/// Applica una catena indipendente a ogni canale di un segnale interleaved
+pub struct Enhancer {
+    chains: Vec<EnhancementChain>,
+    channel: Vec<f32>,
+    processed: Vec<Vec<f32>>,
+}
+
+impl Enhancer {
+    pub fn new(sample_rate: u32, channels: usize, settings: &EnhancementSettings) -> Self {
+        let channels = channels.max(1);
+        Self {
+            chains: (0..channels)
+                .map(|_| EnhancementChain::new(sample_rate, settings))
+                .collect(),
+            channel: Vec::new(),
+            processed: vec![Vec::new(); channels],
+        }
+    }
+
+    /// Sostituisce il contenuto di `data` con il segnale elaborato
+    pub fn process(&mut self, data: &mut Vec<f32>) {
+        let channels = self.chains.len();
+        for (index, chain) in self.chains.iter_mut().enumerate() {
+            self.channel.clear();
+            self.channel
+                .extend(data.iter().skip(index).step_by(channels).copied());
+            self.processed[index].clear();
+            chain.process(&self.channel, &mut self.processed[index]);
+        }
+        self.interleave(data);
+    }
+
+    /// Sostituisce il contenuto di `data` con i campioni trattenuti
+    pub fn flush(&mut self, data: &mut Vec<f32>) {
+        for (chain, processed) in self.chains.iter_mut().zip(&mut self.processed) {
+            processed.clear();
+            chain.flush(processed);
+        }
+        self.interleave(data);
+    }
+
+    fn interleave(&self, data: &mut Vec<f32>) {
+        let frames = self.processed.iter().map(Vec::len).min().unwrap_or(0);
+        data.clear();
+        for frame in 0..frames {
+            data.extend(self.processed.iter().map(|channel| channel[frame]));
+        }
+    }
+}
+
+/// Scrive in un WAV 16 kHz mono i campioni decodificati, già migliorati
+struct EnhanceSink {
+    writer: WavWriter<BufWriter<File>>,
+    converter: MonoConverter,
+    chain: EnhancementChain,
+    processed: Vec<f32>,
+}
+
+impl EnhanceSink {
+    fn write_processed(&mut self) -> Result<(), AudioError> {
+        for &sample in &self.processed {
+            self.writer
+                .write_sample(to_i16(sample))
+                .map_err(|e| AudioError::FileError(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl SampleSink for EnhanceSink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        self.processed.clear();
+        let mono = self.converter.convert(data, sample_rate, channels);
+        self.chain.process(mono, &mut self.processed);
+        self.write_processed()
+    }
+}
+
+/// Applica la catena a un file esistente (qualsiasi formato importabile) e
+/// salva il risultato in `output_path` come WAV 16 kHz mono. Restituisce la
+/// durata in secondi.
+pub fn enhance_file(
+    input_path: &Path,
+    output_path: &Path,
+    settings: &EnhancementSettings,
+) -> Result<f64, AudioError> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate: TARGET_SAMPLE_RATE,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let writer =
+        WavWriter::create(output_path, spec).map_err(|e| AudioError::FileError(e.to_string()))?;
+
+    let mut sink = EnhanceSink {
+        writer,
+        converter: MonoConverter::new(),
+        chain: EnhancementChain::new(TARGET_SAMPLE_RATE, settings),
+        processed: Vec::new(),
+    };
+
+    let decoded = decode_recording(input_path, &mut sink).and_then(|_| {
+        sink.processed.clear();
+        sink.chain.flush(&mut sink.processed);
+        sink.write_processed()
+    });
+
+    let duration = sink.writer.duration() as f64 / TARGET_SAMPLE_RATE as f64;
+    let finalized = sink
+        .writer
+        .finalize()
+        .map_err(|e| AudioError::FileError(e.to_string()));
+
+    if let Err(e) = decoded.and(finalized) {
+        let _ = std::fs::remove_file(output_path);
+        return Err(e);
+    }
+    Ok(duration)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tone(len: usize) -> Vec<f32> {
+        (0..len).map(|i| (i as f32 * 0.07).sin() * 0.3).collect()
+    }
+
+    #[test]
+    fn noise_suppressor_flush_keeps_input_length() {
+        for len in [1, 255, 256, 257, 4_000, 16_037] {
+            let mut suppressor = NoiseSuppressor::new(TARGET_SAMPLE_RATE, 12.0);
+            let mut out = Vec::new();
+            for chunk in tone(len).chunks(160) {
+                suppressor.process(chunk, &mut out);
+            }
+            suppressor.flush(&mut out);
+            assert_eq!(out.len(), len, "ingresso di {} campioni", len);
+        }
+    }
+
+    #[test]
+    fn noise_suppressor_without_reduction_is_aligned() {
+        // Con 0 dB di riduzione resta solo l'overlap-add: la latenza della
+        // finestra è compensata e l'uscita coincide con l'ingresso
+        let input = tone(5_000);
+        let mut suppressor = NoiseSuppressor::new(TARGET_SAMPLE_RATE, 0.0);
+        let mut out = Vec::new();
+        suppressor.process(&input, &mut out);
+        suppressor.flush(&mut out);
+
+        assert_eq!(out.len(), input.len());
+        for (index, (a, b)) in out.iter().zip(&input).enumerate() {
+            assert!((a - b).abs() < 1e-4, "campione {}", index);
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use super::level::TrackLevel;
+use super::quality::{QualityReport, QualityWarning};
+use super::stats::TrackStats;
+use super::vad::SilenceMap;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc};
+use std::thread;
+
+/// Eventi prodotti dal thread di registrazione
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RecorderEvent {
+    /// Il dispositivo di una traccia non è più disponibile
+    DeviceLost {
+        track: usize,
+        device_id: String,
+        offset_seconds: f64,
+    },
+    /// La traccia ha ripreso su un altro dispositivo (o sullo stesso,
+    /// ricollegato); il buco è stato riempito con silenzio
+    DeviceSwitched {
+        track: usize,
+        from_device_id: String,
+        to_device_id: String,
+        offset_seconds: f64,
+        gap_seconds: f64,
+    },
+    /// La registrazione è stata messa in pausa; `offset_seconds` è la
+    /// posizione nel file in cui riprenderà l'audio
+    Paused { offset_seconds: f64 },
+    /// La registrazione è ripresa dopo `paused_seconds` di pausa
+    Resumed {
+        offset_seconds: f64,
+        paused_seconds: f64,
+    },
+    /// Livelli di ingresso di tutte le tracce, emesso a intervalli regolari
+    Level { levels: Vec<TrackLevel> },
+    /// Contatori di cattura a fine registrazione (campioni persi, overrun)
+    CaptureStats { tracks: Vec<TrackStats> },
+    /// Il VAD ha rilevato un enunciato concluso; `audio_path` è il segmento
+    /// della registrazione che lo contiene, iniziato a `segment_start_seconds`
+    Utterance {
+        start_seconds: f64,
+        end_seconds: f64,
+        audio_path: PathBuf,
+        segment_start_seconds: f64,
+    },
+    /// Nessun parlato da `silence_seconds`: la registrazione va fermata
+    SilenceTimeout {
+        offset_seconds: f64,
+        silence_seconds: f64,
+    },
+    /// Regioni di parlato dell'intera registrazione, emesso a fine cattura
+    SpeechAnalysis { silence_map: SilenceMap },
+    /// Problema di qualità rilevato su una traccia durante la registrazione
+    QualityWarning {
+        track: usize,
+        warning: QualityWarning,
+    },
+    /// Riepilogo della qualità di tutte le tracce, emesso a fine cattura
+    QualityReport { report: QualityReport },
+    /// Nuovi picchi della forma d'onda (coppie `[min, max]`), a partire da
+    /// `offset_seconds`
+    Waveform {
+        offset_seconds: f64,
+        seconds_per_peak: f64,
+        peaks: Vec<[f32; 2]>,
+    },
+}
+
+pub type RecorderEventHandler = Arc<dyn Fn(RecorderEvent) + Send + Sync>;
+
+enum Dispatch {
+    Event(RecorderEvent),
+    /// Risponde quando tutti gli eventi precedenti sono stati gestiti
+    Flush(mpsc::Sender<()>),
+}
+
+/// Consegna gli eventi al gestore da un thread dedicato, nell'ordine in cui
+/// sono stati prodotti. Il gestore salva su database e notifica il
+/// frontend: eseguito dal thread di scrittura lo bloccherebbe mentre i ring
+/// buffer delle callback si riempiono.
+#[derive(Clone)]
+pub struct EventDispatcher {
+    sender: mpsc::Sender<Dispatch>,
+}
+
+impl EventDispatcher {
+    pub fn spawn(handler: RecorderEventHandler) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            for message in receiver {
+                match message {
+                    Dispatch::Event(event) => handler(event),
+                    Dispatch::Flush(done) => {
+                        let _ = done.send(());
+                    }
+                }
+            }
+        });
+        Self { sender }
+    }
+
+    pub fn send(&self, event: RecorderEvent) {
+        let _ = self.sender.send(Dispatch::Event(event));
+    }
+
+    /// Attende che il gestore abbia elaborato gli eventi inviati finora
+    pub fn flush(&self) {
+        let (done, finished) = mpsc::channel();
+        if self.sender.send(Dispatch::Flush(done)).is_ok() {
+            let _ = finished.recv();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[test]
+    fn flush_waits_for_pending_events() {
+        let handled = Arc::new(Mutex::new(Vec::new()));
+        let dispatcher = {
+            let handled = handled.clone();
+            EventDispatcher::spawn(Arc::new(move |event| {
+                // Un gestore lento (es. database occupato)
+                thread::sleep(Duration::from_millis(20));
+                if let RecorderEvent::Paused { offset_seconds } = event {
+                    handled.lock().unwrap().push(offset_seconds);
+                }
+            }))
+        };
+
+        for offset_seconds in [1.0, 2.0, 3.0] {
+            dispatcher.send(RecorderEvent::Paused { offset_seconds });
+        }
+        assert!(handled.lock().unwrap().len() < 3);
+
+        dispatcher.flush();
+        assert_eq!(*handled.lock().unwrap(), vec![1.0, 2.0, 3.0]);
+    }
+}
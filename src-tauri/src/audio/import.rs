@@ -0,0 +1,408 @@
+//! Importazione di file audio/video esterni (registrazioni Zoom, Teams,
+//! memo vocali) nel formato delle registrazioni dell'app.
+
+use super::capture::AudioError;
+use super::resample::{downmix_to_mono, to_i16, Resampler, TARGET_SAMPLE_RATE};
+use hound::{WavSpec, WavWriter};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::{CodecParameters, DecoderOptions, CODEC_TYPE_NULL, CODEC_TYPE_OPUS};
+use symphonia::core::errors::{Error as SymphoniaError, SeekErrorKind};
+use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::{Time, TimeStamp};
+
+/// Estensioni proposte nella finestra di selezione file
+pub const SUPPORTED_EXTENSIONS: &[&str] = &[
+    "mp3", "m4a", "mp4", "aac", "ogg", "oga", "opus", "webm", "mkv", "wav", "flac", "caf", "aiff",
+];
+
+/// Durata massima di un pacchetto Opus (120 ms a 48 kHz)
+const OPUS_MAX_FRAME_SAMPLES: usize = 5760;
+
+/// Informazioni sul file importato
+#[derive(Debug, Clone)]
+pub struct ImportedAudio {
+    pub duration_seconds: f64,
+    pub source_codec: String,
+    pub source_sample_rate: u32,
+    pub source_channels: usize,
+}
+
+/// Destinazione dei campioni decodificati
+pub(crate) trait SampleSink {
+    /// Riceve campioni interleaved alla frequenza `sample_rate`
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError>;
+
+    /// Il file è stato posizionato con `decode_file_from`: i prossimi
+    /// campioni iniziano a `seconds` dall'inizio del file
+    fn seeked(&mut self, _seconds: f64) {}
+
+    /// Il sink non ha bisogno di altri campioni: la decodifica si ferma
+    fn is_done(&self) -> bool {
+        false
+    }
+}
+
+/// Porta i campioni decodificati a 16 kHz mono, come le registrazioni
+pub(crate) struct MonoConverter {
+    resampler: Option<Resampler>,
+    sample_rate: u32,
+    channels: usize,
+    mono: Vec<f32>,
+    resampled: Vec<f32>,
+}
+
+impl MonoConverter {
+    pub(crate) fn new() -> Self {
+        Self {
+            resampler: None,
+            sample_rate: 0,
+            channels: 0,
+            mono: Vec::new(),
+            resampled: Vec::new(),
+        }
+    }
+
+    pub(crate) fn convert(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> &[f32] {
+        if self.resampler.is_none() || sample_rate != self.sample_rate {
+            self.resampler = Some(Resampler::new(sample_rate, TARGET_SAMPLE_RATE));
+            self.sample_rate = sample_rate;
+        }
+        self.channels = channels;
+
+        self.mono.clear();
+        self.resampled.clear();
+        downmix_to_mono(data, channels, &mut self.mono);
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.process(&self.mono, &mut self.resampled);
+        }
+        &self.resampled
+    }
+}
+
+/// Scrive i campioni decodificati in un WAV 16 kHz mono
+struct WavSink {
+    writer: WavWriter<BufWriter<File>>,
+    converter: MonoConverter,
+}
+
+impl WavSink {
+    fn create(output_path: &Path) -> Result<Self, AudioError> {
+        let spec = WavSpec {
+            channels: 1,
+            sample_rate: TARGET_SAMPLE_RATE,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let writer = WavWriter::create(output_path, spec)
+            .map_err(|e| AudioError::FileError(e.to_string()))?;
+
+        Ok(Self {
+            writer,
+            converter: MonoConverter::new(),
+        })
+    }
+
+    fn finalize(self) -> Result<f64, AudioError> {
+        let duration = self.writer.duration() as f64 / TARGET_SAMPLE_RATE as f64;
+        self.writer
+            .finalize()
+            .map_err(|e| AudioError::FileError(e.to_string()))?;
+        Ok(duration)
+    }
+}
+
+impl SampleSink for WavSink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        for &sample in self.converter.convert(data, sample_rate, channels) {
+            self.writer
+                .write_sample(to_i16(sample))
+                .map_err(|e| AudioError::FileError(e.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Decodifica `input_path` e lo salva in `output_path` come WAV 16 kHz mono.
+pub fn import_to_wav(input_path: &Path, output_path: &Path) -> Result<ImportedAudio, AudioError> {
+    let mut sink = WavSink::create(output_path)?;
+    let decoded = decode_file(input_path, &mut sink);
+
+    let source_sample_rate = sink.converter.sample_rate;
+    let source_channels = sink.converter.channels;
+    let duration_seconds = sink.finalize()?;
+
+    let source_codec = match decoded {
+        Ok(codec) => codec,
+        Err(e) => {
+            let _ = std::fs::remove_file(output_path);
+            return Err(e);
+        }
+    };
+    if duration_seconds <= 0.0 {
+        let _ = std::fs::remove_file(output_path);
+        return Err(AudioError::DecodeError(
+            "Il file non contiene audio decodificabile".to_string(),
+        ));
+    }
+
+    Ok(ImportedAudio {
+        duration_seconds,
+        source_codec,
+        source_sample_rate,
+        source_channels,
+    })
+}
+
+/// Decodifica la prima traccia audio di `input_path` verso `sink`.
+/// Restituisce il nome del codec sorgente.
+pub(crate) fn decode_file(
+    input_path: &Path,
+    sink: &mut dyn SampleSink,
+) -> Result<String, AudioError> {
+    decode_file_from(input_path, 0.0, sink)
+}
+
+/// Come `decode_file`, ma salta a `start_seconds` senza decodificare
+/// l'audio precedente. La posizione raggiunta (al più `start_seconds`, o la
+/// fine del file se l'inizio è oltre) viene comunicata a `sink.seeked`; se il
+/// formato non permette di spostarsi si decodifica dall'inizio.
+pub(crate) fn decode_file_from(
+    input_path: &Path,
+    start_seconds: f64,
+    sink: &mut dyn SampleSink,
+) -> Result<String, AudioError> {
+    let file = File::open(input_path).map_err(|e| AudioError::FileError(e.to_string()))?;
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = input_path.extension().and_then(|e| e.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            stream,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|e| AudioError::DecodeError(format!("Formato non riconosciuto: {}", e)))?;
+    let mut format = probed.format;
+
+    let track = format
+        .tracks()
+        .iter()
+        .find(|t| t.codec_params.codec != CODEC_TYPE_NULL && t.codec_params.sample_rate.is_some())
+        .or_else(|| {
+            format
+                .tracks()
+                .iter()
+                .find(|t| t.codec_params.codec != CODEC_TYPE_NULL)
+        })
+        .ok_or_else(|| AudioError::DecodeError("Nessuna traccia audio nel file".to_string()))?;
+    let track_id = track.id;
+    let params = track.codec_params.clone();
+
+    let codec_name = symphonia::default::get_codecs()
+        .get_codec(params.codec)
+        .map(|d| d.short_name.to_string())
+        .unwrap_or_else(|| {
+            if params.codec == CODEC_TYPE_OPUS {
+                "opus".to_string()
+            } else {
+                format!("{}", params.codec)
+            }
+        });
+
+    let mut seeked = false;
+    if start_seconds > 0.0 {
+        let to = SeekTo::Time {
+            time: Time::from(start_seconds),
+            track_id: Some(track_id),
+        };
+        match format.seek(SeekMode::Accurate, to) {
+            Ok(position) => {
+                sink.seeked(timestamp_seconds(&params, position.actual_ts));
+                seeked = true;
+            }
+            // Inizio oltre la fine: non c'è niente da decodificare
+            Err(SymphoniaError::SeekError(SeekErrorKind::OutOfRange))
+                if params.n_frames.is_some() =>
+            {
+                let end = params.start_ts + params.n_frames.unwrap_or(0);
+                sink.seeked(timestamp_seconds(&params, end));
+                return Ok(codec_name);
+            }
+            Err(e) => eprintln!(
+                "Impossibile spostarsi nel file, decodifica dall'inizio: {}",
+                e
+            ),
+        }
+    }
+
+    if params.codec == CODEC_TYPE_OPUS {
+        decode_opus(format.as_mut(), track_id, &params, seeked, sink)?;
+    } else {
+        decode_symphonia(format.as_mut(), track_id, &params, sink)?;
+    }
+
+    Ok(codec_name)
+}
+
+/// Converte un timestamp della traccia in secondi
+fn timestamp_seconds(params: &CodecParameters, ts: TimeStamp) -> f64 {
+    match (params.time_base, params.sample_rate) {
+        (Some(time_base), _) => {
+            let time = time_base.calc_time(ts);
+            time.seconds as f64 + time.frac
+        }
+        (None, Some(sample_rate)) => ts as f64 / sample_rate as f64,
+        (None, None) => 0.0,
+    }
+}
+
+/// Legge il prossimo pacchetto della traccia, `None` a fine file
+fn next_packet(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+) -> Result<Option<symphonia::core::formats::Packet>, AudioError> {
+    loop {
+        match format.next_packet() {
+            Ok(packet) if packet.track_id() == track_id => return Ok(Some(packet)),
+            Ok(_) => continue,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            // Nuovo stream concatenato (es. Ogg a catena): ci si ferma al primo
+            Err(SymphoniaError::ResetRequired) => return Ok(None),
+            Err(e) => return Err(AudioError::DecodeError(e.to_string())),
+        }
+    }
+}
+
+fn decode_symphonia(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+    sink: &mut dyn SampleSink,
+) -> Result<(), AudioError> {
+    let mut decoder = symphonia::default::get_codecs()
+        .make(params, &DecoderOptions::default())
+        .map_err(|e| AudioError::DecodeError(format!("Codec non supportato: {}", e)))?;
+
+    let mut buffer: Option<SampleBuffer<f32>> = None;
+
+    while let Some(packet) = next_packet(format, track_id)? {
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Pacchetto corrotto: lo si salta senza interrompere l'importazione
+            Err(SymphoniaError::DecodeError(e)) => {
+                eprintln!("Pacchetto non decodificabile ignorato: {}", e);
+                continue;
+            }
+            Err(e) => return Err(AudioError::DecodeError(e.to_string())),
+        };
+
+        let spec = *decoded.spec();
+        if buffer
+            .as_ref()
+            .map(|b| b.capacity() < decoded.capacity() * spec.channels.count())
+            .unwrap_or(true)
+        {
+            buffer = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+
+        if let Some(buffer) = buffer.as_mut() {
+            buffer.copy_interleaved_ref(decoded);
+            sink.push(buffer.samples(), spec.rate, spec.channels.count())?;
+        }
+        if sink.is_done() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Symphonia non include un decoder Opus: i pacchetti estratti dal
+/// contenitore (WebM, Ogg) vengono decodificati con libopus.
+/// Dopo uno spostamento (`seeked`) il pre-skip iniziale non si applica.
+fn decode_opus(
+    format: &mut dyn FormatReader,
+    track_id: u32,
+    params: &CodecParameters,
+    seeked: bool,
+    sink: &mut dyn SampleSink,
+) -> Result<(), AudioError> {
+    use audiopus::coder::Decoder;
+    use audiopus::packet::Packet;
+    use audiopus::{Channels, MutSignals, SampleRate};
+
+    // Header "OpusHead": numero di canali e campioni da scartare all'inizio
+    let head = params
+        .extra_data
+        .as_deref()
+        .filter(|data| data.len() >= 12 && data.starts_with(b"OpusHead"));
+    let channel_count = head
+        .map(|data| data[9] as usize)
+        .or_else(|| params.channels.map(|c| c.count()))
+        .unwrap_or(2);
+    let mut pre_skip = head
+        .map(|data| u16::from_le_bytes([data[10], data[11]]) as usize)
+        .or(params.delay.map(|d| d as usize))
+        .unwrap_or(0);
+    if seeked {
+        pre_skip = 0;
+    }
+
+    let channels = match channel_count {
+        1 => Channels::Mono,
+        2 => Channels::Stereo,
+        n => {
+            return Err(AudioError::DecodeError(format!(
+                "Opus con {} canali non supportato",
+                n
+            )))
+        }
+    };
+
+    let mut decoder = Decoder::new(SampleRate::Hz48000, channels)
+        .map_err(|e| AudioError::DecodeError(e.to_string()))?;
+    let mut output = vec![0.0f32; OPUS_MAX_FRAME_SAMPLES * channel_count];
+
+    while let Some(packet) = next_packet(format, track_id)? {
+        let Ok(opus_packet) = Packet::try_from(packet.buf()) else {
+            continue;
+        };
+        let Ok(signals) = MutSignals::try_from(&mut output[..]) else {
+            continue;
+        };
+
+        let frames = match decoder.decode_float(Some(opus_packet), signals, false) {
+            Ok(frames) => frames,
+            Err(e) => {
+                eprintln!("Pacchetto Opus non decodificabile ignorato: {}", e);
+                continue;
+            }
+        };
+
+        let skip = pre_skip.min(frames);
+        pre_skip -= skip;
+
+        let samples = &output[skip * channel_count..frames * channel_count];
+        if !samples.is_empty() {
+            sink.push(samples, 48_000, channel_count)?;
+        }
+        if sink.is_done() {
+            break;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,340 @@
+use super::capture::AudioError;
+use super::devices::{find_input_device, input_devices_with_ids, InputConfig, MONITOR_ID_PREFIX};
+use super::level::LevelMeter;
+use super::stats::CaptureCounters;
+use super::watcher::{mark_in_use, DeviceInUse};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, Sample, SampleFormat, SizedSample};
+use rtrb::{Consumer, Producer, RingBuffer};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Secondi di audio che il ring buffer può contenere se il thread di
+/// scrittura resta indietro (es. disco lento)
+const RING_BUFFER_SECONDS: usize = 2;
+
+/// Stato condiviso fra la callback di una traccia e il thread di registrazione
+#[derive(Clone)]
+pub struct StreamShared {
+    pub is_recording: Arc<AtomicBool>,
+    pub meter: Arc<LevelMeter>,
+    pub counters: Arc<CaptureCounters>,
+    /// Impostato dalla callback di errore quando il dispositivo sparisce
+    pub device_lost: Arc<AtomicBool>,
+}
+
+impl StreamShared {
+    pub fn new(is_recording: Arc<AtomicBool>) -> Self {
+        Self {
+            is_recording,
+            meter: Arc::new(LevelMeter::new()),
+            counters: Arc::new(CaptureCounters::new()),
+            device_lost: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+/// Dispositivo di input pronto per aprire uno stream
+pub struct InputSource {
+    /// ID stabile del dispositivo (vedi `input_devices_with_ids`)
+    pub id: String,
+    pub device: cpal::Device,
+    pub config: cpal::StreamConfig,
+    pub sample_format: SampleFormat,
+    /// Sorgente monitor PulseAudio, registrata con `parec` invece che con cpal
+    pub monitor_source: Option<String>,
+    /// Configurazione scelta dall'utente, riusata se il dispositivo viene
+    /// riaperto dopo una disconnessione
+    pub requested_config: Option<InputConfig>,
+}
+
+/// Dispositivo trovato a partire dal suo ID stabile
+pub struct LocatedDevice {
+    pub id: String,
+    pub device: cpal::Device,
+    pub monitor_source: Option<String>,
+}
+
+/// Cerca il dispositivo `device_id` (quello predefinito se `None`)
+pub fn locate_device(
+    host: &cpal::Host,
+    device_id: Option<&str>,
+) -> Result<LocatedDevice, AudioError> {
+    let Some(id) = device_id else {
+        let device = host
+            .default_input_device()
+            .ok_or(AudioError::NoInputDevice)?;
+        return Ok(LocatedDevice {
+            id: default_device_id(host, &device),
+            device,
+            monitor_source: None,
+        });
+    };
+
+    if let Some(source) = id.strip_prefix(MONITOR_ID_PREFIX) {
+        return Ok(LocatedDevice {
+            id: id.to_string(),
+            device: open_monitor_device(host, source)?,
+            monitor_source: Some(source.to_string()),
+        });
+    }
+
+    let device =
+        find_input_device(host, id).ok_or_else(|| AudioError::DeviceNotFound(id.to_string()))?;
+    Ok(LocatedDevice {
+        id: id.to_string(),
+        device,
+        monitor_source: None,
+    })
+}
+
+pub fn resolve_input(
+    host: &cpal::Host,
+    device_id: Option<&str>,
+    requested_config: Option<InputConfig>,
+) -> Result<InputSource, AudioError> {
+    let located = locate_device(host, device_id)?;
+
+    let config = match requested_config {
+        Some(requested) => select_config(&located.device, requested)?,
+        None => located
+            .device
+            .default_input_config()
+            .map_err(|e| AudioError::DeviceError(e.to_string()))?,
+    };
+
+    Ok(InputSource {
+        id: located.id,
+        device: located.device,
+        sample_format: config.sample_format(),
+        config: config.into(),
+        monitor_source: located.monitor_source,
+        requested_config,
+    })
+}
+
+/// Sceglie fra le configurazioni supportate quella con frequenza e canali
+/// richiesti, preferendo i formati che non perdono precisione
+fn select_config(
+    device: &cpal::Device,
+    requested: InputConfig,
+) -> Result<cpal::SupportedStreamConfig, AudioError> {
+    let rank = |format: SampleFormat| match format {
+        SampleFormat::F32 => 0,
+        SampleFormat::I32 => 1,
+        SampleFormat::I16 => 2,
+        SampleFormat::F64 => 3,
+        _ => 4,
+    };
+
+    device
+        .supported_input_configs()
+        .map_err(|e| AudioError::DeviceError(e.to_string()))?
+        .filter(|range| {
+            range.channels() == requested.channels
+                && range.min_sample_rate().0 <= requested.sample_rate
+                && requested.sample_rate <= range.max_sample_rate().0
+        })
+        .min_by_key(|range| rank(range.sample_format()))
+        .map(|range| range.with_sample_rate(cpal::SampleRate(requested.sample_rate)))
+        .ok_or_else(|| {
+            AudioError::DeviceError(format!(
+                "Configurazione non supportata: {} Hz, {} canali",
+                requested.sample_rate, requested.channels
+            ))
+        })
+}
+
+/// ID stabile del dispositivo predefinito, ricavato dal nome
+fn default_device_id(host: &cpal::Host, device: &cpal::Device) -> String {
+    let name = device.name().unwrap_or_default();
+    input_devices_with_ids(host)
+        .into_iter()
+        .find(|(_, d)| d.name().map(|n| n == name).unwrap_or(false))
+        .map(|(id, _)| id)
+        .unwrap_or(name)
+}
+
+/// Stream aperto di una traccia: la cattura si ferma quando viene chiuso
+/// (drop). Finché è aperto il dispositivo risulta in uso al watcher.
+pub struct InputStream {
+    _handle: StreamHandle,
+    _in_use: DeviceInUse,
+}
+
+// Mai letto: serve solo a tenere aperto lo stream
+#[allow(dead_code)]
+enum StreamHandle {
+    Cpal(cpal::Stream),
+    /// Sorgente monitor registrata da `parec`
+    #[cfg(target_os = "linux")]
+    Monitor(super::pulse::MonitorStream),
+}
+
+/// Accoda nel ring buffer i campioni interleaved ricevuti da uno stream,
+/// aggiornando livelli e contatori. Gira sul thread real-time della
+/// callback: niente lock, allocazioni o I/O.
+pub fn enqueue_samples<T>(
+    producer: &mut Producer<f32>,
+    shared: &StreamShared,
+    channels: usize,
+    data: &[T],
+) where
+    T: Sample,
+    f32: FromSample<T>,
+{
+    if !shared.is_recording.load(Ordering::SeqCst) {
+        return;
+    }
+
+    shared
+        .meter
+        .update(data.iter().map(|&s| f32::from_sample(s)));
+
+    // Solo frame completi, per non sfasare i canali interleaved
+    let slots = producer.slots() - producer.slots() % channels;
+    let writable = data.len().min(slots);
+    if let Ok(chunk) = producer.write_chunk_uninit(writable) {
+        chunk.fill_from_iter(data[..writable].iter().map(|&s| f32::from_sample(s)));
+    }
+
+    shared.counters.record(writable, data.len() - writable);
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    mut producer: Producer<f32>,
+    shared: StreamShared,
+    err_fn: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample,
+    f32: FromSample<T>,
+{
+    let channels = (config.channels as usize).max(1);
+
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &_| enqueue_samples(&mut producer, &shared, channels, data),
+        err_fn,
+        None,
+    )
+}
+
+/// Apre e avvia lo stream di `source`, restituendo il lato di lettura del
+/// ring buffer in cui la callback accoda i campioni. `device_lost` viene
+/// impostato quando il dispositivo sparisce (es. cuffie Bluetooth
+/// scollegate), così il thread di registrazione può passare a un fallback.
+pub fn open_stream(
+    source: &InputSource,
+    shared: &StreamShared,
+) -> Result<(InputStream, Consumer<f32>), AudioError> {
+    let device_lost = shared.device_lost.clone();
+    let err_fn = move |err: cpal::StreamError| {
+        eprintln!("Errore stream audio: {}", err);
+        if matches!(err, cpal::StreamError::DeviceNotAvailable) {
+            device_lost.store(true, Ordering::SeqCst);
+        }
+    };
+
+    let capacity = source.config.sample_rate.0 as usize
+        * source.config.channels as usize
+        * RING_BUFFER_SECONDS;
+    let (producer, consumer) = RingBuffer::new(capacity);
+    let shared = shared.clone();
+
+    let in_use = mark_in_use(source.device.name().unwrap_or_else(|_| source.id.clone()));
+
+    if let Some(monitor) = source.monitor_source.as_deref() {
+        let handle = open_monitor_stream(monitor, &source.config, producer, shared)?;
+        return Ok((
+            InputStream {
+                _handle: handle,
+                _in_use: in_use,
+            },
+            consumer,
+        ));
+    }
+
+    macro_rules! build_as {
+        ($sample:ty) => {
+            build_stream::<$sample>(&source.device, &source.config, producer, shared, err_fn)
+                .map_err(|e| AudioError::StreamError(e.to_string()))
+        };
+    }
+
+    let stream = match source.sample_format {
+        SampleFormat::I8 => build_as!(i8),
+        SampleFormat::I16 => build_as!(i16),
+        SampleFormat::I32 => build_as!(i32),
+        SampleFormat::I64 => build_as!(i64),
+        SampleFormat::U8 => build_as!(u8),
+        SampleFormat::U16 => build_as!(u16),
+        SampleFormat::U32 => build_as!(u32),
+        SampleFormat::U64 => build_as!(u64),
+        SampleFormat::F32 => build_as!(f32),
+        SampleFormat::F64 => build_as!(f64),
+        other => Err(AudioError::StreamError(format!(
+            "Formato sample non supportato: {}",
+            other
+        ))),
+    }?;
+
+    stream
+        .play()
+        .map_err(|e| AudioError::StreamError(e.to_string()))?;
+
+    Ok((
+        InputStream {
+            _handle: StreamHandle::Cpal(stream),
+            _in_use: in_use,
+        },
+        consumer,
+    ))
+}
+
+/// Dispositivo cpal attraverso cui si registrano le sorgenti monitor
+#[cfg(target_os = "linux")]
+fn open_monitor_device(host: &cpal::Host, source: &str) -> Result<cpal::Device, AudioError> {
+    let available = super::pulse::list_monitor_sources();
+    if !available.iter().any(|s| s.name == source) {
+        return Err(AudioError::DeviceNotFound(format!(
+            "{}{}",
+            MONITOR_ID_PREFIX, source
+        )));
+    }
+
+    super::pulse::find_pulse_device(host).ok_or_else(|| {
+        AudioError::DeviceError("Dispositivo PulseAudio non disponibile".to_string())
+    })
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_monitor_device(_host: &cpal::Host, _source: &str) -> Result<cpal::Device, AudioError> {
+    Err(AudioError::DeviceError(
+        "Cattura audio di sistema non supportata su questa piattaforma".to_string(),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+fn open_monitor_stream(
+    source: &str,
+    config: &cpal::StreamConfig,
+    producer: Producer<f32>,
+    shared: StreamShared,
+) -> Result<StreamHandle, AudioError> {
+    super::pulse::open_monitor_stream(source, config, producer, shared).map(StreamHandle::Monitor)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_monitor_stream(
+    _source: &str,
+    _config: &cpal::StreamConfig,
+    _producer: Producer<f32>,
+    _shared: StreamShared,
+) -> Result<StreamHandle, AudioError> {
+    Err(AudioError::DeviceError(
+        "Cattura audio di sistema non supportata su questa piattaforma".to_string(),
+    ))
+}
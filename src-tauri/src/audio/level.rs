@@ -0,0 +1,89 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+
+/// Soglia oltre la quale un campione è considerato in clipping
+const CLIP_THRESHOLD: f32 = 0.999;
+
+/// Livelli di una traccia nell'ultimo intervallo di misura
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackLevel {
+    pub track: usize,
+    /// Valore efficace, lineare in [0, 1]
+    pub rms: f32,
+    /// Picco assoluto, lineare in [0, 1]
+    pub peak: f32,
+    /// Almeno un campione ha raggiunto il fondo scala
+    pub clipping: bool,
+}
+
+/// Misura RMS e picco direttamente nella callback audio.
+///
+/// La callback accumula i valori con operazioni atomiche (nessun lock né
+/// allocazione); il thread di registrazione li legge e azzera con `take`.
+#[derive(Default)]
+pub struct LevelMeter {
+    /// Bit del picco `f32`: per valori positivi l'ordinamento coincide
+    peak: AtomicU32,
+    /// Bit della somma dei quadrati `f64`
+    sum_squares: AtomicU64,
+    samples: AtomicU64,
+    clipped: AtomicU64,
+}
+
+impl LevelMeter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Accumula un buffer di campioni normalizzati
+    pub fn update(&self, samples: impl IntoIterator<Item = f32>) {
+        let mut count = 0u64;
+        let mut peak = 0.0f32;
+        let mut sum_squares = 0.0f64;
+        let mut clipped = 0u64;
+
+        for sample in samples {
+            count += 1;
+            let abs = sample.abs();
+            peak = peak.max(abs);
+            sum_squares += (sample as f64) * (sample as f64);
+            if abs >= CLIP_THRESHOLD {
+                clipped += 1;
+            }
+        }
+
+        if count == 0 {
+            return;
+        }
+
+        self.peak.fetch_max(peak.to_bits(), Ordering::Relaxed);
+        let _ = self
+            .sum_squares
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |bits| {
+                Some((f64::from_bits(bits) + sum_squares).to_bits())
+            });
+        self.samples.fetch_add(count, Ordering::Relaxed);
+        self.clipped.fetch_add(clipped, Ordering::Relaxed);
+    }
+
+    /// Restituisce i livelli accumulati dall'ultima chiamata e li azzera
+    pub fn take(&self, track: usize) -> TrackLevel {
+        let peak = f32::from_bits(self.peak.swap(0, Ordering::Relaxed));
+        let sum_squares = f64::from_bits(self.sum_squares.swap(0, Ordering::Relaxed));
+        let samples = self.samples.swap(0, Ordering::Relaxed);
+        let clipped = self.clipped.swap(0, Ordering::Relaxed);
+
+        let rms = if samples > 0 {
+            (sum_squares / samples as f64).sqrt() as f32
+        } else {
+            0.0
+        };
+
+        TrackLevel {
+            track,
+            rms,
+            peak,
+            clipping: clipped > 0,
+        }
+    }
+}
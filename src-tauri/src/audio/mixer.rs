@@ -0,0 +1,346 @@
+use super::resample::{downmix_to_mono, Resampler, TARGET_SAMPLE_RATE};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Instant;
+
+/// Correzione massima applicata al rapporto di resampling (0,5%)
+const MAX_DRIFT_CORRECTION: f64 = 0.005;
+/// Correzione per campione di differenza fra le code delle tracce
+const DRIFT_GAIN: f64 = 2e-6;
+/// Correzione accumulata per campione di differenza e secondo di audio:
+/// assorbe lo scarto costante fra i clock, così la differenza fra le code
+/// torna a zero invece di restare proporzionale al drift
+const DRIFT_INTEGRAL_GAIN: f64 = 3e-8;
+/// Coefficiente di smoothing dell'errore di allineamento
+const DRIFT_SMOOTHING: f64 = 0.02;
+/// Scarto massimo tollerato fra le tracce prima di riempire con silenzio (1s)
+const MAX_SKEW_SAMPLES: usize = TARGET_SAMPLE_RATE as usize;
+
+/// Come vengono salvati i dispositivi registrati insieme
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingLayout {
+    /// Un unico file mono con tutte le sorgenti mixate
+    #[default]
+    Mixed,
+    /// Un file multicanale con una traccia mono per dispositivo
+    MultiTrack,
+}
+
+struct Track {
+    channels: usize,
+    resampler: Resampler,
+    /// Campioni a 16 kHz mono in attesa di essere mixati
+    pending: VecDeque<f32>,
+    smoothed_error: f64,
+    /// Parte integrale della correzione del drift
+    drift_integral: f64,
+    first_data: Option<Instant>,
+    mono: Vec<f32>,
+    resampled: Vec<f32>,
+}
+
+/// Allinea e combina più sorgenti portandole a 16 kHz mono.
+///
+/// La prima traccia fa da riferimento di clock: le altre vengono
+/// ricampionate con un rapporto corretto dinamicamente in base alla
+/// differenza di riempimento delle code, così da compensare il drift fra
+/// dispositivi con oscillatori diversi anche su registrazioni di ore.
+pub struct Mixer {
+    tracks: Vec<Track>,
+    layout: RecordingLayout,
+    aligned: bool,
+}
+
+impl Mixer {
+    /// Crea il mixer a partire da (sample rate, canali) di ogni sorgente
+    pub fn new(inputs: &[(u32, u16)], layout: RecordingLayout) -> Self {
+        let tracks = inputs
+            .iter()
+            .map(|&(sample_rate, channels)| Track {
+                channels: channels as usize,
+                resampler: Resampler::new(sample_rate, TARGET_SAMPLE_RATE),
+                pending: VecDeque::new(),
+                smoothed_error: 0.0,
+                drift_integral: 0.0,
+                first_data: None,
+                mono: Vec::new(),
+                resampled: Vec::new(),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            aligned: tracks.len() <= 1,
+            tracks,
+            layout,
+        }
+    }
+
+    /// Numero di canali del file prodotto
+    pub fn output_channels(&self) -> u16 {
+        match self.layout {
+            RecordingLayout::Mixed => 1,
+            RecordingLayout::MultiTrack => self.tracks.len().max(1) as u16,
+        }
+    }
+
+    /// Accoda campioni interleaved alla frequenza originale della sorgente
+    pub fn push(&mut self, track: usize, data: &[f32]) {
+        let Some(track) = self.tracks.get_mut(track) else {
+            return;
+        };
+        if data.is_empty() {
+            return;
+        }
+
+        track.first_data.get_or_insert_with(Instant::now);
+
+        track.mono.clear();
+        track.resampled.clear();
+        downmix_to_mono(data, track.channels, &mut track.mono);
+        track.resampler.process(&track.mono, &mut track.resampled);
+        track.pending.extend(track.resampled.iter().copied());
+    }
+
+    /// Sostituisce la sorgente di una traccia (es. dopo un cambio di dispositivo)
+    pub fn replace_source(&mut self, track: usize, sample_rate: u32, channels: u16) {
+        if let Some(track) = self.tracks.get_mut(track) {
+            track.channels = channels as usize;
+            track.resampler = Resampler::new(sample_rate, TARGET_SAMPLE_RATE);
+            track.smoothed_error = 0.0;
+            track.drift_integral = 0.0;
+        }
+    }
+
+    /// Riempie con silenzio il buco lasciato da una sorgente interrotta.
+    ///
+    /// Con più tracce basta riallineare la traccia alle altre, che hanno
+    /// continuato a registrare; con una sola traccia si inserisce la durata
+    /// dell'interruzione.
+    pub fn insert_gap(&mut self, track: usize, seconds: f64) {
+        let longest = self
+            .tracks
+            .iter()
+            .map(|t| t.pending.len())
+            .max()
+            .unwrap_or(0);
+        let single = self.tracks.len() == 1;
+
+        let Some(track) = self.tracks.get_mut(track) else {
+            return;
+        };
+
+        if single {
+            let gap = (seconds * TARGET_SAMPLE_RATE as f64) as usize;
+            track.pending.extend(std::iter::repeat(0.0).take(gap));
+        } else if track.pending.len() < longest {
+            track.pending.resize(longest, 0.0);
+        }
+    }
+
+    /// Estrae i frame già allineati su tutte le tracce, accodandoli a `out`
+    pub fn mix(&mut self, out: &mut Vec<f32>) {
+        if !self.aligned {
+            self.align_start();
+        }
+        self.fill_stalled_tracks();
+
+        let available = self
+            .tracks
+            .iter()
+            .map(|t| t.pending.len())
+            .min()
+            .unwrap_or(0);
+        self.compensate_drift(available as f64 / TARGET_SAMPLE_RATE as f64);
+        self.drain(available, out);
+    }
+
+    /// Svuota le code completando con silenzio le tracce più corte
+    pub fn flush(&mut self, out: &mut Vec<f32>) {
+        let longest = self
+            .tracks
+            .iter()
+            .map(|t| t.pending.len())
+            .max()
+            .unwrap_or(0);
+        for track in &mut self.tracks {
+            track.pending.resize(longest, 0.0);
+        }
+        self.drain(longest, out);
+    }
+
+    fn drain(&mut self, frames: usize, out: &mut Vec<f32>) {
+        match self.layout {
+            RecordingLayout::Mixed => {
+                for _ in 0..frames {
+                    let sum: f32 = self
+                        .tracks
+                        .iter_mut()
+                        .filter_map(|t| t.pending.pop_front())
+                        .sum();
+                    out.push(sum.clamp(-1.0, 1.0));
+                }
+            }
+            RecordingLayout::MultiTrack => {
+                for _ in 0..frames {
+                    for track in &mut self.tracks {
+                        out.push(track.pending.pop_front().unwrap_or(0.0));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Compensa il diverso istante di avvio degli stream anteponendo
+    /// silenzio alle tracce partite dopo la prima.
+    fn align_start(&mut self) {
+        if self.tracks.iter().any(|t| t.first_data.is_none()) {
+            // Una sorgente che non parte entro il tempo massimo verrà
+            // gestita come traccia in stallo
+            let longest = self.tracks.iter().map(|t| t.pending.len()).max();
+            if longest.unwrap_or(0) > MAX_SKEW_SAMPLES {
+                self.aligned = true;
+            }
+            return;
+        }
+
+        let earliest = self
+            .tracks
+            .iter()
+            .filter_map(|t| t.first_data)
+            .min()
+            .unwrap_or_else(Instant::now);
+
+        for track in &mut self.tracks {
+            let delay = track
+                .first_data
+                .map(|t| t.duration_since(earliest).as_secs_f64())
+                .unwrap_or(0.0);
+            let padding = (delay * TARGET_SAMPLE_RATE as f64) as usize;
+            for _ in 0..padding {
+                track.pending.push_front(0.0);
+            }
+        }
+
+        self.aligned = true;
+    }
+
+    /// Se una sorgente smette di produrre dati (o non è mai partita) le
+    /// altre non devono restare bloccate: la si riempie con silenzio.
+    fn fill_stalled_tracks(&mut self) {
+        let longest = self
+            .tracks
+            .iter()
+            .map(|t| t.pending.len())
+            .max()
+            .unwrap_or(0);
+
+        for track in &mut self.tracks {
+            if longest - track.pending.len() > MAX_SKEW_SAMPLES {
+                track.pending.resize(longest, 0.0);
+            }
+        }
+    }
+
+    /// Corregge il rapporto di resampling delle tracce non di riferimento.
+    /// `seconds` è l'audio mixato in questo passo, su cui si integra
+    /// l'errore.
+    fn compensate_drift(&mut self, seconds: f64) {
+        let Some((reference, others)) = self.tracks.split_first_mut() else {
+            return;
+        };
+        let reference_len = reference.pending.len() as f64;
+
+        for track in others {
+            let error = track.pending.len() as f64 - reference_len;
+            track.smoothed_error += DRIFT_SMOOTHING * (error - track.smoothed_error);
+
+            track.drift_integral = (track.drift_integral
+                + track.smoothed_error * DRIFT_INTEGRAL_GAIN * seconds)
+                .clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+
+            let correction = (track.smoothed_error * DRIFT_GAIN + track.drift_integral)
+                .clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+            track.resampler.set_ratio_adjustment(1.0 + correction);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLOCK: usize = 160;
+
+    #[test]
+    fn drift_is_compensated() {
+        // Due sorgenti a 16 kHz nominali, la seconda con il clock più
+        // veloce dello 0,1%: senza correzione dopo 5 minuti sarebbe avanti
+        // di 4800 campioni
+        let rate = TARGET_SAMPLE_RATE;
+        let mut mixer = Mixer::new(&[(rate, 1), (rate, 1)], RecordingLayout::MultiTrack);
+        let fast_block = BLOCK as f64 * 1.001;
+        let mut fast_due = 0.0;
+        let mut out = Vec::new();
+
+        for _ in 0..(5 * 60 * rate as usize / BLOCK) {
+            mixer.push(0, &[0.0; BLOCK]);
+            fast_due += fast_block;
+            let fast = fast_due.floor() as usize;
+            fast_due -= fast as f64;
+            mixer.push(1, &vec![0.0; fast]);
+
+            out.clear();
+            mixer.mix(&mut out);
+        }
+
+        // Il termine integrale ha assorbito lo 0,1% di differenza fra i
+        // clock: lo scarto fra le code è tornato quasi a zero
+        let skew = mixer.tracks[1].pending.len() as f64 - mixer.tracks[0].pending.len() as f64;
+        assert!(skew.abs() < 100.0, "scarto di {} campioni", skew);
+        let integral = mixer.tracks[1].drift_integral;
+        assert!((integral - 0.001).abs() < 2e-4, "correzione {}", integral);
+    }
+
+    #[test]
+    fn mixed_layout_sums_and_clamps() {
+        let rate = TARGET_SAMPLE_RATE;
+        let mut mixer = Mixer::new(&[(rate, 1), (rate, 1)], RecordingLayout::Mixed);
+        assert_eq!(mixer.output_channels(), 1);
+        mixer.aligned = true;
+
+        mixer.push(0, &[0.25, 0.75]);
+        mixer.push(1, &[0.25, 0.75]);
+        let mut out = Vec::new();
+        mixer.mix(&mut out);
+        assert_eq!(out, vec![0.5, 1.0]);
+    }
+
+    #[test]
+    fn multitrack_layout_interleaves_and_flush_pads() {
+        let rate = TARGET_SAMPLE_RATE;
+        let mut mixer = Mixer::new(&[(rate, 1), (rate, 2)], RecordingLayout::MultiTrack);
+        assert_eq!(mixer.output_channels(), 2);
+        mixer.aligned = true;
+
+        mixer.push(0, &[0.1, 0.2]);
+        mixer.push(1, &[0.4, 0.6]);
+        let mut out = Vec::new();
+        mixer.flush(&mut out);
+        assert_eq!(out.len(), 4);
+        assert_eq!(out[0], 0.1);
+        assert!((out[1] - 0.5).abs() < 1e-6);
+        assert_eq!(out[2], 0.2);
+        assert_eq!(out[3], 0.0);
+    }
+
+    #[test]
+    fn gap_on_single_track_inserts_silence() {
+        let mut mixer = Mixer::new(&[(TARGET_SAMPLE_RATE, 1)], RecordingLayout::Mixed);
+        mixer.insert_gap(0, 0.5);
+        let mut out = Vec::new();
+        mixer.mix(&mut out);
+        assert_eq!(out.len(), TARGET_SAMPLE_RATE as usize / 2);
+        assert!(out.iter().all(|&s| s == 0.0));
+    }
+}
@@ -1,5 +1,40 @@
 pub mod capture;
 pub mod devices;
+pub mod edit;
+pub mod enhance;
+pub mod events;
+pub mod import;
+pub mod input;
+pub mod level;
+pub mod mixer;
+pub mod preroll;
+#[cfg(target_os = "linux")]
+pub mod pulse;
+pub mod quality;
+pub mod recovery;
+pub mod resample;
+pub mod segments;
+pub mod stats;
+pub mod storage;
+pub mod vad;
+pub mod watcher;
+pub mod waveform;
 
-pub use capture::AudioRecorder;
-pub use devices::{list_audio_devices, AudioDevice};
+pub use capture::{companion_files, AudioRecorder, RecordingOptions};
+pub use devices::{
+    device_capabilities, list_audio_devices, AudioDevice, DeviceCapabilities, InputConfig,
+};
+pub use edit::{extract_clip, redact_recording_files, RedactionMode, TimeRange};
+pub use enhance::{enhance_file, EnhancementSettings};
+pub use events::RecorderEvent;
+pub use import::{import_to_wav, SUPPORTED_EXTENSIONS};
+pub use mixer::RecordingLayout;
+pub use quality::{analyze_quality, QualityReport};
+pub use recovery::repair_recordings;
+pub use segments::{
+    recording_parts, rename_recording, segment_manifest, segment_path, AudioSegment,
+};
+pub use storage::{compress_recording, remove_partial_archives, StorageCodec};
+pub use vad::{analyze_speech, SilenceMap, SpeechSegment};
+pub use watcher::spawn_device_watcher;
+pub use waveform::{analyze_waveform, WaveformPeaks, WaveformSlice, SAMPLES_PER_PEAK};
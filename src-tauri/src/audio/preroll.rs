@@ -0,0 +1,52 @@
+use std::collections::VecDeque;
+
+/// Limite alla durata del pre-roll, per non tenere in memoria minuti di
+/// audio a piena qualità
+pub const MAX_PRE_ROLL_SECONDS: u32 = 60;
+
+/// Ultimi secondi catturati da una sorgente mentre la registrazione è
+/// armata. Contiene campioni interleaved alla frequenza e con i canali del
+/// dispositivo, e scarta i frame più vecchi quando è pieno.
+pub struct PreRollBuffer {
+    samples: VecDeque<f32>,
+    /// Campioni massimi, sempre un multiplo dei canali
+    capacity: usize,
+    sample_rate: u32,
+    channels: usize,
+}
+
+impl PreRollBuffer {
+    pub fn new(seconds: f64, sample_rate: u32, channels: u16) -> Self {
+        let channels = channels.max(1) as usize;
+        let frames = (seconds.max(0.0) * sample_rate as f64) as usize;
+        let capacity = frames * channels;
+
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+            sample_rate,
+            channels,
+        }
+    }
+
+    /// Secondi di audio nel buffer
+    pub fn seconds(&self) -> f64 {
+        (self.samples.len() / self.channels) as f64 / self.sample_rate.max(1) as f64
+    }
+
+    /// Aggiunge frame completi in coda, scartando i più vecchi
+    pub fn push(&mut self, data: &[f32]) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        self.samples.extend(data);
+        let excess = self.samples.len().saturating_sub(self.capacity);
+        self.samples.drain(..excess);
+    }
+
+    /// Svuota il buffer restituendo i campioni dal più vecchio
+    pub fn take(&mut self) -> Vec<f32> {
+        self.samples.drain(..).collect()
+    }
+}
@@ -0,0 +1,243 @@
+//! Cattura dell'audio di sistema su Linux tramite le sorgenti "monitor"
+//! di PulseAudio (o PipeWire con pipewire-pulse).
+//!
+//! cpal passa da ALSA, che non espone le sorgenti monitor come dispositivi:
+//! le registriamo con `parec`, che si collega direttamente alla sorgente e
+//! scrive campioni float grezzi su stdout. Così la scelta della sorgente non
+//! passa dall'ambiente del processo, condiviso con cpal e gli altri thread.
+
+use super::capture::AudioError;
+use super::input::{enqueue_samples, StreamShared};
+use cpal::traits::{DeviceTrait, HostTrait};
+use rtrb::Producer;
+use std::io::Read;
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Latenza chiesta a `parec`: blocchi piccoli come quelli di una callback cpal
+const PAREC_LATENCY_MSEC: u32 = 20;
+
+#[derive(Debug, Clone)]
+pub struct MonitorSource {
+    pub name: String,
+    pub description: String,
+}
+
+/// Elenca le sorgenti monitor dei sink di output usando `pactl`.
+pub fn list_monitor_sources() -> Vec<MonitorSource> {
+    let output = match Command::new("pactl")
+        .args(["list", "sources"])
+        .env("LC_ALL", "C")
+        .output()
+    {
+        Ok(output) if output.status.success() => output,
+        Ok(output) => {
+            eprintln!(
+                "pactl ha restituito un errore: {}",
+                String::from_utf8_lossy(&output.stderr)
+            );
+            return Vec::new();
+        }
+        Err(e) => {
+            eprintln!("pactl non disponibile: {}", e);
+            return Vec::new();
+        }
+    };
+
+    parse_monitor_sources(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_monitor_sources(output: &str) -> Vec<MonitorSource> {
+    let mut sources = Vec::new();
+    let mut name: Option<String> = None;
+    let mut description: Option<String> = None;
+    let mut is_monitor = false;
+
+    let mut flush =
+        |name: &mut Option<String>, description: &mut Option<String>, is_monitor: bool| {
+            if let Some(n) = name.take() {
+                if is_monitor {
+                    sources.push(MonitorSource {
+                        description: description.take().unwrap_or_else(|| n.clone()),
+                        name: n,
+                    });
+                }
+            }
+            *description = None;
+        };
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+
+        if trimmed.starts_with("Source #") {
+            flush(&mut name, &mut description, is_monitor);
+            is_monitor = false;
+        } else if let Some(value) = trimmed.strip_prefix("Name:") {
+            name = Some(value.trim().to_string());
+        } else if let Some(value) = trimmed.strip_prefix("Description:") {
+            description = Some(value.trim().to_string());
+        } else if let Some(value) = trimmed.strip_prefix("Monitor of Sink:") {
+            is_monitor = value.trim() != "n/a";
+        }
+    }
+    flush(&mut name, &mut description, is_monitor);
+
+    sources
+}
+
+/// Trova il dispositivo ALSA che instrada verso il server PulseAudio.
+pub fn find_pulse_device(host: &cpal::Host) -> Option<cpal::Device> {
+    let devices: Vec<cpal::Device> = host.input_devices().ok()?.collect();
+
+    ["pulse", "pipewire", "default"].iter().find_map(|wanted| {
+        devices
+            .iter()
+            .find(|d| d.name().map(|n| n == *wanted).unwrap_or(false))
+            .cloned()
+    })
+}
+
+/// Registrazione di una sorgente monitor tramite `parec`. Il processo
+/// viene terminato quando lo stream viene chiuso.
+pub struct MonitorStream {
+    child: Child,
+    closing: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+}
+
+/// Avvia `parec` sulla sorgente `source` e accoda i campioni in `producer`
+/// come farebbe la callback di uno stream cpal. Se `parec` termina da solo
+/// (sorgente rimossa, server riavviato) viene impostato `device_lost`.
+pub fn open_monitor_stream(
+    source: &str,
+    config: &cpal::StreamConfig,
+    mut producer: Producer<f32>,
+    shared: StreamShared,
+) -> Result<MonitorStream, AudioError> {
+    let mut child = Command::new("parec")
+        .arg(format!("--device={}", source))
+        .arg("--format=float32le")
+        .arg(format!("--rate={}", config.sample_rate.0))
+        .arg(format!("--channels={}", config.channels))
+        .arg(format!("--latency-msec={}", PAREC_LATENCY_MSEC))
+        .arg("--raw")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| AudioError::StreamError(format!("parec non disponibile: {}", e)))?;
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| AudioError::StreamError("Output di parec non disponibile".to_string()))?;
+
+    let closing = Arc::new(AtomicBool::new(false));
+    let stopped = closing.clone();
+    let channels = (config.channels as usize).max(1);
+    let reader = thread::spawn(move || {
+        let mut bytes = vec![0u8; 4096];
+        let mut samples = Vec::with_capacity(bytes.len() / 4);
+        // Byte di un campione rimasto a metà fra due letture
+        let mut pending = Vec::with_capacity(4);
+
+        loop {
+            let read = match stdout.read(&mut bytes) {
+                Ok(0) | Err(_) => break,
+                Ok(read) => read,
+            };
+            pending.extend_from_slice(&bytes[..read]);
+            let complete = pending.len() - pending.len() % 4;
+            samples.clear();
+            samples.extend(
+                pending[..complete]
+                    .chunks_exact(4)
+                    .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            );
+            pending.drain(..complete);
+
+            enqueue_samples(&mut producer, &shared, channels, &samples);
+        }
+
+        if !stopped.load(Ordering::SeqCst) {
+            eprintln!("Errore stream audio: parec terminato");
+            shared.device_lost.store(true, Ordering::SeqCst);
+        }
+    });
+
+    Ok(MonitorStream {
+        child,
+        closing,
+        reader: Some(reader),
+    })
+}
+
+impl Drop for MonitorStream {
+    fn drop(&mut self) {
+        self.closing.store(true, Ordering::SeqCst);
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACTL_OUTPUT: &str = "\
+Source #47
+\tState: SUSPENDED
+\tName: alsa_output.pci-0000_00_1f.3.analog-stereo.monitor
+\tDescription: Monitor of Built-in Audio Analog Stereo
+\tDriver: PipeWire
+\tSample Specification: s32le 2ch 48000Hz
+\tMonitor of Sink: alsa_output.pci-0000_00_1f.3.analog-stereo
+\tProperties:
+\t\tdevice.description = \"Monitor of Built-in Audio Analog Stereo\"
+\t\tdevice.class = \"monitor\"
+
+Source #48
+\tState: RUNNING
+\tName: alsa_input.pci-0000_00_1f.3.analog-stereo
+\tDescription: Built-in Audio Analog Stereo
+\tMonitor of Sink: n/a
+\tProperties:
+\t\tdevice.description = \"Built-in Audio Analog Stereo\"
+
+Source #52
+\tState: IDLE
+\tName: bluez_output.00_11_22_33_44_55.1.monitor
+\tMonitor of Sink: bluez_output.00_11_22_33_44_55.1
+";
+
+    #[test]
+    fn only_monitor_sources_are_listed() {
+        let sources = parse_monitor_sources(PACTL_OUTPUT);
+        let names: Vec<&str> = sources.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "alsa_output.pci-0000_00_1f.3.analog-stereo.monitor",
+                "bluez_output.00_11_22_33_44_55.1.monitor",
+            ]
+        );
+    }
+
+    #[test]
+    fn description_falls_back_to_name() {
+        let sources = parse_monitor_sources(PACTL_OUTPUT);
+        assert_eq!(
+            sources[0].description,
+            "Monitor of Built-in Audio Analog Stereo"
+        );
+        assert_eq!(sources[1].description, sources[1].name);
+    }
+
+    #[test]
+    fn empty_output_has_no_sources() {
+        assert!(parse_monitor_sources("").is_empty());
+    }
+}
@@ -0,0 +1,486 @@
+//! Diagnostica della qualità di registrazione.
+//!
+//! Ogni traccia viene analizzata a blocchi di 50 ms sul PCM originale del
+//! dispositivo: clipping, livello, offset DC, buchi di silenzio digitale
+//! (campioni esattamente a zero, tipici di un microfono disattivato) e un
+//! rapporto segnale/rumore stimato dall'istogramma dei livelli. Durante la
+//! registrazione i problemi vengono segnalati subito; a fine cattura si
+//! ottiene un riepilogo per traccia.
+
+use super::capture::AudioError;
+use super::import::SampleSink;
+use super::segments::decode_recording;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Soglia oltre la quale un campione è considerato in clipping
+const CLIP_THRESHOLD: f32 = 0.999;
+/// Durata di un blocco di analisi
+const WINDOW_SECONDS: f64 = 0.05;
+/// Estremo inferiore dell'istogramma dei livelli (passi di 1 dB fino a 0)
+const FLOOR_DB: f64 = -120.0;
+const HISTOGRAM_BINS: usize = 121;
+
+/// Percentuale di campioni in clipping in un secondo che fa scattare
+/// l'avviso
+const CLIPPING_WARNING_PERCENT: f64 = 0.1;
+/// Percentuale di campioni in clipping sull'intera traccia che la segna
+/// nel riepilogo
+const CLIPPING_SUMMARY_PERCENT: f64 = 0.01;
+/// Livello sotto il quale il segnale è considerato troppo basso
+const LOW_SIGNAL_DB: f64 = -55.0;
+/// Secondi consecutivi di segnale troppo basso prima dell'avviso
+const LOW_SIGNAL_SECONDS: f64 = 30.0;
+/// Offset DC (in frazione del fondo scala) oltre il quale si segnala
+const DC_OFFSET_THRESHOLD: f64 = 0.02;
+/// Secondi su cui si misura l'offset DC durante la registrazione
+const DC_BLOCK_SECONDS: f64 = 10.0;
+/// Durata minima di un buco di silenzio digitale
+const DROPOUT_SECONDS: f64 = 0.5;
+/// Audio necessario prima di stimare il rapporto segnale/rumore
+const SNR_MIN_SECONDS: f64 = 30.0;
+/// Rapporto segnale/rumore sotto il quale si segnala
+const LOW_SNR_DB: f64 = 10.0;
+/// Lo stesso problema viene segnalato al più una volta in questo intervallo
+const WARNING_INTERVAL_SECONDS: f64 = 60.0;
+
+/// Problemi di qualità rilevabili
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QualityIssue {
+    Clipping,
+    LowSignal,
+    DcOffset,
+    Dropout,
+    LowSnr,
+}
+
+impl QualityIssue {
+    const ALL: [QualityIssue; 5] = [
+        QualityIssue::Clipping,
+        QualityIssue::LowSignal,
+        QualityIssue::DcOffset,
+        QualityIssue::Dropout,
+        QualityIssue::LowSnr,
+    ];
+
+    fn index(self) -> usize {
+        Self::ALL.iter().position(|i| *i == self).unwrap_or(0)
+    }
+}
+
+/// Problema rilevato durante la registrazione. `value` dipende dal tipo:
+/// percentuale di campioni in clipping nell'ultimo secondo, livello in dBFS
+/// per il segnale basso, offset DC, durata del buco in secondi o rapporto
+/// segnale/rumore in dB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QualityWarning {
+    pub issue: QualityIssue,
+    pub offset_seconds: f64,
+    pub value: f64,
+}
+
+/// Riepilogo della qualità di una traccia
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackQuality {
+    pub track: usize,
+    pub device_id: Option<String>,
+    pub duration_seconds: f64,
+    /// Picco assoluto in dBFS
+    pub peak_db: f64,
+    /// Livello mediano dei blocchi con segnale, in dBFS
+    pub median_db: Option<f64>,
+    pub clipped_samples: u64,
+    pub clipping_percent: f64,
+    pub dc_offset: f64,
+    pub dropouts: usize,
+    pub dropout_seconds: f64,
+    pub longest_dropout_seconds: f64,
+    /// Livello di fondo stimato (10° percentile dei blocchi), in dBFS
+    pub noise_floor_db: Option<f64>,
+    pub snr_db: Option<f64>,
+    pub issues: Vec<QualityIssue>,
+}
+
+/// Riepilogo della qualità di una registrazione, salvato con la riunione
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct QualityReport {
+    pub tracks: Vec<TrackQuality>,
+}
+
+impl QualityReport {
+    /// Problemi presenti in almeno una traccia
+    pub fn issues(&self) -> Vec<QualityIssue> {
+        QualityIssue::ALL
+            .into_iter()
+            .filter(|issue| self.tracks.iter().any(|t| t.issues.contains(issue)))
+            .collect()
+    }
+}
+
+/// Blocco di analisi in costruzione
+#[derive(Default)]
+struct Window {
+    frames: usize,
+    sum_squares: f64,
+    samples: usize,
+}
+
+/// Analizzatore di una traccia
+pub struct QualityAnalyzer {
+    sample_rate: u32,
+    channels: usize,
+    window_frames: usize,
+    window: Window,
+    /// Secondi nei blocchi già conclusi
+    elapsed: f64,
+
+    /// Blocchi con segnale per livello, in passi di 1 dB
+    histogram: [u64; HISTOGRAM_BINS],
+    total_samples: u64,
+    peak: f32,
+    clipped_samples: u64,
+    sample_sum: f64,
+
+    /// Campioni e clipping del secondo in corso
+    second_samples: u64,
+    second_clipped: u64,
+    second_elapsed: f64,
+    /// Offset DC del blocco in corso
+    dc_sum: f64,
+    dc_samples: u64,
+    dc_elapsed: f64,
+    low_signal_seconds: f64,
+    /// Frame consecutivi a zero
+    zero_frames: usize,
+    dropout_warned: bool,
+    dropouts: usize,
+    dropout_seconds: f64,
+    longest_dropout_seconds: f64,
+
+    /// Ultimo avviso per tipo di problema
+    last_warning: [Option<f64>; 5],
+    last_snr_check: f64,
+}
+
+impl QualityAnalyzer {
+    pub fn new(sample_rate: u32, channels: u16) -> Self {
+        let mut analyzer = Self {
+            sample_rate: 0,
+            channels: 1,
+            window_frames: 1,
+            window: Window::default(),
+            elapsed: 0.0,
+            histogram: [0; HISTOGRAM_BINS],
+            total_samples: 0,
+            peak: 0.0,
+            clipped_samples: 0,
+            sample_sum: 0.0,
+            second_samples: 0,
+            second_clipped: 0,
+            second_elapsed: 0.0,
+            dc_sum: 0.0,
+            dc_samples: 0,
+            dc_elapsed: 0.0,
+            low_signal_seconds: 0.0,
+            zero_frames: 0,
+            dropout_warned: false,
+            dropouts: 0,
+            dropout_seconds: 0.0,
+            longest_dropout_seconds: 0.0,
+            last_warning: [None; 5],
+            last_snr_check: 0.0,
+        };
+        analyzer.set_format(sample_rate, channels);
+        analyzer
+    }
+
+    /// Il dispositivo della traccia è cambiato: le statistiche proseguono
+    pub fn set_format(&mut self, sample_rate: u32, channels: u16) {
+        if self.window.frames > 0 {
+            let mut warnings = Vec::new();
+            self.close_window(&mut warnings);
+        }
+        self.close_dropout();
+
+        self.sample_rate = sample_rate.max(1);
+        self.channels = channels.max(1) as usize;
+        self.window_frames = ((self.sample_rate as f64 * WINDOW_SECONDS) as usize).max(1);
+    }
+
+    /// Posizione corrente nella traccia, in secondi
+    fn position(&self) -> f64 {
+        self.elapsed + self.window.frames as f64 / self.sample_rate as f64
+    }
+
+    /// Analizza campioni interleaved e aggiunge a `warnings` i problemi
+    /// rilevati
+    pub fn process(&mut self, data: &[f32], warnings: &mut Vec<QualityWarning>) {
+        for frame in data.chunks_exact(self.channels) {
+            let mut all_zero = true;
+            for &sample in frame {
+                let magnitude = sample.abs();
+                all_zero &= sample == 0.0;
+                self.peak = self.peak.max(magnitude);
+                if magnitude >= CLIP_THRESHOLD {
+                    self.clipped_samples += 1;
+                    self.second_clipped += 1;
+                }
+                self.sample_sum += sample as f64;
+                self.dc_sum += sample as f64;
+                self.window.sum_squares += (sample as f64) * (sample as f64);
+            }
+
+            let samples = frame.len() as u64;
+            self.total_samples += samples;
+            self.second_samples += samples;
+            self.dc_samples += samples;
+            self.window.samples += frame.len();
+
+            if all_zero {
+                self.zero_frames += 1;
+                let seconds = self.zero_frames as f64 / self.sample_rate as f64;
+                if !self.dropout_warned && seconds >= DROPOUT_SECONDS {
+                    self.dropout_warned = true;
+                    let offset_seconds = self.position() - seconds;
+                    self.warn(warnings, QualityIssue::Dropout, offset_seconds, seconds);
+                }
+            } else {
+                self.close_dropout();
+            }
+
+            self.window.frames += 1;
+            if self.window.frames >= self.window_frames {
+                self.close_window(warnings);
+            }
+        }
+    }
+
+    fn close_window(&mut self, warnings: &mut Vec<QualityWarning>) {
+        let window = std::mem::take(&mut self.window);
+        let seconds = window.frames as f64 / self.sample_rate as f64;
+        self.elapsed += seconds;
+
+        let level_db = if window.sum_squares > 0.0 {
+            let mean_square = window.sum_squares / window.samples.max(1) as f64;
+            let db = 10.0 * mean_square.log10();
+            let bin = (db - FLOOR_DB)
+                .round()
+                .clamp(0.0, (HISTOGRAM_BINS - 1) as f64) as usize;
+            self.histogram[bin] += 1;
+            Some(db)
+        } else {
+            None
+        };
+
+        // Segnale troppo basso (i buchi a zero hanno già un loro avviso)
+        match level_db {
+            Some(db) if db < LOW_SIGNAL_DB => {
+                self.low_signal_seconds += seconds;
+                if self.low_signal_seconds >= LOW_SIGNAL_SECONDS {
+                    let offset_seconds = self.elapsed - self.low_signal_seconds;
+                    self.warn(warnings, QualityIssue::LowSignal, offset_seconds, db);
+                }
+            }
+            Some(_) => self.low_signal_seconds = 0.0,
+            None => {}
+        }
+
+        self.second_elapsed += seconds;
+        if self.second_elapsed >= 1.0 {
+            let percent = self.second_clipped as f64 * 100.0 / self.second_samples.max(1) as f64;
+            if percent >= CLIPPING_WARNING_PERCENT {
+                let offset_seconds = self.elapsed - self.second_elapsed;
+                self.warn(warnings, QualityIssue::Clipping, offset_seconds, percent);
+            }
+            self.second_elapsed = 0.0;
+            self.second_samples = 0;
+            self.second_clipped = 0;
+        }
+
+        self.dc_elapsed += seconds;
+        if self.dc_elapsed >= DC_BLOCK_SECONDS {
+            let dc = self.dc_sum / self.dc_samples.max(1) as f64;
+            if dc.abs() >= DC_OFFSET_THRESHOLD {
+                let offset_seconds = self.elapsed - self.dc_elapsed;
+                self.warn(warnings, QualityIssue::DcOffset, offset_seconds, dc);
+            }
+            self.dc_elapsed = 0.0;
+            self.dc_sum = 0.0;
+            self.dc_samples = 0;
+        }
+
+        if self.elapsed - self.last_snr_check >= SNR_MIN_SECONDS {
+            self.last_snr_check = self.elapsed;
+            if let Some((_, snr)) = self.snr() {
+                if snr < LOW_SNR_DB {
+                    let offset_seconds = self.elapsed;
+                    self.warn(warnings, QualityIssue::LowSnr, offset_seconds, snr);
+                }
+            }
+        }
+    }
+
+    /// Chiude l'eventuale buco di silenzio digitale in corso
+    fn close_dropout(&mut self) {
+        let seconds = self.zero_frames as f64 / self.sample_rate.max(1) as f64;
+        if seconds >= DROPOUT_SECONDS {
+            self.dropouts += 1;
+            self.dropout_seconds += seconds;
+            self.longest_dropout_seconds = self.longest_dropout_seconds.max(seconds);
+        }
+        self.zero_frames = 0;
+        self.dropout_warned = false;
+    }
+
+    fn warn(
+        &mut self,
+        warnings: &mut Vec<QualityWarning>,
+        issue: QualityIssue,
+        offset_seconds: f64,
+        value: f64,
+    ) {
+        let last = &mut self.last_warning[issue.index()];
+        if let Some(last) = last {
+            if self.elapsed - *last < WARNING_INTERVAL_SECONDS {
+                return;
+            }
+        }
+        *last = Some(self.elapsed);
+        warnings.push(QualityWarning {
+            issue,
+            offset_seconds: offset_seconds.max(0.0),
+            value,
+        });
+    }
+
+    /// Percentile `p` (fra 0 e 1) dei livelli dei blocchi con segnale
+    fn percentile_db(&self, p: f64) -> Option<f64> {
+        let total: u64 = self.histogram.iter().sum();
+        if total == 0 {
+            return None;
+        }
+
+        let target = ((total as f64 * p).ceil() as u64).max(1);
+        let mut count = 0;
+        for (bin, &n) in self.histogram.iter().enumerate() {
+            count += n;
+            if count >= target {
+                return Some(FLOOR_DB + bin as f64);
+            }
+        }
+        None
+    }
+
+    /// Livello di fondo e rapporto segnale/rumore: il fondo è il 10°
+    /// percentile dei blocchi, il parlato il 95°
+    fn snr(&self) -> Option<(f64, f64)> {
+        let windows: u64 = self.histogram.iter().sum();
+        if (windows as f64) * WINDOW_SECONDS < SNR_MIN_SECONDS {
+            return None;
+        }
+        let noise = self.percentile_db(0.10)?;
+        let signal = self.percentile_db(0.95)?;
+        Some((noise, signal - noise))
+    }
+
+    /// Conclude l'analisi e restituisce il riepilogo della traccia
+    pub fn finish(&mut self, track: usize, device_id: Option<String>) -> TrackQuality {
+        if self.window.frames > 0 {
+            let mut warnings = Vec::new();
+            self.close_window(&mut warnings);
+        }
+        self.close_dropout();
+
+        let clipping_percent =
+            self.clipped_samples as f64 * 100.0 / self.total_samples.max(1) as f64;
+        let dc_offset = self.sample_sum / self.total_samples.max(1) as f64;
+        let median_db = self.percentile_db(0.5);
+        let (noise_floor_db, snr_db) = match self.snr() {
+            Some((noise, snr)) => (Some(noise), Some(snr)),
+            None => (None, None),
+        };
+
+        let mut issues = Vec::new();
+        if clipping_percent >= CLIPPING_SUMMARY_PERCENT {
+            issues.push(QualityIssue::Clipping);
+        }
+        if median_db.map(|db| db < LOW_SIGNAL_DB).unwrap_or(true) {
+            issues.push(QualityIssue::LowSignal);
+        }
+        if dc_offset.abs() >= DC_OFFSET_THRESHOLD {
+            issues.push(QualityIssue::DcOffset);
+        }
+        if self.dropouts > 0 {
+            issues.push(QualityIssue::Dropout);
+        }
+        if snr_db.map(|snr| snr < LOW_SNR_DB).unwrap_or(false) {
+            issues.push(QualityIssue::LowSnr);
+        }
+
+        let peak_db = if self.peak > 0.0 {
+            20.0 * (self.peak as f64).log10()
+        } else {
+            FLOOR_DB
+        };
+
+        TrackQuality {
+            track,
+            device_id,
+            duration_seconds: self.elapsed,
+            peak_db,
+            median_db,
+            clipped_samples: self.clipped_samples,
+            clipping_percent,
+            dc_offset,
+            dropouts: self.dropouts,
+            dropout_seconds: self.dropout_seconds,
+            longest_dropout_seconds: self.longest_dropout_seconds,
+            noise_floor_db,
+            snr_db,
+            issues,
+        }
+    }
+}
+
+/// Invia i campioni decodificati all'analizzatore
+struct QualitySink {
+    analyzer: Option<QualityAnalyzer>,
+    format: (u32, usize),
+    warnings: Vec<QualityWarning>,
+}
+
+impl SampleSink for QualitySink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        let analyzer = self
+            .analyzer
+            .get_or_insert_with(|| QualityAnalyzer::new(sample_rate, channels as u16));
+        if self.format != (sample_rate, channels) {
+            analyzer.set_format(sample_rate, channels as u16);
+            self.format = (sample_rate, channels);
+        }
+
+        analyzer.process(data, &mut self.warnings);
+        self.warnings.clear();
+        Ok(())
+    }
+}
+
+/// Analizza la qualità di un file già su disco (registrazioni precedenti,
+/// file importati). Il file è trattato come una sola traccia.
+pub fn analyze_quality(path: &Path) -> Result<QualityReport, AudioError> {
+    let mut sink = QualitySink {
+        analyzer: None,
+        format: (0, 0),
+        warnings: Vec::new(),
+    };
+    decode_recording(path, &mut sink)?;
+
+    let analyzer = sink
+        .analyzer
+        .as_mut()
+        .ok_or_else(|| AudioError::DecodeError("Nessun campione audio nel file".to_string()))?;
+    Ok(QualityReport {
+        tracks: vec![analyzer.finish(0, None)],
+    })
+}
@@ -0,0 +1,232 @@
+//! Riparazione dei WAV rimasti senza header definitivo dopo un crash.
+//!
+//! Durante la registrazione l'header viene aggiornato periodicamente, ma
+//! se l'app termina in modo anomalo le dimensioni scritte nei chunk RIFF e
+//! `data` restano indietro rispetto ai campioni effettivamente su disco.
+
+use super::capture::AudioError;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Posizione dei campi da correggere nell'header
+struct WavLayout {
+    /// Offset del campo dimensione del chunk `data`
+    data_size_offset: u64,
+    /// Offset del primo byte di audio
+    data_start: u64,
+    /// Dimensione di un frame in byte
+    block_align: u64,
+    riff_size: u32,
+    data_size: u32,
+}
+
+/// Risultato della riparazione di un file
+#[derive(Debug, Clone)]
+pub struct RepairedWav {
+    pub path: PathBuf,
+    pub duration_seconds: f64,
+}
+
+fn read_u16(bytes: &[u8]) -> u16 {
+    u16::from_le_bytes([bytes[0], bytes[1]])
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+fn invalid(path: &Path, reason: &str) -> AudioError {
+    AudioError::FileError(format!("{}: {}", path.display(), reason))
+}
+
+fn read_layout(file: &mut File, path: &Path) -> Result<WavLayout, AudioError> {
+    let mut riff = [0u8; 12];
+    file.read_exact(&mut riff)
+        .map_err(|_| invalid(path, "header RIFF mancante"))?;
+    if &riff[0..4] != b"RIFF" || &riff[8..12] != b"WAVE" {
+        return Err(invalid(path, "non è un file WAVE"));
+    }
+
+    let mut block_align = None;
+    let mut offset = 12u64;
+
+    loop {
+        let mut header = [0u8; 8];
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.read_exact(&mut header))
+            .map_err(|_| invalid(path, "chunk data non trovato"))?;
+
+        let size = read_u32(&header[4..8]);
+
+        match &header[0..4] {
+            b"fmt " => {
+                let mut fmt = [0u8; 16];
+                file.read_exact(&mut fmt)
+                    .map_err(|_| invalid(path, "chunk fmt incompleto"))?;
+                block_align = Some(read_u16(&fmt[12..14]) as u64);
+            }
+            b"data" => {
+                return Ok(WavLayout {
+                    data_size_offset: offset + 4,
+                    data_start: offset + 8,
+                    block_align: block_align
+                        .filter(|&b| b > 0)
+                        .ok_or_else(|| invalid(path, "chunk fmt mancante"))?,
+                    riff_size: read_u32(&riff[4..8]),
+                    data_size: size,
+                });
+            }
+            _ => {}
+        }
+
+        // I chunk hanno lunghezza pari (byte di padding)
+        offset += 8 + size as u64 + (size as u64 & 1);
+    }
+}
+
+/// Corregge le dimensioni nell'header di `path` in base alla lunghezza del
+/// file. Restituisce `None` se l'header era già coerente.
+pub fn repair_wav(path: &Path) -> Result<Option<RepairedWav>, AudioError> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+
+    let layout = read_layout(&mut file, path)?;
+    let file_len = file
+        .metadata()
+        .map_err(|e| AudioError::FileError(e.to_string()))?
+        .len();
+
+    // Un eventuale frame scritto a metà viene scartato
+    let available = file_len.saturating_sub(layout.data_start);
+    let data_size = (available - available % layout.block_align).min(u32::MAX as u64) as u32;
+    let riff_size = (layout.data_start - 8 + data_size as u64).min(u32::MAX as u64) as u32;
+
+    if data_size == layout.data_size && riff_size == layout.riff_size {
+        return Ok(None);
+    }
+
+    let write = |file: &mut File, offset: u64, value: u32| {
+        file.seek(SeekFrom::Start(offset))
+            .and_then(|_| file.write_all(&value.to_le_bytes()))
+            .map_err(|e| AudioError::FileError(e.to_string()))
+    };
+    write(&mut file, 4, riff_size)?;
+    write(&mut file, layout.data_size_offset, data_size)?;
+    file.set_len(layout.data_start + data_size as u64)
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+    file.sync_all()
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+
+    let duration_seconds = hound::WavReader::open(path)
+        .map(|reader| reader.duration() as f64 / reader.spec().sample_rate.max(1) as f64)
+        .unwrap_or(0.0);
+
+    println!(
+        "Header WAV riparato: {} ({:.1}s)",
+        path.display(),
+        duration_seconds
+    );
+
+    Ok(Some(RepairedWav {
+        path: path.to_path_buf(),
+        duration_seconds,
+    }))
+}
+
+/// Ripara tutti i WAV di `dir` rimasti con un header non aggiornato
+pub fn repair_recordings(dir: &Path) -> Vec<RepairedWav> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.extension()
+                .map(|ext| ext.eq_ignore_ascii_case("wav"))
+                .unwrap_or(false)
+        })
+        .filter_map(|path| match repair_wav(&path) {
+            Ok(repaired) => repaired,
+            Err(e) => {
+                eprintln!("Impossibile riparare {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hound::{SampleFormat, WavSpec, WavWriter};
+
+    fn temp_wav(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("recovery-{}-{}.wav", std::process::id(), name))
+    }
+
+    /// WAV stereo a 16 bit con `frames` frame e un header corretto
+    fn write_wav(path: &Path, frames: usize) {
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 16_000,
+            bits_per_sample: 16,
+            sample_format: SampleFormat::Int,
+        };
+        let mut writer = WavWriter::create(path, spec).unwrap();
+        for i in 0..frames * 2 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    fn set_u32(path: &Path, offset: u64, value: u32) {
+        let mut file = OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(offset)).unwrap();
+        file.write_all(&value.to_le_bytes()).unwrap();
+    }
+
+    #[test]
+    fn stale_header_is_repaired() {
+        let path = temp_wav("stale");
+        write_wav(&path, 16_000);
+
+        // Header fermo all'ultimo aggiornamento (0,1 s) e un frame scritto
+        // a metà in coda, come dopo un crash
+        set_u32(&path, 4, 36 + 6_400);
+        set_u32(&path, 40, 6_400);
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&[1, 2, 3]).unwrap();
+        drop(file);
+
+        let repaired = repair_wav(&path).unwrap().expect("header da riparare");
+        assert!((repaired.duration_seconds - 1.0).abs() < 1e-9);
+
+        let reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.duration(), 16_000);
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), 44 + 16_000 * 4);
+
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn consistent_header_is_left_alone() {
+        let path = temp_wav("consistent");
+        write_wav(&path, 100);
+        assert!(repair_wav(&path).unwrap().is_none());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn non_wave_files_are_rejected() {
+        let path = temp_wav("invalid");
+        std::fs::write(&path, b"RIFF\0\0\0\0AVI LIST").unwrap();
+        assert!(repair_wav(&path).is_err());
+        let _ = std::fs::remove_file(&path);
+    }
+}
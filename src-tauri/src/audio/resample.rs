@@ -0,0 +1,278 @@
+use std::f64::consts::PI;
+
+/// Frequenza di campionamento delle registrazioni destinate a Whisper
+pub const TARGET_SAMPLE_RATE: u32 = 16_000;
+
+/// Numero di attraversamenti dello zero del kernel sinc per lato
+const ZERO_CROSSINGS: usize = 8;
+/// Risoluzione della tabella del kernel (campioni per attraversamento)
+const TABLE_RESOLUTION: usize = 128;
+
+/// Converte campioni interleaved in mono facendo la media dei canali.
+pub fn downmix_to_mono(data: &[f32], channels: usize, out: &mut Vec<f32>) {
+    if channels <= 1 {
+        out.extend_from_slice(data);
+        return;
+    }
+
+    let scale = 1.0 / channels as f32;
+    out.extend(
+        data.chunks_exact(channels)
+            .map(|frame| frame.iter().sum::<f32>() * scale),
+    );
+}
+
+/// Resampler mono in streaming basato su interpolazione sinc finestrata.
+///
+/// Mantiene lo stato fra una chiamata e l'altra, quindi può essere
+/// alimentato direttamente con i buffer che arrivano dalla callback cpal.
+pub struct Resampler {
+    /// Passo nominale in campioni di input per ogni campione di output
+    base_step: f64,
+    /// Passo effettivo, corretto per compensare il drift di clock
+    step: f64,
+    /// Nessuna conversione necessaria: i campioni passano invariati
+    passthrough: bool,
+    /// Taglio del filtro anti-aliasing, relativo alla Nyquist di input
+    cutoff: f64,
+    /// Semi-ampiezza del kernel in campioni di input
+    half_width: usize,
+    /// Kernel sinc * Blackman campionato su [0, ZERO_CROSSINGS]
+    table: Vec<f32>,
+    /// Campioni di input non ancora consumati (storia inclusa)
+    buffer: Vec<f32>,
+    /// Posizione del prossimo campione di output dentro `buffer`
+    position: f64,
+}
+
+impl Resampler {
+    pub fn new(input_rate: u32, output_rate: u32) -> Self {
+        let step = input_rate as f64 / output_rate as f64;
+        let cutoff = (1.0 / step).min(1.0);
+        let half_width = (ZERO_CROSSINGS as f64 / cutoff).ceil() as usize;
+
+        let table_len = ZERO_CROSSINGS * TABLE_RESOLUTION + 1;
+        let table = (0..table_len)
+            .map(|i| {
+                let x = i as f64 / TABLE_RESOLUTION as f64;
+                let sinc = if i == 0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+                let t = 0.5 + 0.5 * x / ZERO_CROSSINGS as f64;
+                let window = 0.42 - 0.5 * (2.0 * PI * t).cos() + 0.08 * (4.0 * PI * t).cos();
+                (sinc * window) as f32
+            })
+            .collect();
+
+        Self {
+            base_step: step,
+            step,
+            passthrough: input_rate == output_rate,
+            cutoff,
+            half_width,
+            table,
+            buffer: vec![0.0; half_width],
+            position: half_width as f64,
+        }
+    }
+
+    /// Indica se il resampler lascia passare i campioni invariati
+    pub fn is_passthrough(&self) -> bool {
+        self.passthrough
+    }
+
+    /// Corregge il rapporto di conversione di un fattore vicino a 1.
+    ///
+    /// Valori maggiori di 1 consumano l'input più velocemente (meno campioni
+    /// in uscita), valori minori lo rallentano.
+    pub fn set_ratio_adjustment(&mut self, factor: f64) {
+        self.step = self.base_step * factor;
+        if factor != 1.0 {
+            self.passthrough = false;
+        }
+    }
+
+    /// Ricampiona `input` accodando il risultato in `out`.
+    pub fn process(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        if self.is_passthrough() {
+            out.extend_from_slice(input);
+            // La storia del kernel resta aggiornata: se una correzione del
+            // drift disattiva il passthrough l'interpolazione prosegue
+            // dall'ultimo campione, senza ripartire dagli zeri iniziali
+            self.buffer.extend_from_slice(input);
+            let excess = self.buffer.len().saturating_sub(self.half_width);
+            self.buffer.drain(..excess);
+            return;
+        }
+
+        self.buffer.extend_from_slice(input);
+
+        while (self.position.floor() as usize) + self.half_width < self.buffer.len() {
+            out.push(self.interpolate(self.position));
+            self.position += self.step;
+        }
+
+        // Scarta i campioni che non servono più al kernel
+        let keep_from = (self.position.floor() as usize).saturating_sub(self.half_width);
+        if keep_from > 0 {
+            self.buffer.drain(..keep_from);
+            self.position -= keep_from as f64;
+        }
+    }
+
+    fn interpolate(&self, position: f64) -> f32 {
+        let center = position.floor() as usize;
+        let first = center + 1 - self.half_width;
+        let last = center + self.half_width;
+
+        let mut acc = 0.0f32;
+        for n in first..=last {
+            let distance = (position - n as f64).abs() * self.cutoff;
+            acc += self.buffer[n] * self.kernel(distance);
+        }
+
+        acc * self.cutoff as f32
+    }
+
+    fn kernel(&self, distance: f64) -> f32 {
+        if distance >= ZERO_CROSSINGS as f64 {
+            return 0.0;
+        }
+
+        let index = distance * TABLE_RESOLUTION as f64;
+        let i = index.floor() as usize;
+        let frac = (index - i as f64) as f32;
+        let a = self.table[i];
+        let b = self.table.get(i + 1).copied().unwrap_or(0.0);
+        a + (b - a) * frac
+    }
+}
+
+/// Converte un campione float normalizzato in PCM a 16 bit.
+pub fn to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downmix_averages_channels() {
+        let mut out = Vec::new();
+        downmix_to_mono(&[1.0, 0.0, 0.5, 0.5, -1.0, 1.0], 2, &mut out);
+        assert_eq!(out, vec![0.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn downmix_mono_passes_through() {
+        let mut out = vec![0.25];
+        downmix_to_mono(&[0.1, 0.2], 1, &mut out);
+        assert_eq!(out, vec![0.25, 0.1, 0.2]);
+    }
+
+    #[test]
+    fn downmix_drops_incomplete_frame() {
+        let mut out = Vec::new();
+        downmix_to_mono(&[0.2, 0.4, 0.6], 2, &mut out);
+        assert_eq!(out.len(), 1);
+        assert!((out[0] - 0.3).abs() < 1e-6);
+    }
+
+    #[test]
+    fn output_length_follows_ratio() {
+        let mut resampler = Resampler::new(48_000, TARGET_SAMPLE_RATE);
+        let mut out = Vec::new();
+        resampler.process(&vec![0.0; 48_000], &mut out);
+
+        // Manca solo la latenza del kernel, che resta nel buffer
+        let latency = resampler.half_width / 3 + 1;
+        assert!(out.len() <= 16_000);
+        assert!(out.len() >= 16_000 - latency, "{} campioni", out.len());
+    }
+
+    #[test]
+    fn chunked_input_gives_same_output() {
+        let input: Vec<f32> = (0..4_410).map(|i| (i as f32 * 0.01).sin()).collect();
+
+        let mut whole = Vec::new();
+        Resampler::new(44_100, TARGET_SAMPLE_RATE).process(&input, &mut whole);
+
+        let mut resampler = Resampler::new(44_100, TARGET_SAMPLE_RATE);
+        let mut chunked = Vec::new();
+        for chunk in input.chunks(441) {
+            resampler.process(chunk, &mut chunked);
+        }
+
+        assert_eq!(whole.len(), chunked.len());
+        for (a, b) in whole.iter().zip(&chunked) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn dc_gain_is_unity() {
+        for input_rate in [48_000, 44_100, 8_000] {
+            let mut resampler = Resampler::new(input_rate, TARGET_SAMPLE_RATE);
+            let mut out = Vec::new();
+            resampler.process(&vec![0.5; input_rate as usize], &mut out);
+
+            // Si salta l'attacco, dove il kernel vede ancora gli zeri iniziali
+            for &sample in &out[out.len() / 2..] {
+                assert!((sample - 0.5).abs() < 0.005, "{} Hz", input_rate);
+            }
+        }
+    }
+
+    #[test]
+    fn ratio_adjustment_changes_output_length() {
+        let mut faster = Resampler::new(48_000, TARGET_SAMPLE_RATE);
+        faster.set_ratio_adjustment(1.01);
+        let mut out = Vec::new();
+        faster.process(&vec![0.0; 48_000], &mut out);
+        assert!(out.len() < 15_900);
+    }
+
+    #[test]
+    fn same_rate_is_passthrough() {
+        let mut resampler = Resampler::new(16_000, TARGET_SAMPLE_RATE);
+        assert!(resampler.is_passthrough());
+        let mut out = Vec::new();
+        resampler.process(&[0.1, -0.2], &mut out);
+        assert_eq!(out, vec![0.1, -0.2]);
+    }
+
+    #[test]
+    fn leaving_passthrough_is_seamless() {
+        let signal = |position: f64| (position * 0.05).sin() as f32 * 0.5;
+        let input: Vec<f32> = (0..3_200).map(|i| signal(i as f64)).collect();
+        let mut resampler = Resampler::new(16_000, TARGET_SAMPLE_RATE);
+        let mut out = Vec::new();
+        for (index, chunk) in input.chunks(160).enumerate() {
+            if index == 10 {
+                // Il kernel riparte dagli ultimi campioni passati invariati
+                let history = &input[1_600 - resampler.half_width..1_600];
+                assert_eq!(resampler.buffer, history);
+                resampler.set_ratio_adjustment(1.05);
+            }
+            resampler.process(chunk, &mut out);
+        }
+
+        // Dopo il cambio l'interpolazione riprende dal campione successivo
+        assert!(out.len() > 3_000);
+        for (index, &sample) in out.iter().enumerate() {
+            let position = if index < 1_600 {
+                index as f64
+            } else {
+                1_600.0 + (index - 1_600) as f64 * 1.05
+            };
+            let expected = signal(position);
+            assert!((sample - expected).abs() < 0.01, "campione {}", index);
+        }
+    }
+
+    #[test]
+    fn to_i16_clamps() {
+        assert_eq!(to_i16(2.0), i16::MAX);
+        assert_eq!(to_i16(-2.0), -i16::MAX);
+        assert_eq!(to_i16(0.0), 0);
+    }
+}
@@ -0,0 +1,273 @@
+//! Registrazioni divise in segmenti.
+//!
+//! Un WAV RIFF non può superare i 4 GB: le registrazioni lunghe (giornate
+//! intere, multitraccia a 48 kHz) passano a un nuovo file prima del limite.
+//! Il primo segmento mantiene il percorso della registrazione, i successivi
+//! si chiamano `<nome>_part2`, `<nome>_part3`, ... con la stessa estensione,
+//! così restano file accompagnatori della registrazione (vedi
+//! `companion_files`) e seguono compressione ed eliminazione.
+
+use super::capture::AudioError;
+use super::import::{decode_file, SampleSink};
+use hound::{WavSpec, WavWriter};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+
+/// Dati audio per segmento: metà del limite RIFF, così anche i lettori che
+/// usano dimensioni a 32 bit con segno aprono i file
+const MAX_SEGMENT_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// Segmento di una registrazione, con la sua posizione nella timeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AudioSegment {
+    pub index: usize,
+    pub path: String,
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+/// Percorso del segmento `index` (da 0) della registrazione `path`
+pub fn segment_path(path: &Path, index: usize) -> PathBuf {
+    if index == 0 {
+        return path.to_path_buf();
+    }
+
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let name = match path.extension() {
+        Some(ext) => format!("{}_part{}.{}", stem, index + 1, ext.to_string_lossy()),
+        None => format!("{}_part{}", stem, index + 1),
+    };
+    path.with_file_name(name)
+}
+
+/// Il file è un segmento successivo al primo di un'altra registrazione
+pub fn is_segment_part(path: &Path) -> bool {
+    path.file_stem()
+        .and_then(|s| s.to_str())
+        .and_then(|s| s.rsplit_once("_part"))
+        .map(|(_, number)| !number.is_empty() && number.bytes().all(|b| b.is_ascii_digit()))
+        .unwrap_or(false)
+}
+
+/// File della registrazione in ordine: il primo e i segmenti successivi
+/// presenti su disco
+pub fn recording_parts(path: &Path) -> Vec<PathBuf> {
+    let mut parts = vec![path.to_path_buf()];
+    loop {
+        let next = segment_path(path, parts.len());
+        if !next.is_file() {
+            return parts;
+        }
+        parts.push(next);
+    }
+}
+
+/// Rinomina la registrazione `from` in `to`, segmenti compresi
+pub fn rename_recording(from: &Path, to: &Path) -> std::io::Result<()> {
+    for (index, part) in recording_parts(from).iter().enumerate() {
+        std::fs::rename(part, segment_path(to, index))?;
+    }
+    Ok(())
+}
+
+/// Segmenti di una registrazione WAV, letti dagli header
+pub fn segment_manifest(path: &Path) -> Result<Vec<AudioSegment>, AudioError> {
+    let mut segments = Vec::new();
+    let mut start_seconds = 0.0;
+
+    for (index, part) in recording_parts(path).into_iter().enumerate() {
+        let reader =
+            hound::WavReader::open(&part).map_err(|e| AudioError::FileError(e.to_string()))?;
+        let duration_seconds = reader.duration() as f64 / reader.spec().sample_rate.max(1) as f64;
+        segments.push(AudioSegment {
+            index,
+            path: part.to_string_lossy().to_string(),
+            start_seconds,
+            duration_seconds,
+        });
+        start_seconds += duration_seconds;
+    }
+
+    Ok(segments)
+}
+
+/// Decodifica tutti i segmenti di una registrazione verso `sink`, come un
+/// unico flusso continuo
+pub(crate) fn decode_recording(
+    path: &Path,
+    sink: &mut dyn SampleSink,
+) -> Result<String, AudioError> {
+    let mut codec = String::new();
+    for part in recording_parts(path) {
+        codec = decode_file(&part, sink)?;
+    }
+    Ok(codec)
+}
+
+/// Scrittore WAV che passa a un nuovo segmento prima del limite dei 4 GB
+pub struct SegmentedWavWriter {
+    path: PathBuf,
+    spec: WavSpec,
+    writer: WavWriter<BufWriter<File>>,
+    /// Percorso e primo frame di ogni segmento aperto finora
+    segments: Vec<(PathBuf, u64)>,
+    /// Frame nei segmenti già chiusi
+    closed_frames: u64,
+    max_frames: u32,
+    /// Campioni scritti nel frame corrente
+    frame_position: u16,
+}
+
+impl SegmentedWavWriter {
+    pub fn create(path: &Path, spec: WavSpec) -> Result<Self, hound::Error> {
+        let frame_bytes = spec.channels.max(1) as u64 * (spec.bits_per_sample as u64).div_ceil(8);
+        let max_frames = (MAX_SEGMENT_BYTES / frame_bytes).min(u32::MAX as u64) as u32;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            spec,
+            writer: WavWriter::create(path, spec)?,
+            segments: vec![(path.to_path_buf(), 0)],
+            closed_frames: 0,
+            max_frames,
+            frame_position: 0,
+        })
+    }
+
+    pub fn spec(&self) -> WavSpec {
+        self.spec
+    }
+
+    /// Frame scritti in tutti i segmenti
+    pub fn duration(&self) -> u64 {
+        self.closed_frames + self.writer.duration() as u64
+    }
+
+    pub fn write_sample(&mut self, sample: i16) -> Result<(), hound::Error> {
+        // Si cambia file solo fra un frame e l'altro
+        if self.frame_position == 0 && self.writer.duration() >= self.max_frames {
+            self.roll_over()?;
+        }
+
+        self.writer.write_sample(sample)?;
+        self.frame_position = (self.frame_position + 1) % self.spec.channels.max(1);
+        Ok(())
+    }
+
+    fn roll_over(&mut self) -> Result<(), hound::Error> {
+        let next_path = segment_path(&self.path, self.segments.len());
+        let next = WavWriter::create(&next_path, self.spec)?;
+        let previous = std::mem::replace(&mut self.writer, next);
+
+        self.closed_frames += previous.duration() as u64;
+        previous.finalize()?;
+        println!("Nuovo segmento di registrazione: {}", next_path.display());
+        self.segments.push((next_path, self.closed_frames));
+        Ok(())
+    }
+
+    /// Segmento che contiene l'istante `seconds`, con il suo inizio in
+    /// secondi
+    pub fn segment_at(&self, seconds: f64) -> (&Path, f64) {
+        let frame = (seconds.max(0.0) * self.spec.sample_rate as f64) as u64;
+        let (path, start_frame) = self
+            .segments
+            .iter()
+            .rev()
+            .find(|(_, start)| *start <= frame)
+            .unwrap_or(&self.segments[0]);
+        (path, *start_frame as f64 / self.spec.sample_rate as f64)
+    }
+
+    pub fn flush(&mut self) -> Result<(), hound::Error> {
+        self.writer.flush()
+    }
+
+    pub fn finalize(self) -> Result<(), hound::Error> {
+        self.writer.finalize()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("segments-{}-{}", std::process::id(), name));
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn segment_names() {
+        let path = Path::new("/rec/meeting_2024.wav");
+        assert_eq!(segment_path(path, 0), path);
+        assert_eq!(
+            segment_path(path, 1),
+            Path::new("/rec/meeting_2024_part2.wav")
+        );
+        assert_eq!(
+            segment_path(path, 9),
+            Path::new("/rec/meeting_2024_part10.wav")
+        );
+        assert_eq!(
+            segment_path(Path::new("/rec/meeting"), 2),
+            Path::new("/rec/meeting_part3")
+        );
+    }
+
+    #[test]
+    fn segment_parts_are_recognized() {
+        assert!(is_segment_part(Path::new("/rec/meeting_part2.wav")));
+        assert!(is_segment_part(Path::new("/rec/meeting_part12.flac")));
+        assert!(!is_segment_part(Path::new("/rec/meeting.wav")));
+        assert!(!is_segment_part(Path::new("/rec/meeting_part.wav")));
+        assert!(!is_segment_part(Path::new("/rec/meeting_partner.wav")));
+    }
+
+    #[test]
+    fn writer_rolls_over_and_parts_form_one_timeline() {
+        let dir = temp_dir("rollover");
+        let path = dir.join("meeting.wav");
+        let spec = WavSpec {
+            channels: 2,
+            sample_rate: 100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+
+        let mut writer = SegmentedWavWriter::create(&path, spec).unwrap();
+        writer.max_frames = 100;
+        for i in 0..250 * 2 {
+            writer.write_sample(i as i16).unwrap();
+        }
+        assert_eq!(writer.duration(), 250);
+        assert_eq!(writer.segment_at(0.5), (path.as_path(), 0.0));
+        assert_eq!(writer.segment_at(2.2).1, 2.0);
+        writer.finalize().unwrap();
+
+        let parts = recording_parts(&path);
+        assert_eq!(
+            parts,
+            vec![
+                path.clone(),
+                dir.join("meeting_part2.wav"),
+                dir.join("meeting_part3.wav")
+            ]
+        );
+
+        let manifest = segment_manifest(&path).unwrap();
+        let timeline: Vec<(f64, f64)> = manifest
+            .iter()
+            .map(|s| (s.start_seconds, s.duration_seconds))
+            .collect();
+        assert_eq!(timeline, vec![(0.0, 1.0), (1.0, 1.0), (2.0, 0.5)]);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
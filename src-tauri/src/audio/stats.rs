@@ -0,0 +1,51 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Contatori aggiornati dalla callback audio di una traccia. Sopravvivono
+/// alla riapertura dello stream, così coprono l'intera registrazione.
+#[derive(Default)]
+pub struct CaptureCounters {
+    /// Campioni accodati nel ring buffer
+    captured_samples: AtomicU64,
+    /// Campioni scartati perché il ring buffer era pieno
+    dropped_samples: AtomicU64,
+    /// Callback in cui almeno un campione è stato scartato
+    overruns: AtomicU64,
+}
+
+impl CaptureCounters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, captured: usize, dropped: usize) {
+        self.captured_samples
+            .fetch_add(captured as u64, Ordering::Relaxed);
+        if dropped > 0 {
+            self.dropped_samples
+                .fetch_add(dropped as u64, Ordering::Relaxed);
+            self.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn snapshot(&self, track: usize, device_id: &str) -> TrackStats {
+        TrackStats {
+            track,
+            device_id: device_id.to_string(),
+            captured_samples: self.captured_samples.load(Ordering::Relaxed),
+            dropped_samples: self.dropped_samples.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Statistiche di cattura di una traccia a fine registrazione
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackStats {
+    pub track: usize,
+    /// Ultimo dispositivo usato dalla traccia
+    pub device_id: String,
+    pub captured_samples: u64,
+    pub dropped_samples: u64,
+    pub overruns: u64,
+}
@@ -0,0 +1,423 @@
+//! Compressione delle registrazioni per l'archivio: i WAV a 16 bit prodotti
+//! dalla cattura vengono convertiti in FLAC (senza perdita) o Opus (voce).
+
+use super::capture::AudioError;
+use super::resample::Resampler;
+use flacenc::bitsink::ByteSink;
+use flacenc::component::{BitRepr, StreamInfo};
+use flacenc::constant::{MAX_CHANNELS as FLAC_MAX_CHANNELS, MIN_BLOCK_SIZE};
+use flacenc::error::Verify;
+use flacenc::source::{Fill, FrameBuf};
+use hound::WavReader;
+use md5::{Digest, Md5};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Bitrate Opus per canale: ampiamente sufficiente per il parlato
+const OPUS_BITRATE_PER_CHANNEL: i32 = 24_000;
+
+/// Durata di un pacchetto Opus
+const OPUS_FRAME_MS: usize = 20;
+
+/// Pacchetti per pagina Ogg (circa un secondo), per una ricerca rapida
+const OPUS_PACKETS_PER_PAGE: usize = 50;
+
+/// Formato con cui le registrazioni vengono conservate
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageCodec {
+    #[default]
+    Wav,
+    Flac,
+    Opus,
+}
+
+impl StorageCodec {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StorageCodec::Wav => "wav",
+            StorageCodec::Flac => "flac",
+            StorageCodec::Opus => "opus",
+        }
+    }
+
+    pub fn from_setting(value: &str) -> Option<Self> {
+        match value {
+            "wav" => Some(StorageCodec::Wav),
+            "flac" => Some(StorageCodec::Flac),
+            "opus" => Some(StorageCodec::Opus),
+            _ => None,
+        }
+    }
+}
+
+/// Converte il WAV `wav_path` nel formato `codec`, accanto all'originale, e
+/// rimuove il WAV. Restituisce il percorso del file risultante.
+pub fn compress_recording(wav_path: &Path, codec: StorageCodec) -> Result<PathBuf, AudioError> {
+    if codec == StorageCodec::Wav {
+        return Ok(wav_path.to_path_buf());
+    }
+
+    let reader = WavReader::open(wav_path).map_err(|e| AudioError::FileError(e.to_string()))?;
+    let spec = reader.spec();
+    if spec.sample_format != hound::SampleFormat::Int || spec.bits_per_sample != 16 {
+        return Err(AudioError::FileError(format!(
+            "Formato WAV non supportato: {} bit {:?}",
+            spec.bits_per_sample, spec.sample_format
+        )));
+    }
+
+    // Opus gestisce solo mono e stereo: le registrazioni multitraccia
+    // restano senza perdita
+    let codec = if codec == StorageCodec::Opus && spec.channels > 2 {
+        StorageCodec::Flac
+    } else {
+        codec
+    };
+    if codec == StorageCodec::Flac && spec.channels as usize > FLAC_MAX_CHANNELS {
+        return Err(AudioError::FileError(format!(
+            "FLAC supporta al massimo {} canali",
+            FLAC_MAX_CHANNELS
+        )));
+    }
+
+    let output_path = wav_path.with_extension(codec.as_str());
+    // Si scrive su un file temporaneo: un'interruzione non lascia mai un
+    // archivio incompleto al posto del WAV
+    let partial_path = wav_path.with_extension(format!("{}.part", codec.as_str()));
+
+    let result = match codec {
+        StorageCodec::Flac => encode_flac(reader, &partial_path),
+        StorageCodec::Opus => encode_opus(reader, &partial_path),
+        StorageCodec::Wav => unreachable!(),
+    };
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&partial_path);
+        return Err(e);
+    }
+
+    std::fs::rename(&partial_path, &output_path)
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+    std::fs::remove_file(wav_path).map_err(|e| AudioError::FileError(e.to_string()))?;
+
+    Ok(output_path)
+}
+
+/// Rimuove i file temporanei lasciati da una compressione o da un
+/// oscuramento interrotti: il file originale viene sostituito solo a
+/// operazione completata
+pub fn remove_partial_archives(dir: &Path) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        // `<nome>.redacting.wav` è la copia di lavoro di `redact_recording`,
+        // eventualmente già compressa
+        let is_partial = path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("part"))
+            .unwrap_or(false)
+            || path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .map(|stem| stem.ends_with(".redacting"))
+                .unwrap_or(false);
+        if is_partial {
+            match std::fs::remove_file(&path) {
+                Ok(()) => println!("Rimosso archivio incompleto: {}", path.display()),
+                Err(e) => eprintln!("Impossibile rimuovere {}: {}", path.display(), e),
+            }
+        }
+    }
+}
+
+fn encode_flac(reader: WavReader<BufReader<File>>, output_path: &Path) -> Result<(), AudioError> {
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    let flac_error =
+        |e: &dyn std::fmt::Debug| AudioError::FileError(format!("Errore codifica FLAC: {:?}", e));
+    let io_error = |e: std::io::Error| AudioError::FileError(e.to_string());
+
+    let config = flacenc::config::Encoder::default()
+        .into_verified()
+        .map_err(|(_, e)| flac_error(&e))?;
+    let block_size = config.block_size;
+    let mut stream_info =
+        StreamInfo::new(spec.sample_rate as usize, channels, 16).map_err(|e| flac_error(&e))?;
+
+    let file = File::create(output_path).map_err(io_error)?;
+    let mut writer = BufWriter::new(file);
+    // STREAMINFO viene riscritto a fine codifica, quando durata e MD5 sono noti
+    writer.write_all(b"fLaC").map_err(io_error)?;
+    write_flac_stream_info(&mut writer, &stream_info)?;
+
+    // I blocchi vengono letti, codificati e scritti uno alla volta, senza
+    // caricare il WAV in memoria. L'ultimo blocco è ridimensionato a mano:
+    // FrameBuf non azzera la parte non riempita e ripeterebbe l'audio
+    // precedente.
+    let mut framebuf = FrameBuf::with_size(channels, block_size).map_err(|e| flac_error(&e))?;
+    // L'MD5 di flacenc include il riempimento dell'ultimo blocco: lo si
+    // calcola qui sui soli campioni reali
+    let mut md5 = Md5::new();
+    let mut total_frames = 0usize;
+    let mut samples = reader.into_samples::<i16>();
+    let mut block: Vec<i32> = Vec::with_capacity(block_size * channels);
+    let mut frame_count = 0usize;
+    let mut last_block_size = block_size;
+
+    loop {
+        block.clear();
+        for sample in samples.by_ref().take(block_size * channels) {
+            block.push(sample.map_err(|e| AudioError::FileError(e.to_string()))? as i32);
+        }
+        // Un frame incompleto in coda (file troncato) viene scartato
+        block.truncate(block.len() / channels * channels);
+        if block.is_empty() {
+            break;
+        }
+
+        for sample in &block {
+            md5.update((*sample as i16).to_le_bytes());
+        }
+        let block_frames = block.len() / channels;
+        total_frames += block_frames;
+        if block_frames < block_size {
+            last_block_size = block_frames.max(MIN_BLOCK_SIZE);
+            block.resize(last_block_size * channels, 0);
+            framebuf.resize(last_block_size);
+        }
+
+        framebuf
+            .fill_interleaved(&block)
+            .map_err(|e| flac_error(&e))?;
+        let frame = flacenc::encode_fixed_size_frame(&config, &framebuf, frame_count, &stream_info)
+            .map_err(|e| flac_error(&e))?;
+        stream_info.update_frame_info(&frame);
+        frame_count += 1;
+
+        let mut sink = ByteSink::new();
+        frame.write(&mut sink).map_err(|e| flac_error(&e))?;
+        writer.write_all(sink.as_slice()).map_err(io_error)?;
+    }
+
+    // La dimensione minima dei blocchi esclude l'ultimo (più corto): con un
+    // valore diverso dal massimo i decoder tratterebbero il flusso come a
+    // blocchi variabili
+    let fixed_block_size = if frame_count > 1 {
+        block_size
+    } else {
+        last_block_size
+    };
+    stream_info
+        .set_block_sizes(fixed_block_size, fixed_block_size)
+        .map_err(|e| flac_error(&e))?;
+    stream_info.set_total_samples(total_frames);
+    stream_info.set_md5_digest(&md5.finalize().into());
+
+    let mut file = writer.into_inner().map_err(|e| io_error(e.into_error()))?;
+    file.seek(SeekFrom::Start(4)).map_err(io_error)?;
+    write_flac_stream_info(&mut file, &stream_info)?;
+    file.sync_all().map_err(io_error)
+}
+
+/// Scrive il blocco di metadati STREAMINFO, unico e quindi ultimo
+fn write_flac_stream_info(writer: &mut impl Write, info: &StreamInfo) -> Result<(), AudioError> {
+    let mut sink = ByteSink::new();
+    info.write(&mut sink)
+        .map_err(|e| AudioError::FileError(format!("Errore codifica FLAC: {:?}", e)))?;
+
+    let length = sink.as_slice().len() as u32;
+    let mut header = [0u8; 4];
+    header[0] = 0x80;
+    header[1..].copy_from_slice(&length.to_be_bytes()[1..]);
+
+    writer
+        .write_all(&header)
+        .and_then(|_| writer.write_all(sink.as_slice()))
+        .map_err(|e| AudioError::FileError(e.to_string()))
+}
+
+fn encode_opus(reader: WavReader<BufReader<File>>, output_path: &Path) -> Result<(), AudioError> {
+    use audiopus::coder::Encoder;
+    use audiopus::{Application, Bitrate, Channels, SampleRate};
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    let input_rate = spec.sample_rate;
+
+    // Opus accetta solo alcune frequenze: le altre vengono portate a 48 kHz
+    let encoder_rate = match input_rate {
+        8_000 | 12_000 | 16_000 | 24_000 | 48_000 => input_rate,
+        _ => 48_000,
+    };
+    let opus_rate = SampleRate::try_from(encoder_rate as i32)
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+    let opus_channels = if channels == 1 {
+        Channels::Mono
+    } else {
+        Channels::Stereo
+    };
+
+    let mut encoder = Encoder::new(opus_rate, opus_channels, Application::Voip)
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+    encoder
+        .set_bitrate(Bitrate::BitsPerSecond(
+            OPUS_BITRATE_PER_CHANNEL * channels as i32,
+        ))
+        .map_err(|e| AudioError::FileError(e.to_string()))?;
+
+    // Le posizioni nel flusso Ogg sono sempre espresse a 48 kHz
+    let granule_scale = 48_000 / encoder_rate as u64;
+    let pre_skip = encoder
+        .lookahead()
+        .map_err(|e| AudioError::FileError(e.to_string()))? as u64
+        * granule_scale;
+
+    let file = File::create(output_path).map_err(|e| AudioError::FileError(e.to_string()))?;
+    let mut writer = PacketWriter::new(BufWriter::new(file));
+    let serial = stream_serial();
+    let io_error = |e: std::io::Error| AudioError::FileError(e.to_string());
+
+    writer
+        .write_packet(
+            opus_head(channels as u8, pre_skip as u16, input_rate),
+            serial,
+            PacketWriteEndInfo::EndPage,
+            0,
+        )
+        .map_err(io_error)?;
+    writer
+        .write_packet(opus_tags(), serial, PacketWriteEndInfo::EndPage, 0)
+        .map_err(io_error)?;
+
+    let mut resamplers: Vec<Resampler> = (0..channels)
+        .map(|_| Resampler::new(input_rate, encoder_rate))
+        .collect();
+    let frame_len = encoder_rate as usize * OPUS_FRAME_MS / 1000 * channels;
+
+    let mut pending: Vec<f32> = Vec::with_capacity(frame_len * 2);
+    let mut packet = vec![0u8; 4000];
+    let mut packets_written = 0usize;
+    let mut input_frames = 0u64;
+    let mut encoded_frames = 0u64;
+
+    let mut samples = reader.into_samples::<i16>();
+    let mut block: Vec<f32> = Vec::with_capacity(4096 * channels);
+    let mut planar: Vec<Vec<f32>> = vec![Vec::new(); channels];
+    let mut resampled: Vec<Vec<f32>> = vec![Vec::new(); channels];
+
+    loop {
+        block.clear();
+        for sample in samples.by_ref().take(4096 * channels) {
+            let sample = sample.map_err(|e| AudioError::FileError(e.to_string()))?;
+            block.push(sample as f32 / 32768.0);
+        }
+        let finished = block.len() < 4096 * channels;
+        block.truncate(block.len() / channels * channels);
+
+        // Ricampionamento per canale e nuovo interleave
+        for (channel, (planar, resampled)) in planar.iter_mut().zip(&mut resampled).enumerate() {
+            planar.clear();
+            resampled.clear();
+            planar.extend(block.iter().skip(channel).step_by(channels));
+        }
+        for ((resampler, planar), resampled) in
+            resamplers.iter_mut().zip(&planar).zip(&mut resampled)
+        {
+            resampler.process(planar, resampled);
+        }
+        let produced = resampled.iter().map(|r| r.len()).min().unwrap_or(0);
+        for i in 0..produced {
+            pending.extend(resampled.iter().map(|r| r[i]));
+        }
+        input_frames += produced as u64;
+
+        // A fine file si aggiunge silenzio finché anche il ritardo
+        // dell'encoder (pre-skip) è stato smaltito
+        if finished {
+            let needed = (input_frames + pre_skip / granule_scale) * channels as u64;
+            let total = encoded_frames * channels as u64 + pending.len() as u64;
+            if total < needed {
+                pending.resize(pending.len() + (needed - total) as usize, 0.0);
+            }
+            let remainder = pending.len() % frame_len;
+            if remainder != 0 {
+                pending.resize(pending.len() + frame_len - remainder, 0.0);
+            }
+        }
+
+        let mut offset = 0;
+        while pending.len() - offset >= frame_len {
+            let len = encoder
+                .encode_float(&pending[offset..offset + frame_len], &mut packet)
+                .map_err(|e| AudioError::FileError(format!("Errore codifica Opus: {}", e)))?;
+            offset += frame_len;
+            encoded_frames += (frame_len / channels) as u64;
+            packets_written += 1;
+
+            let last = finished && pending.len() - offset < frame_len;
+            let granule = if last {
+                // Il decoder scarta i campioni oltre la fine reale
+                pre_skip + input_frames * granule_scale
+            } else {
+                encoded_frames * granule_scale
+            };
+            let end_info = if last {
+                PacketWriteEndInfo::EndStream
+            } else if packets_written % OPUS_PACKETS_PER_PAGE == 0 {
+                PacketWriteEndInfo::EndPage
+            } else {
+                PacketWriteEndInfo::NormalPacket
+            };
+
+            writer
+                .write_packet(packet[..len].to_vec(), serial, end_info, granule)
+                .map_err(io_error)?;
+        }
+        pending.drain(..offset);
+
+        if finished {
+            break;
+        }
+    }
+
+    writer.into_inner().flush().map_err(io_error)
+}
+
+/// Header di identificazione Opus (RFC 7845, sezione 5.1)
+fn opus_head(channels: u8, pre_skip: u16, input_rate: u32) -> Vec<u8> {
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1);
+    head.push(channels);
+    head.extend_from_slice(&pre_skip.to_le_bytes());
+    head.extend_from_slice(&input_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes());
+    head.push(0);
+    head
+}
+
+/// Header dei commenti Opus (RFC 7845, sezione 5.2)
+fn opus_tags() -> Vec<u8> {
+    let vendor = concat!("meet-transcriber ", env!("CARGO_PKG_VERSION"));
+    let mut tags = Vec::with_capacity(16 + vendor.len());
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor.as_bytes());
+    tags.extend_from_slice(&0u32.to_le_bytes());
+    tags
+}
+
+/// Numero di serie del flusso Ogg: deve solo essere diverso tra flussi
+/// concatenati, quindi basta derivarlo dall'orologio
+fn stream_serial() -> u32 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() ^ d.as_secs() as u32)
+        .unwrap_or(1)
+}
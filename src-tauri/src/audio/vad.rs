@@ -0,0 +1,322 @@
+//! Rilevamento dell'attività vocale (VAD) basato sull'energia.
+//!
+//! Il segnale viene analizzato a frame di 30 ms: un frame è considerato
+//! parlato quando la sua energia supera di un margine il rumore di fondo,
+//! stimato seguendo i minimi del segnale. Le pause brevi restano dentro il
+//! segmento, così ogni segmento corrisponde a un enunciato completo.
+
+use super::capture::AudioError;
+use super::import::{MonoConverter, SampleSink};
+use super::resample::TARGET_SAMPLE_RATE;
+use super::segments::decode_recording;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Durata di un frame di analisi
+const FRAME_MS: usize = 30;
+/// Margine sopra il rumore di fondo oltre il quale un frame è parlato
+const SPEECH_MARGIN_DB: f64 = 12.0;
+/// Sotto questa energia un frame non è mai parlato, anche in una stanza
+/// silenziosissima
+const MIN_SPEECH_DB: f64 = -55.0;
+/// Tetto della stima iniziale del rumore di fondo: se la registrazione
+/// inizia mentre qualcuno parla, il primo frame non è rumore
+const INITIAL_NOISE_DB: f64 = -50.0;
+/// Velocità con cui il rumore di fondo risale (dB per secondo): lenta, così
+/// il parlato continuo non viene scambiato per rumore. Le pause tra le
+/// parole riportano comunque la stima al livello reale.
+const NOISE_RISE_DB_PER_SECOND: f64 = 1.0;
+/// Silenzio dopo il quale un enunciato è considerato concluso
+const HANGOVER_MS: usize = 700;
+/// Gli enunciati più brevi (click, colpi di tosse) vengono scartati
+const MIN_SPEECH_MS: usize = 250;
+/// Margine aggiunto prima e dopo ogni segmento, per non tagliare le sillabe
+const PADDING_MS: usize = 200;
+/// Oltre questa durata un enunciato viene spezzato, per non ritardare
+/// troppo la trascrizione in tempo reale
+const MAX_UTTERANCE_SECONDS: f64 = 30.0;
+
+/// Intervallo di parlato, in secondi dall'inizio della registrazione
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SpeechSegment {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+}
+
+impl SpeechSegment {
+    pub fn duration_seconds(&self) -> f64 {
+        self.end_seconds - self.start_seconds
+    }
+}
+
+/// Regioni di parlato di una registrazione: tutto ciò che è fuori dai
+/// segmenti è silenzio
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SilenceMap {
+    pub total_seconds: f64,
+    pub speech: Vec<SpeechSegment>,
+}
+
+impl SilenceMap {
+    pub fn speech_seconds(&self) -> f64 {
+        self.speech.iter().map(|s| s.duration_seconds()).sum()
+    }
+}
+
+/// VAD in streaming: riceve campioni mono e restituisce gli enunciati man
+/// mano che si concludono
+pub struct VoiceActivityDetector {
+    sample_rate: u32,
+    frame_len: usize,
+    frame: Vec<f32>,
+    /// Frame analizzati finora
+    frames: usize,
+    /// Stima del rumore di fondo, dal primo frame analizzato in poi
+    noise_db: Option<f64>,
+    /// Inizio (in frame) dell'enunciato in corso
+    speech_start: Option<usize>,
+    /// Ultimo frame parlato dell'enunciato in corso
+    last_voiced: usize,
+    /// Frame parlati nell'enunciato in corso
+    voiced_frames: usize,
+    /// Fine dell'ultimo enunciato valido
+    last_speech_end: usize,
+    /// Fine dell'ultimo segmento restituito, per non sovrapporre i margini
+    last_segment_end: f64,
+    segments: Vec<SpeechSegment>,
+}
+
+impl VoiceActivityDetector {
+    pub fn new(sample_rate: u32) -> Self {
+        let frame_len = (sample_rate as usize * FRAME_MS / 1000).max(1);
+        Self {
+            sample_rate,
+            frame_len,
+            frame: Vec::with_capacity(frame_len),
+            frames: 0,
+            noise_db: None,
+            speech_start: None,
+            last_voiced: 0,
+            voiced_frames: 0,
+            last_speech_end: 0,
+            last_segment_end: 0.0,
+            segments: Vec::new(),
+        }
+    }
+
+    /// Analizza `samples` e accoda in `utterances` gli enunciati conclusi
+    pub fn process(&mut self, samples: &[f32], utterances: &mut Vec<SpeechSegment>) {
+        for &sample in samples {
+            self.frame.push(sample);
+            if self.frame.len() == self.frame_len {
+                self.analyze_frame(utterances);
+                self.frame.clear();
+            }
+        }
+    }
+
+    /// Secondi trascorsi dall'ultimo parlato rilevato
+    pub fn silence_seconds(&self) -> f64 {
+        if self.speech_start.is_some() {
+            return 0.0;
+        }
+        self.frames_to_seconds(self.frames.saturating_sub(self.last_speech_end))
+    }
+
+    /// Chiude l'eventuale enunciato in corso e restituisce la mappa completa
+    pub fn finish(mut self, utterances: &mut Vec<SpeechSegment>) -> SilenceMap {
+        if !self.frame.is_empty() {
+            self.frame.resize(self.frame_len, 0.0);
+            self.analyze_frame(utterances);
+        }
+        self.close_utterance(utterances);
+
+        let total_seconds = self.frames_to_seconds(self.frames);
+        for segment in &mut self.segments {
+            segment.end_seconds = segment.end_seconds.min(total_seconds);
+        }
+
+        SilenceMap {
+            total_seconds,
+            speech: self.segments,
+        }
+    }
+
+    fn analyze_frame(&mut self, utterances: &mut Vec<SpeechSegment>) {
+        let energy =
+            self.frame.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / self.frame_len as f64;
+        let energy_db = 10.0 * (energy + 1e-10).log10();
+
+        let noise_db = *self.noise_db.get_or_insert(energy_db.min(INITIAL_NOISE_DB));
+        let voiced = energy_db > (noise_db + SPEECH_MARGIN_DB).max(MIN_SPEECH_DB);
+
+        // Il rumore di fondo segue subito i minimi e risale lentamente
+        self.noise_db = Some(if energy_db < noise_db {
+            energy_db.max(-100.0)
+        } else {
+            noise_db + NOISE_RISE_DB_PER_SECOND * FRAME_MS as f64 / 1000.0
+        });
+
+        let index = self.frames;
+        self.frames += 1;
+
+        if voiced {
+            if self.speech_start.is_none() {
+                self.speech_start = Some(index);
+                self.voiced_frames = 0;
+            }
+            self.last_voiced = index;
+            self.voiced_frames += 1;
+
+            let start = self.speech_start.unwrap_or(index);
+            if self.frames_to_seconds(self.frames - start) >= MAX_UTTERANCE_SECONDS {
+                self.close_utterance(utterances);
+            }
+        } else if self.speech_start.is_some()
+            && (index - self.last_voiced) * FRAME_MS >= HANGOVER_MS
+        {
+            self.close_utterance(utterances);
+        }
+    }
+
+    fn close_utterance(&mut self, utterances: &mut Vec<SpeechSegment>) {
+        let Some(start) = self.speech_start.take() else {
+            return;
+        };
+
+        if self.voiced_frames * FRAME_MS < MIN_SPEECH_MS {
+            return;
+        }
+
+        let padding = PADDING_MS as f64 / 1000.0;
+        let segment = SpeechSegment {
+            start_seconds: (self.frames_to_seconds(start) - padding).max(self.last_segment_end),
+            end_seconds: self.frames_to_seconds(self.last_voiced + 1) + padding,
+        };
+        self.last_segment_end = segment.end_seconds;
+        self.last_speech_end = self.last_voiced + 1;
+
+        self.segments.push(segment);
+        utterances.push(segment);
+    }
+
+    fn frames_to_seconds(&self, frames: usize) -> f64 {
+        (frames * self.frame_len) as f64 / self.sample_rate as f64
+    }
+}
+
+/// Invia i campioni decodificati al VAD
+struct SpeechSink {
+    converter: MonoConverter,
+    detector: VoiceActivityDetector,
+    utterances: Vec<SpeechSegment>,
+}
+
+impl SampleSink for SpeechSink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        let mono = self.converter.convert(data, sample_rate, channels);
+        self.detector.process(mono, &mut self.utterances);
+        self.utterances.clear();
+        Ok(())
+    }
+}
+
+/// Calcola la mappa del silenzio di un file già su disco (registrazioni
+/// precedenti al VAD, file importati, archivi FLAC/Opus)
+pub fn analyze_speech(path: &Path) -> Result<SilenceMap, AudioError> {
+    let mut sink = SpeechSink {
+        converter: MonoConverter::new(),
+        detector: VoiceActivityDetector::new(TARGET_SAMPLE_RATE),
+        utterances: Vec::new(),
+    };
+    decode_recording(path, &mut sink)?;
+    Ok(sink.detector.finish(&mut sink.utterances))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = TARGET_SAMPLE_RATE;
+
+    fn tone(seconds: f64) -> Vec<f32> {
+        (0..(seconds * RATE as f64) as usize)
+            .map(|i| 0.3 * (i as f32 * 0.2).sin())
+            .collect()
+    }
+
+    fn silence(seconds: f64) -> Vec<f32> {
+        vec![0.0; (seconds * RATE as f64) as usize]
+    }
+
+    fn analyze(parts: &[Vec<f32>]) -> (SilenceMap, Vec<SpeechSegment>) {
+        let mut detector = VoiceActivityDetector::new(RATE);
+        let mut utterances = Vec::new();
+        for part in parts {
+            detector.process(part, &mut utterances);
+        }
+        let map = detector.finish(&mut utterances);
+        (map, utterances)
+    }
+
+    #[test]
+    fn short_pauses_stay_in_one_segment() {
+        let (map, _) = analyze(&[
+            silence(1.0),
+            tone(1.0),
+            silence(0.3),
+            tone(1.0),
+            silence(2.0),
+        ]);
+        assert_eq!(map.speech.len(), 1);
+        let segment = map.speech[0];
+        assert!((segment.start_seconds - 0.8).abs() < 0.05);
+        assert!((segment.end_seconds - 3.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn long_pauses_split_segments_without_overlap() {
+        let (map, utterances) = analyze(&[
+            silence(1.0),
+            tone(1.0),
+            silence(0.9),
+            tone(1.0),
+            silence(1.0),
+        ]);
+        assert_eq!(map.speech.len(), 2);
+        assert_eq!(utterances, map.speech);
+        assert!(map.speech[0].end_seconds <= map.speech[1].start_seconds);
+        assert!((map.total_seconds - 4.9).abs() < 0.05);
+    }
+
+    #[test]
+    fn short_bursts_are_dropped() {
+        let (map, _) = analyze(&[silence(1.0), tone(0.1), silence(1.0)]);
+        assert!(map.speech.is_empty());
+        assert_eq!(map.speech_seconds(), 0.0);
+    }
+
+    #[test]
+    fn long_utterances_are_split() {
+        // Parlato continuo, con le brevi pause fra le parole che tengono
+        // basso il rumore di fondo
+        let mut speech = Vec::new();
+        for _ in 0..(MAX_UTTERANCE_SECONDS as usize / 2 + 3) {
+            speech.extend(tone(1.8));
+            speech.extend(silence(0.2));
+        }
+        let (map, _) = analyze(&[speech]);
+        assert_eq!(map.speech.len(), 2);
+        assert!(map.speech[0].duration_seconds() <= MAX_UTTERANCE_SECONDS + 0.5);
+    }
+
+    #[test]
+    fn silence_is_counted_from_last_speech() {
+        let mut detector = VoiceActivityDetector::new(RATE);
+        let mut utterances = Vec::new();
+        detector.process(&tone(1.0), &mut utterances);
+        detector.process(&silence(3.0), &mut utterances);
+        assert_eq!(utterances.len(), 1);
+        assert!((detector.silence_seconds() - 3.0).abs() < 0.05);
+    }
+}
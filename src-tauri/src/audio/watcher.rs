@@ -0,0 +1,110 @@
+use super::devices::{list_audio_devices, AudioDevice};
+use cpal::traits::{DeviceTrait, HostTrait};
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+/// Intervallo di polling della lista dispositivi
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Poll consecutivi in cui un dispositivo deve mancare per essere
+/// considerato scollegato: una singola enumerazione incompleta non basta
+const REMOVAL_POLLS: u32 = 2;
+
+/// Dispositivi con uno stream aperto, per nome. ALSA può ometterli
+/// dall'elenco finché sono occupati dalla registrazione.
+static IN_USE: Lazy<Mutex<HashMap<String, usize>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeviceChange {
+    pub devices: Vec<AudioDevice>,
+    /// Nomi dei dispositivi comparsi e scomparsi
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+/// Segna un dispositivo come in uso finché il valore non viene rilasciato
+pub struct DeviceInUse {
+    name: String,
+}
+
+pub fn mark_in_use(name: String) -> DeviceInUse {
+    if let Ok(mut in_use) = IN_USE.lock() {
+        *in_use.entry(name.clone()).or_insert(0) += 1;
+    }
+    DeviceInUse { name }
+}
+
+impl Drop for DeviceInUse {
+    fn drop(&mut self) {
+        if let Ok(mut in_use) = IN_USE.lock() {
+            if let Some(count) = in_use.get_mut(&self.name) {
+                *count -= 1;
+                if *count == 0 {
+                    in_use.remove(&self.name);
+                }
+            }
+        }
+    }
+}
+
+/// Nomi dei dispositivi di input del sistema. Legge solo l'elenco dell'host,
+/// senza aprire le configurazioni né interrogare PulseAudio; i dispositivi
+/// in uso contano sempre come presenti.
+fn device_names(host: &cpal::Host) -> HashSet<String> {
+    let mut names: HashSet<String> = host
+        .input_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default();
+    if let Ok(in_use) = IN_USE.lock() {
+        names.extend(in_use.keys().cloned());
+    }
+    names
+}
+
+/// Avvia un thread che controlla periodicamente i dispositivi disponibili
+/// e chiama `on_change` quando ne compaiono o scompaiono.
+pub fn spawn_device_watcher(on_change: impl Fn(DeviceChange) + Send + 'static) {
+    thread::spawn(move || {
+        let host = cpal::default_host();
+        let mut known = device_names(&host);
+        // Poll consecutivi in cui ogni dispositivo noto è mancato
+        let mut missing: HashMap<String, u32> = HashMap::new();
+
+        loop {
+            thread::sleep(POLL_INTERVAL);
+
+            let current = device_names(&host);
+            missing.retain(|name, _| !current.contains(name));
+
+            let added: Vec<String> = current.difference(&known).cloned().collect();
+            let mut removed = Vec::new();
+            for name in known.difference(&current) {
+                let polls = missing.entry(name.clone()).or_insert(0);
+                *polls += 1;
+                if *polls >= REMOVAL_POLLS {
+                    removed.push(name.clone());
+                }
+            }
+
+            if added.is_empty() && removed.is_empty() {
+                continue;
+            }
+
+            for name in &removed {
+                known.remove(name);
+                missing.remove(name);
+            }
+            known.extend(added.iter().cloned());
+
+            on_change(DeviceChange {
+                devices: list_audio_devices(),
+                added,
+                removed,
+            });
+        }
+    });
+}
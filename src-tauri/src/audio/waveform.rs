@@ -0,0 +1,412 @@
+//! Picchi della forma d'onda a più risoluzioni.
+//!
+//! Il livello 0 contiene minimo e massimo di ogni blocco di 256 campioni
+//! (16 ms a 16 kHz); ogni livello successivo raggruppa 4 picchi del
+//! precedente. Per disegnare una porzione della timeline basta leggere il
+//! livello più grossolano che ha ancora qualche picco per pixel, senza
+//! decodificare l'audio.
+
+use super::capture::AudioError;
+use super::import::{MonoConverter, SampleSink};
+use super::resample::{to_i16, TARGET_SAMPLE_RATE};
+use super::segments::decode_recording;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Campioni riassunti da un picco del livello 0
+pub const SAMPLES_PER_PEAK: u32 = 256;
+/// Picchi di un livello raggruppati in un picco del livello successivo
+const LEVEL_FACTOR: usize = 4;
+/// Il livello più grossolano ha un picco ogni ~4 minuti di audio
+const MAX_LEVELS: usize = 8;
+/// Limite ai bucket richiesti in una volta (un pixel ciascuno)
+const MAX_BUCKETS: usize = 8192;
+
+/// Minimo e massimo di un intervallo, in campioni a 16 bit
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Peak {
+    min: i16,
+    max: i16,
+}
+
+impl Peak {
+    const EMPTY: Peak = Peak {
+        min: i16::MAX,
+        max: i16::MIN,
+    };
+
+    fn include(&mut self, other: Peak) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+    }
+
+    fn to_f32(self) -> [f32; 2] {
+        if self.min > self.max {
+            return [0.0, 0.0];
+        }
+        [
+            self.min as f32 / i16::MAX as f32,
+            self.max as f32 / i16::MAX as f32,
+        ]
+    }
+}
+
+/// Un livello della piramide: i picchi completi e quello in costruzione
+#[derive(Debug, Clone)]
+struct Level {
+    peaks: Vec<Peak>,
+    partial: Peak,
+    /// Elementi (campioni o picchi del livello inferiore) già nel parziale
+    filled: usize,
+}
+
+impl Level {
+    fn new() -> Self {
+        Self {
+            peaks: Vec::new(),
+            partial: Peak::EMPTY,
+            filled: 0,
+        }
+    }
+
+    /// Il picco parziale conta già: durante la registrazione la coda del
+    /// segnale resta visibile
+    fn get(&self, index: usize) -> Option<Peak> {
+        match self.peaks.get(index) {
+            Some(peak) => Some(*peak),
+            None if index == self.peaks.len() && self.filled > 0 => Some(self.partial),
+            None => None,
+        }
+    }
+}
+
+/// Porzione della forma d'onda restituita al frontend: una coppia
+/// `[min, max]` (fra -1 e 1) per bucket
+#[derive(Debug, Clone, Serialize)]
+pub struct WaveformSlice {
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub duration_seconds: f64,
+    pub seconds_per_bucket: f64,
+    pub peaks: Vec<[f32; 2]>,
+}
+
+/// Picchi di una registrazione, costruiti in modo incrementale dai campioni
+/// mono a 16 kHz
+#[derive(Debug, Clone)]
+pub struct WaveformPeaks {
+    sample_rate: u32,
+    total_samples: u64,
+    levels: Vec<Level>,
+}
+
+/// Picchi condivisi fra il thread di registrazione e i comandi, per la
+/// forma d'onda live
+pub type SharedWaveform = Arc<Mutex<WaveformPeaks>>;
+
+impl WaveformPeaks {
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            sample_rate,
+            total_samples: 0,
+            levels: vec![Level::new()],
+        }
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.total_samples
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.total_samples as f64 / self.sample_rate as f64
+    }
+
+    pub fn seconds_per_peak(&self) -> f64 {
+        SAMPLES_PER_PEAK as f64 / self.sample_rate as f64
+    }
+
+    /// Aggiunge campioni mono in coda
+    pub fn push(&mut self, samples: &[f32]) {
+        for &sample in samples {
+            let value = to_i16(sample);
+            self.add(
+                0,
+                Peak {
+                    min: value,
+                    max: value,
+                },
+            );
+        }
+        self.total_samples += samples.len() as u64;
+    }
+
+    fn add(&mut self, index: usize, peak: Peak) {
+        let size = if index == 0 {
+            SAMPLES_PER_PEAK as usize
+        } else {
+            LEVEL_FACTOR
+        };
+
+        let level = &mut self.levels[index];
+        level.partial.include(peak);
+        level.filled += 1;
+        if level.filled < size {
+            return;
+        }
+
+        let complete = std::mem::replace(&mut level.partial, Peak::EMPTY);
+        level.filled = 0;
+        level.peaks.push(complete);
+
+        if index + 1 < MAX_LEVELS {
+            if self.levels.len() == index + 1 {
+                self.levels.push(Level::new());
+            }
+            self.add(index + 1, complete);
+        }
+    }
+
+    /// Chiude i picchi parziali a fine registrazione. Dopo `finish` non
+    /// vanno aggiunti altri campioni.
+    pub fn finish(&mut self) {
+        for index in 0..self.levels.len() {
+            let level = &mut self.levels[index];
+            if level.filled == 0 {
+                continue;
+            }
+
+            let peak = std::mem::replace(&mut level.partial, Peak::EMPTY);
+            level.filled = 0;
+            level.peaks.push(peak);
+
+            if let Some(upper) = self.levels.get_mut(index + 1) {
+                upper.partial.include(peak);
+                upper.filled += 1;
+            }
+        }
+    }
+
+    /// Picchi completi del livello 0 a partire da `from`, per gli
+    /// aggiornamenti della forma d'onda live
+    pub fn base_peaks(&self, from: usize) -> Vec<[f32; 2]> {
+        self.levels[0]
+            .peaks
+            .get(from..)
+            .unwrap_or_default()
+            .iter()
+            .map(|peak| peak.to_f32())
+            .collect()
+    }
+
+    /// Riduce l'intervallo `start..end` (in secondi, di default l'intera
+    /// registrazione) a `buckets` coppie minimo/massimo
+    pub fn slice(&self, start: Option<f64>, end: Option<f64>, buckets: usize) -> WaveformSlice {
+        let duration_seconds = self.duration_seconds();
+        let start_seconds = start.unwrap_or(0.0).clamp(0.0, duration_seconds);
+        let end_seconds = end
+            .unwrap_or(duration_seconds)
+            .clamp(start_seconds, duration_seconds);
+        let buckets = buckets.clamp(1, MAX_BUCKETS);
+
+        let rate = self.sample_rate as f64;
+        let first_sample = start_seconds * rate;
+        let samples_per_bucket = (end_seconds - start_seconds) * rate / buckets as f64;
+
+        if samples_per_bucket <= 0.0 {
+            return WaveformSlice {
+                start_seconds,
+                end_seconds,
+                duration_seconds,
+                seconds_per_bucket: 0.0,
+                peaks: Vec::new(),
+            };
+        }
+
+        // Livello più grossolano con almeno LEVEL_FACTOR picchi per bucket:
+        // i picchi a cavallo dei bordi sbordano al più di un quarto
+        let mut index = 0;
+        let mut peak_size = SAMPLES_PER_PEAK as f64;
+        let max_peak_size = samples_per_bucket / (LEVEL_FACTOR * LEVEL_FACTOR) as f64;
+        while index + 1 < self.levels.len() && peak_size <= max_peak_size {
+            index += 1;
+            peak_size *= LEVEL_FACTOR as f64;
+        }
+        let level = &self.levels[index];
+
+        let peaks = (0..buckets)
+            .map(|bucket| {
+                let from = first_sample + bucket as f64 * samples_per_bucket;
+                let to = from + samples_per_bucket;
+                let first_peak = (from / peak_size).floor() as usize;
+                let last_peak = ((to / peak_size).ceil() as usize).max(first_peak + 1);
+
+                let mut peak = Peak::EMPTY;
+                for peak_index in first_peak..last_peak {
+                    if let Some(p) = level.get(peak_index) {
+                        peak.include(p);
+                    }
+                }
+                peak.to_f32()
+            })
+            .collect();
+
+        WaveformSlice {
+            start_seconds,
+            end_seconds,
+            duration_seconds,
+            seconds_per_bucket: samples_per_bucket / rate,
+            peaks,
+        }
+    }
+
+    /// Serializza i livelli per il salvataggio: per ogni livello il numero
+    /// di picchi (u32) seguito dalle coppie min/max (i16), little endian.
+    /// Va chiamata dopo `finish`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let total: usize = self.levels.iter().map(|l| 4 + l.peaks.len() * 4).sum();
+        let mut bytes = Vec::with_capacity(total);
+        for level in &self.levels {
+            bytes.extend_from_slice(&(level.peaks.len() as u32).to_le_bytes());
+            for peak in &level.peaks {
+                bytes.extend_from_slice(&peak.min.to_le_bytes());
+                bytes.extend_from_slice(&peak.max.to_le_bytes());
+            }
+        }
+        bytes
+    }
+
+    /// Ricostruisce i picchi salvati con `to_bytes`; `None` se i dati sono
+    /// troncati o incoerenti
+    pub fn from_bytes(sample_rate: u32, total_samples: u64, bytes: &[u8]) -> Option<Self> {
+        let mut levels = Vec::new();
+        let mut rest = bytes;
+
+        while !rest.is_empty() {
+            let (count, tail) = rest.split_first_chunk::<4>()?;
+            let count = u32::from_le_bytes(*count) as usize;
+            let size = count.checked_mul(4).filter(|size| *size <= tail.len())?;
+            let (data, tail) = tail.split_at(size);
+
+            let peaks = data
+                .chunks_exact(4)
+                .map(|chunk| Peak {
+                    min: i16::from_le_bytes([chunk[0], chunk[1]]),
+                    max: i16::from_le_bytes([chunk[2], chunk[3]]),
+                })
+                .collect();
+            levels.push(Level {
+                peaks,
+                partial: Peak::EMPTY,
+                filled: 0,
+            });
+            rest = tail;
+        }
+
+        if levels.is_empty() || levels.len() > MAX_LEVELS {
+            return None;
+        }
+
+        Some(Self {
+            sample_rate,
+            total_samples,
+            levels,
+        })
+    }
+}
+
+/// Invia i campioni decodificati al costruttore dei picchi
+struct PeakSink {
+    converter: MonoConverter,
+    peaks: WaveformPeaks,
+}
+
+impl SampleSink for PeakSink {
+    fn push(&mut self, data: &[f32], sample_rate: u32, channels: usize) -> Result<(), AudioError> {
+        let mono = self.converter.convert(data, sample_rate, channels);
+        self.peaks.push(mono);
+        Ok(())
+    }
+}
+
+/// Calcola i picchi di un file già su disco (registrazioni precedenti,
+/// file importati, audio migliorato)
+pub fn analyze_waveform(path: &Path) -> Result<WaveformPeaks, AudioError> {
+    let mut sink = PeakSink {
+        converter: MonoConverter::new(),
+        peaks: WaveformPeaks::new(TARGET_SAMPLE_RATE),
+    };
+    decode_recording(path, &mut sink)?;
+    sink.peaks.finish();
+    Ok(sink.peaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rampa da -1 a 1 lunga `samples` campioni
+    fn ramp(samples: usize) -> Vec<f32> {
+        (0..samples)
+            .map(|i| i as f32 / samples as f32 * 2.0 - 1.0)
+            .collect()
+    }
+
+    fn finished(samples: &[f32]) -> WaveformPeaks {
+        let mut peaks = WaveformPeaks::new(16_000);
+        peaks.push(samples);
+        peaks.finish();
+        peaks
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let peaks = finished(&ramp(16_000 * 10 + 123));
+        let bytes = peaks.to_bytes();
+        let restored =
+            WaveformPeaks::from_bytes(peaks.sample_rate(), peaks.total_samples(), &bytes).unwrap();
+
+        assert_eq!(restored.total_samples(), peaks.total_samples());
+        assert_eq!(restored.levels.len(), peaks.levels.len());
+        for (a, b) in restored.levels.iter().zip(&peaks.levels) {
+            assert_eq!(a.peaks, b.peaks);
+        }
+        assert_eq!(restored.to_bytes(), bytes);
+        assert_eq!(
+            restored.slice(None, None, 100).peaks,
+            peaks.slice(None, None, 100).peaks
+        );
+    }
+
+    #[test]
+    fn truncated_bytes_are_rejected() {
+        let bytes = finished(&ramp(16_000)).to_bytes();
+        assert!(WaveformPeaks::from_bytes(16_000, 16_000, &bytes[..bytes.len() - 1]).is_none());
+        assert!(WaveformPeaks::from_bytes(16_000, 16_000, &bytes[..2]).is_none());
+        assert!(WaveformPeaks::from_bytes(16_000, 0, &[]).is_none());
+    }
+
+    #[test]
+    fn finish_keeps_the_partial_tail() {
+        let peaks = finished(&ramp(SAMPLES_PER_PEAK as usize + 10));
+        assert_eq!(peaks.levels[0].peaks.len(), 2);
+        assert_eq!(peaks.base_peaks(1).len(), 1);
+    }
+
+    #[test]
+    fn slice_covers_the_whole_range() {
+        let peaks = finished(&ramp(16_000 * 60));
+        let slice = peaks.slice(None, None, 10);
+        assert_eq!(slice.peaks.len(), 10);
+        assert!((slice.duration_seconds - 60.0).abs() < 1e-9);
+        assert!(slice.peaks[0][0] < -0.99);
+        assert!(slice.peaks[9][1] > 0.99);
+        for pair in slice.peaks.windows(2) {
+            assert!(pair[0][1] <= pair[1][1]);
+        }
+    }
+}
@@ -1,6 +1,27 @@
-use crate::audio::{list_audio_devices, AudioDevice};
+use crate::audio::{
+    analyze_quality, analyze_waveform, companion_files, compress_recording, device_capabilities,
+    enhance_file, extract_clip, import_to_wav, list_audio_devices, recording_parts,
+    redact_recording_files, remove_partial_archives, rename_recording, repair_recordings,
+    segment_manifest, AudioDevice, DeviceCapabilities, InputConfig, QualityReport, RecorderEvent,
+    RecordingLayout, RecordingOptions, RedactionMode, SpeechSegment, StorageCodec, TimeRange,
+    WaveformSlice, SUPPORTED_EXTENSIONS,
+};
+use crate::commands::meetings::{
+    load_waveform, record_meeting_event, redact_transcript, save_audio_segments,
+    save_quality_report, save_silence_map, save_waveform, Meeting, TranscriptRedaction,
+};
+use crate::commands::settings::{load_app_settings, AppSettings};
+use crate::transcription::{
+    pause_streaming_transcription, resume_streaming_transcription, send_streaming_utterance,
+};
 use crate::AppState;
-use tauri::State;
+use chrono::{Local, Utc};
+use rusqlite::{params, Connection, OptionalExtension};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_dialog::DialogExt;
 use uuid::Uuid;
 
 #[tauri::command]
@@ -9,10 +30,109 @@ pub fn get_audio_devices() -> Vec<AudioDevice> {
 }
 
 #[tauri::command]
-pub async fn start_recording(
+pub fn get_device_capabilities(device_id: Option<String>) -> Result<DeviceCapabilities, String> {
+    device_capabilities(device_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_preferred_audio_device(
     state: State<'_, AppState>,
     device_id: Option<String>,
-) -> Result<String, String> {
+) -> Result<(), String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+
+    match device_id {
+        Some(id) => conn.execute(
+            "INSERT INTO settings (key, value) VALUES ('preferred_input_device', ?1)
+             ON CONFLICT(key) DO UPDATE SET value = ?1",
+            params![id],
+        ),
+        None => conn.execute(
+            "DELETE FROM settings WHERE key = 'preferred_input_device'",
+            [],
+        ),
+    }
+    .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Senza una scelta esplicita si usa il dispositivo preferito, se è ancora
+/// collegato, altrimenti quello predefinito del sistema
+fn recording_device_ids(device_ids: Option<Vec<String>>, settings: &AppSettings) -> Vec<String> {
+    match device_ids {
+        Some(ids) if !ids.is_empty() => ids,
+        _ => settings
+            .preferred_input_device
+            .clone()
+            .filter(|id| list_audio_devices().iter().any(|d| &d.id == id))
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Apre i dispositivi e conserva gli ultimi secondi di audio (quanti
+/// indicato in `pre_roll_seconds`), che finiranno all'inizio della
+/// prossima registrazione avviata con gli stessi dispositivi
+#[tauri::command]
+pub fn arm_recording(
+    state: State<'_, AppState>,
+    device_ids: Option<Vec<String>>,
+    stream_configs: Option<HashMap<String, InputConfig>>,
+) -> Result<(), String> {
+    let settings = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        load_app_settings(&conn)
+    };
+    if settings.pre_roll_seconds == 0 {
+        return Err("Pre-roll disattivato nelle impostazioni audio".to_string());
+    }
+    let device_ids = recording_device_ids(device_ids, &settings);
+
+    let mut recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+    recorder
+        .arm(
+            device_ids,
+            stream_configs.unwrap_or_default(),
+            Duration::from_secs(settings.pre_roll_seconds as u64),
+        )
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn disarm_recording(state: State<'_, AppState>) -> Result<(), String> {
+    let mut recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+    recorder.disarm();
+    Ok(())
+}
+
+#[tauri::command]
+pub fn is_recording_armed(state: State<'_, AppState>) -> bool {
+    state.recorder.lock().map(|r| r.is_armed()).unwrap_or(false)
+}
+
+#[tauri::command]
+pub async fn start_recording(
+    state: State<'_, AppState>,
+    device_ids: Option<Vec<String>>,
+    layout: Option<RecordingLayout>,
+    title: Option<String>,
+    stream_configs: Option<HashMap<String, InputConfig>>,
+) -> Result<Meeting, String> {
+    begin_recording(&state, device_ids, layout, title, stream_configs, None)
+}
+
+/// Avvia la registrazione e crea la riunione. `auto_stop_silence_minutes`
+/// sostituisce l'impostazione globale (le registrazioni programmate hanno
+/// una propria regola sul silenzio).
+pub(crate) fn begin_recording(
+    state: &AppState,
+    device_ids: Option<Vec<String>>,
+    layout: Option<RecordingLayout>,
+    title: Option<String>,
+    stream_configs: Option<HashMap<String, InputConfig>>,
+    auto_stop_silence_minutes: Option<u32>,
+) -> Result<Meeting, String> {
     let meeting_id = Uuid::new_v4().to_string();
 
     // Create recordings directory
@@ -21,27 +141,1022 @@ pub async fn start_recording(
 
     let audio_path = recordings_dir.join(format!("{}.wav", meeting_id));
 
+    let settings = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        load_app_settings(&conn)
+    };
+
+    let options = RecordingOptions {
+        keep_master_copy: settings.keep_master_copy,
+        layout: layout.unwrap_or_default(),
+        stream_configs: stream_configs.unwrap_or_default(),
+        auto_stop_silence: Some(
+            auto_stop_silence_minutes.unwrap_or(settings.auto_stop_silence_minutes),
+        )
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| Duration::from_secs(minutes as u64 * 60)),
+        enhancement: settings.enhancement(),
+    };
+
+    let device_ids = recording_device_ids(device_ids, &settings);
+
     let mut recorder = state.recorder.lock().map_err(|e| e.to_string())?;
-    recorder
-        .start_recording(audio_path, device_id)
+    if recorder.is_recording() {
+        return Err("Registrazione già in corso".to_string());
+    }
+
+    // La riunione esiste prima che parta la cattura, così gli eventi emessi
+    // fin dall'apertura dei dispositivi possono esserle associati
+    let meeting = Meeting {
+        id: meeting_id.clone(),
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or_else(|| format!("Riunione {}", Local::now().format("%d/%m/%Y %H:%M"))),
+        created_at: Utc::now().to_rfc3339(),
+        duration_seconds: None,
+        audio_path: Some(audio_path.to_string_lossy().to_string()),
+        status: "recording".to_string(),
+    };
+
+    {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "INSERT INTO meetings (id, title, created_at, audio_path, status) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![meeting.id, meeting.title, meeting.created_at, meeting.audio_path, meeting.status],
+        )
         .map_err(|e| e.to_string())?;
+    }
 
     // Store current meeting id
-    let mut current = state.current_meeting_id.lock().map_err(|e| e.to_string())?;
-    *current = Some(meeting_id.clone());
+    *state.current_meeting_id.lock().map_err(|e| e.to_string())? = Some(meeting_id.clone());
+
+    if let Err(e) = recorder.start_recording(audio_path, device_ids, options) {
+        // Nessuna registrazione: la riunione appena creata non serve più
+        *state.current_meeting_id.lock().map_err(|e| e.to_string())? = None;
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = conn.execute("DELETE FROM meetings WHERE id = ?1", params![meeting_id]) {
+            eprintln!("Errore eliminazione riunione: {}", e);
+        }
+        return Err(e.to_string());
+    }
 
-    Ok(meeting_id)
+    Ok(meeting)
 }
 
 #[tauri::command]
 pub async fn stop_recording(state: State<'_, AppState>) -> Result<String, String> {
-    let mut recorder = state.recorder.lock().map_err(|e| e.to_string())?;
-    let audio_path = recorder.stop_recording().map_err(|e| e.to_string())?;
+    let audio_path = finish_recording(&state).await?;
+    Ok(audio_path.to_string_lossy().to_string())
+}
+
+/// Ferma la registrazione in corso, chiude la riunione e comprime l'audio.
+/// Usata sia dallo stop manuale sia da quelli automatici.
+pub(crate) async fn finish_recording(state: &AppState) -> Result<PathBuf, String> {
+    let stopping = {
+        let mut recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+        recorder.stop_recording().map_err(|e| e.to_string())?
+    };
+    // La chiusura dei file avviene fuori dal lock e dal runtime async
+    let (audio_path, waveform) = tokio::task::spawn_blocking(move || stopping.finish())
+        .await
+        .map_err(|e| e.to_string())?;
 
-    let mut current = state.current_meeting_id.lock().map_err(|e| e.to_string())?;
-    *current = None;
+    let meeting_id = state
+        .current_meeting_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .take();
 
-    Ok(audio_path.to_string_lossy().to_string())
+    let storage_codec = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        if let Some(meeting_id) = &meeting_id {
+            let duration_seconds = wav_duration_seconds(&audio_path);
+            conn.execute(
+                "UPDATE meetings SET status = 'recorded', duration_seconds = ?1 WHERE id = ?2",
+                params![duration_seconds, meeting_id],
+            )
+            .map_err(|e| e.to_string())?;
+            // Le durate vanno lette dagli header WAV, prima della compressione
+            save_recording_segments(&conn, meeting_id, &audio_path);
+
+            if let Some(waveform) = &waveform {
+                if let Err(e) = save_waveform(&conn, meeting_id, waveform) {
+                    eprintln!("Errore salvataggio forma d'onda: {}", e);
+                }
+            }
+        }
+        load_app_settings(&conn).storage_codec
+    };
+
+    // Se la compressione fallisce la registrazione resta in WAV
+    let audio_path = match compress_meeting_audio(audio_path.clone(), storage_codec).await {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            eprintln!("Errore compressione registrazione: {}", e);
+            audio_path
+        }
+    };
+
+    if let Some(meeting_id) = &meeting_id {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE meetings SET audio_path = ?1 WHERE id = ?2",
+            params![audio_path.to_string_lossy().to_string(), meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(audio_path)
+}
+
+/// Converte nel formato di archivio le registrazioni ancora in WAV.
+/// Restituisce il numero di riunioni convertite.
+#[tauri::command]
+pub async fn archive_recordings(
+    state: State<'_, AppState>,
+    codec: Option<StorageCodec>,
+) -> Result<usize, String> {
+    let (codec, recordings) = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let codec = codec.unwrap_or_else(|| load_app_settings(&conn).storage_codec);
+
+        let mut stmt = conn
+            .prepare(
+                "SELECT id, audio_path FROM meetings
+                 WHERE audio_path IS NOT NULL AND status NOT IN ('recording', 'paused')",
+            )
+            .map_err(|e| e.to_string())?;
+        let recordings: Vec<(String, String)> = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(|e| e.to_string())?
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        (codec, recordings)
+    };
+
+    if codec == StorageCodec::Wav {
+        return Ok(0);
+    }
+
+    let mut archived = 0;
+    for (meeting_id, audio_path) in recordings {
+        let audio_path = PathBuf::from(audio_path);
+        let is_wav = audio_path
+            .extension()
+            .map(|ext| ext.eq_ignore_ascii_case("wav"))
+            .unwrap_or(false);
+        if !is_wav || !audio_path.is_file() {
+            continue;
+        }
+
+        match compress_meeting_audio(audio_path, codec).await {
+            Ok(compressed) => {
+                let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+                conn.execute(
+                    "UPDATE meetings SET audio_path = ?1 WHERE id = ?2",
+                    params![compressed.to_string_lossy().to_string(), meeting_id],
+                )
+                .map_err(|e| e.to_string())?;
+                archived += 1;
+            }
+            Err(e) => eprintln!("Errore archiviazione riunione {}: {}", meeting_id, e),
+        }
+    }
+
+    Ok(archived)
+}
+
+/// Comprime la registrazione e le sue copie master, restituendo il nuovo
+/// percorso del file principale
+async fn compress_meeting_audio(
+    audio_path: PathBuf,
+    codec: StorageCodec,
+) -> Result<PathBuf, String> {
+    if codec == StorageCodec::Wav {
+        return Ok(audio_path);
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let compressed = compress_recording(&audio_path, codec).map_err(|e| e.to_string())?;
+
+        for companion in companion_files(&audio_path) {
+            let is_wav = companion
+                .extension()
+                .map(|ext| ext.eq_ignore_ascii_case("wav"))
+                .unwrap_or(false);
+            if is_wav {
+                if let Err(e) = compress_recording(&companion, codec) {
+                    eprintln!("Errore compressione {}: {}", companion.display(), e);
+                }
+            }
+        }
+
+        Ok(compressed)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+pub async fn pause_recording(state: State<'_, AppState>) -> Result<(), String> {
+    {
+        let recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+        recorder.pause_recording().map_err(|e| e.to_string())?;
+    }
+
+    set_current_meeting_status(&state, "paused")?;
+
+    // La trascrizione live resta in attesa finché la registrazione è ferma
+    if let Err(e) = pause_streaming_transcription().await {
+        eprintln!("Errore pausa trascrizione streaming: {}", e);
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn resume_recording(state: State<'_, AppState>) -> Result<(), String> {
+    {
+        let recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+        recorder.resume_recording().map_err(|e| e.to_string())?;
+    }
+
+    set_current_meeting_status(&state, "recording")?;
+
+    if let Err(e) = resume_streaming_transcription().await {
+        eprintln!("Errore ripresa trascrizione streaming: {}", e);
+    }
+
+    Ok(())
+}
+
+/// Applica alla registrazione di una riunione la catena di miglioramento
+/// configurata. L'originale resta accanto come `<id>_original`; il nuovo
+/// file diventa l'audio della riunione. Restituisce il nuovo percorso.
+#[tauri::command]
+pub async fn enhance_meeting_audio(
+    state: State<'_, AppState>,
+    meeting_id: String,
+) -> Result<String, String> {
+    let (audio_path, status, settings) = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let (audio_path, status): (Option<String>, String) = conn
+            .query_row(
+                "SELECT audio_path, status FROM meetings WHERE id = ?1",
+                params![meeting_id],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .map_err(|e| e.to_string())?;
+        (audio_path, status, load_app_settings(&conn))
+    };
+
+    if status == "recording" || status == "paused" {
+        return Err("La registrazione è ancora in corso".to_string());
+    }
+    let audio_path = audio_path
+        .map(PathBuf::from)
+        .filter(|p| p.is_file())
+        .ok_or_else(|| "File audio della riunione non trovato".to_string())?;
+
+    let enhancement = settings.enhancement();
+    if !enhancement.is_enabled() {
+        return Err("Nessun filtro attivo nelle impostazioni audio".to_string());
+    }
+
+    let stem = audio_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = audio_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "wav".to_string());
+    let original_path = audio_path.with_file_name(format!("{}_original.{}", stem, extension));
+    if original_path.exists() {
+        return Err("L'audio di questa riunione è già stato migliorato".to_string());
+    }
+
+    let enhanced_path = {
+        let audio_path = audio_path.clone();
+        tokio::task::spawn_blocking(move || -> Result<PathBuf, String> {
+            let temp_path = audio_path.with_file_name(format!("{}_enhanced.wav", stem));
+            enhance_file(&audio_path, &temp_path, &enhancement).map_err(|e| e.to_string())?;
+
+            let enhanced_path = audio_path.with_file_name(format!("{}.wav", stem));
+            rename_recording(&audio_path, &original_path).map_err(|e| e.to_string())?;
+            std::fs::rename(&temp_path, &enhanced_path).map_err(|e| e.to_string())?;
+            Ok(enhanced_path)
+        })
+        .await
+        .map_err(|e| e.to_string())??
+    };
+
+    let enhanced_path =
+        match compress_meeting_audio(enhanced_path.clone(), settings.storage_codec).await {
+            Ok(compressed) => compressed,
+            Err(e) => {
+                eprintln!("Errore compressione audio migliorato: {}", e);
+                enhanced_path
+            }
+        };
+
+    {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE meetings SET audio_path = ?1 WHERE id = ?2",
+            params![enhanced_path.to_string_lossy().to_string(), meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+        // Parlato e forma d'onda vanno ricalcolati sull'audio ripulito
+        conn.execute(
+            "DELETE FROM silence_maps WHERE meeting_id = ?1",
+            params![meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+        conn.execute(
+            "DELETE FROM waveforms WHERE meeting_id = ?1",
+            params![meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+        // L'audio migliorato è un file unico
+        conn.execute(
+            "DELETE FROM audio_segments WHERE meeting_id = ?1",
+            params![meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+        record_meeting_event(
+            &conn,
+            &meeting_id,
+            "enhanced",
+            None,
+            &serde_json::to_value(enhancement).unwrap_or_default(),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(enhanced_path.to_string_lossy().to_string())
+}
+
+/// Percorso e titolo dell'audio di una riunione conclusa
+fn recorded_meeting_audio(state: &AppState, meeting_id: &str) -> Result<(PathBuf, String), String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    let (audio_path, title, status): (Option<String>, String, String) = conn
+        .query_row(
+            "SELECT audio_path, title, status FROM meetings WHERE id = ?1",
+            params![meeting_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .map_err(|e| e.to_string())?;
+
+    if status == "recording" || status == "paused" {
+        return Err("La registrazione è ancora in corso".to_string());
+    }
+    let audio_path = audio_path
+        .map(PathBuf::from)
+        .filter(|p| p.is_file())
+        .ok_or_else(|| "File audio della riunione non trovato".to_string())?;
+
+    Ok((audio_path, title))
+}
+
+fn validate_range(range: &TimeRange) -> Result<(), String> {
+    let valid = range.start_seconds.is_finite()
+        && range.end_seconds.is_finite()
+        && range.start_seconds >= 0.0
+        && range.end_seconds > range.start_seconds;
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Intervallo non valido: {:.1}s - {:.1}s",
+            range.start_seconds, range.end_seconds
+        ))
+    }
+}
+
+/// Minuti e secondi per i nomi dei file (senza `:`, non ammesso su Windows)
+fn file_timestamp(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    format!("{:02}.{:02}", seconds / 60, seconds % 60)
+}
+
+/// Esporta un estratto della registrazione in un WAV scelto dall'utente.
+/// Restituisce il percorso del file, o `None` se l'utente annulla.
+#[tauri::command]
+pub async fn export_meeting_clip(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    meeting_id: String,
+    start_seconds: f64,
+    end_seconds: f64,
+) -> Result<Option<String>, String> {
+    let range = TimeRange {
+        start_seconds,
+        end_seconds,
+    };
+    validate_range(&range)?;
+    let (audio_path, title) = recorded_meeting_audio(&state, &meeting_id)?;
+
+    let safe_title: String = title
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == ' ' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let file_name = format!(
+        "{} ({}-{}).wav",
+        safe_title.trim(),
+        file_timestamp(start_seconds),
+        file_timestamp(end_seconds)
+    );
+
+    let destination = tokio::task::spawn_blocking(move || {
+        app.dialog()
+            .file()
+            .set_title("Esporta clip")
+            .set_file_name(file_name)
+            .add_filter("Audio WAV", &["wav"])
+            .blocking_save_file()
+            .and_then(|file| file.into_path().ok())
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+    let Some(destination) = destination else {
+        return Ok(None);
+    };
+
+    let duration_seconds = {
+        let destination = destination.clone();
+        tokio::task::spawn_blocking(move || extract_clip(&audio_path, &destination, range))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+    };
+
+    {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        record_meeting_event(
+            &conn,
+            &meeting_id,
+            "clip_exported",
+            Some(start_seconds),
+            &serde_json::json!({
+                "start_seconds": start_seconds,
+                "end_seconds": end_seconds,
+                "duration_seconds": duration_seconds,
+                "path": destination.to_string_lossy(),
+            }),
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(Some(destination.to_string_lossy().to_string()))
+}
+
+/// Silenzia (o copre con un bip) gli intervalli nella registrazione e
+/// nell'eventuale originale pre-miglioramento, elimina le copie master (vedi
+/// `redact_recording_files`) e oscura i segmenti corrispondenti di tutte le
+/// trascrizioni. Le trascrizioni senza segmenti restano intatte e vengono
+/// contate nel risultato, perché il frontend chieda di correggerle.
+#[tauri::command]
+pub async fn redact_meeting_audio(
+    state: State<'_, AppState>,
+    meeting_id: String,
+    ranges: Vec<TimeRange>,
+    mode: RedactionMode,
+) -> Result<TranscriptRedaction, String> {
+    if ranges.is_empty() {
+        return Err("Nessun intervallo da oscurare".to_string());
+    }
+    for range in &ranges {
+        validate_range(range)?;
+    }
+    let (audio_path, _) = recorded_meeting_audio(&state, &meeting_id)?;
+
+    let removed_files = {
+        let ranges = ranges.clone();
+        tokio::task::spawn_blocking(move || redact_recording_files(&audio_path, &ranges, mode))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+    };
+
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    let redaction = redact_transcript(&conn, &meeting_id, &ranges).map_err(|e| e.to_string())?;
+
+    // Il silenzio inserito cambia sia il parlato rilevato sia la forma d'onda
+    conn.execute(
+        "DELETE FROM silence_maps WHERE meeting_id = ?1",
+        params![meeting_id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM waveforms WHERE meeting_id = ?1",
+        params![meeting_id],
+    )
+    .map_err(|e| e.to_string())?;
+
+    record_meeting_event(
+        &conn,
+        &meeting_id,
+        "redacted",
+        None,
+        &serde_json::json!({
+            "ranges": ranges,
+            "mode": mode,
+            "transcript_segments": redaction.transcript_segments,
+            "unredacted_transcriptions": redaction.unredacted_transcriptions,
+            "removed_files": removed_files,
+        }),
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(redaction)
+}
+
+/// Analizza la qualità dell'audio di una riunione già registrata (o
+/// importata) e salva il riepilogo. Le registrazioni fatte con l'app lo
+/// ottengono già a fine cattura, con una voce per dispositivo.
+#[tauri::command]
+pub async fn analyze_meeting_quality(
+    state: State<'_, AppState>,
+    meeting_id: String,
+) -> Result<QualityReport, String> {
+    let (audio_path, _) = recorded_meeting_audio(&state, &meeting_id)?;
+
+    let report = tokio::task::spawn_blocking(move || analyze_quality(&audio_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    save_quality_report(&conn, &meeting_id, &report).map_err(|e| e.to_string())?;
+    Ok(report)
+}
+
+/// Porzione della forma d'onda di una riunione fra `start` ed `end` (in
+/// secondi, di default l'intera registrazione), ridotta a `buckets` coppie
+/// minimo/massimo. Durante la registrazione usa i picchi in costruzione; se
+/// la riunione non ha picchi salvati li calcola dal file audio.
+#[tauri::command]
+pub async fn get_waveform(
+    state: State<'_, AppState>,
+    meeting_id: String,
+    start: Option<f64>,
+    end: Option<f64>,
+    buckets: usize,
+) -> Result<WaveformSlice, String> {
+    let (audio_path, saved) = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let audio_path: Option<String> = conn
+            .query_row(
+                "SELECT audio_path FROM meetings WHERE id = ?1",
+                params![meeting_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| e.to_string())?;
+        let saved = load_waveform(&conn, &meeting_id).map_err(|e| e.to_string())?;
+        (audio_path, saved)
+    };
+
+    let audio_path = audio_path
+        .map(PathBuf::from)
+        .ok_or_else(|| "La riunione non ha una registrazione audio".to_string())?;
+
+    {
+        let recorder = state.recorder.lock().map_err(|e| e.to_string())?;
+        if recorder.is_recording() && recorder.output_path.as_ref() == Some(&audio_path) {
+            if let Some(waveform) = recorder.waveform() {
+                let waveform = waveform.lock().map_err(|e| e.to_string())?;
+                return Ok(waveform.slice(start, end, buckets));
+            }
+        }
+    }
+
+    if let Some(waveform) = saved {
+        return Ok(waveform.slice(start, end, buckets));
+    }
+
+    if !audio_path.is_file() {
+        return Err("File audio della riunione non trovato".to_string());
+    }
+    let waveform = tokio::task::spawn_blocking(move || analyze_waveform(&audio_path))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+    {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        if let Err(e) = save_waveform(&conn, &meeting_id, &waveform) {
+            eprintln!("Errore salvataggio forma d'onda: {}", e);
+        }
+    }
+
+    Ok(waveform.slice(start, end, buckets))
+}
+
+/// Importa un file audio/video esistente come nuova riunione, convertendolo
+/// nel formato delle registrazioni
+#[tauri::command]
+pub async fn import_media(
+    state: State<'_, AppState>,
+    path: String,
+    title: Option<String>,
+) -> Result<Meeting, String> {
+    let source_path = PathBuf::from(&path);
+    if !source_path.is_file() {
+        return Err(format!("File non trovato: {}", path));
+    }
+
+    let meeting_id = Uuid::new_v4().to_string();
+
+    let recordings_dir = state.app_data_dir.join("recordings");
+    std::fs::create_dir_all(&recordings_dir).map_err(|e| e.to_string())?;
+
+    let audio_path = recordings_dir.join(format!("{}.wav", meeting_id));
+
+    // La decodifica di file lunghi richiede tempo: la si esegue fuori dal
+    // runtime async per non bloccare gli altri comandi
+    let imported = {
+        let source_path = source_path.clone();
+        let audio_path = audio_path.clone();
+        tokio::task::spawn_blocking(move || import_to_wav(&source_path, &audio_path))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| e.to_string())?
+    };
+
+    let storage_codec = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        load_app_settings(&conn).storage_codec
+    };
+    let audio_path = match compress_meeting_audio(audio_path.clone(), storage_codec).await {
+        Ok(compressed) => compressed,
+        Err(e) => {
+            eprintln!("Errore compressione file importato: {}", e);
+            audio_path
+        }
+    };
+
+    let default_title = source_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| format!("Importazione {}", Local::now().format("%d/%m/%Y %H:%M")));
+
+    let meeting = Meeting {
+        id: meeting_id,
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or(default_title),
+        created_at: Utc::now().to_rfc3339(),
+        duration_seconds: Some(imported.duration_seconds.round() as i64),
+        audio_path: Some(audio_path.to_string_lossy().to_string()),
+        status: "recorded".to_string(),
+    };
+
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    let result = conn
+        .execute(
+            "INSERT INTO meetings (id, title, created_at, duration_seconds, audio_path, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                meeting.id,
+                meeting.title,
+                meeting.created_at,
+                meeting.duration_seconds,
+                meeting.audio_path,
+                meeting.status
+            ],
+        )
+        .and_then(|_| {
+            record_meeting_event(
+                &conn,
+                &meeting.id,
+                "imported",
+                None,
+                &serde_json::json!({
+                    "source_path": path,
+                    "codec": imported.source_codec,
+                    "sample_rate": imported.source_sample_rate,
+                    "channels": imported.source_channels,
+                }),
+            )
+        });
+
+    if let Err(e) = result {
+        let _ = std::fs::remove_file(&audio_path);
+        return Err(e.to_string());
+    }
+
+    Ok(meeting)
+}
+
+/// Mostra la finestra di selezione per i file da importare
+#[tauri::command]
+pub async fn select_media_file(app: AppHandle) -> Result<Option<String>, String> {
+    tokio::task::spawn_blocking(move || {
+        app.dialog()
+            .file()
+            .set_title("Importa registrazione")
+            .add_filter("Audio e video", SUPPORTED_EXTENSIONS)
+            .blocking_pick_file()
+            .and_then(|file| file.into_path().ok())
+            .map(|path| path.to_string_lossy().to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())
+}
+
+fn set_current_meeting_status(state: &AppState, status: &str) -> Result<(), String> {
+    let meeting_id = state
+        .current_meeting_id
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone();
+
+    if let Some(meeting_id) = meeting_id {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        conn.execute(
+            "UPDATE meetings SET status = ?1 WHERE id = ?2",
+            params![status, meeting_id],
+        )
+        .map_err(|e| e.to_string())?;
+    }
+
+    Ok(())
+}
+
+/// Salva i segmenti di una registrazione WAV divisa in più file
+fn save_recording_segments(conn: &Connection, meeting_id: &str, path: &Path) {
+    match segment_manifest(path) {
+        Ok(segments) if segments.len() > 1 => {
+            if let Err(e) = save_audio_segments(conn, meeting_id, &segments) {
+                eprintln!("Errore salvataggio segmenti: {}", e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => eprintln!("Errore lettura segmenti {}: {}", path.display(), e),
+    }
+}
+
+/// Durata di una registrazione WAV, segmenti compresi
+fn wav_duration_seconds(path: &Path) -> Option<i64> {
+    let segments = segment_manifest(path).ok()?;
+    let total: f64 = segments.iter().map(|s| s.duration_seconds).sum();
+    Some(total as i64)
+}
+
+/// Recupera le registrazioni interrotte da un crash: ripara gli header dei
+/// WAV in `recordings_dir`, chiude le riunioni rimaste "in registrazione" e
+/// ricrea quelle mancanti per i file orfani.
+pub fn recover_interrupted_recordings(conn: &Connection, recordings_dir: &Path) {
+    remove_partial_archives(recordings_dir);
+    let repaired = repair_recordings(recordings_dir);
+    let repaired_paths: HashSet<_> = repaired.iter().map(|r| r.path.clone()).collect();
+
+    let interrupted: Vec<(String, Option<String>)> = match conn
+        .prepare("SELECT id, audio_path FROM meetings WHERE status IN ('recording', 'paused')")
+        .and_then(|mut stmt| {
+            let rows = stmt
+                .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+                .collect();
+            rows
+        }) {
+        Ok(rows) => rows,
+        Err(e) => {
+            eprintln!("Errore ricerca registrazioni interrotte: {}", e);
+            return;
+        }
+    };
+
+    for (meeting_id, audio_path) in interrupted {
+        let audio_path = audio_path.map(std::path::PathBuf::from);
+        let duration_seconds = audio_path.as_deref().and_then(wav_duration_seconds);
+        // Il crash può aver lasciato aperto un segmento qualsiasi, non solo
+        // il primo file
+        let header_repaired = audio_path
+            .as_deref()
+            .map(|p| {
+                recording_parts(p)
+                    .iter()
+                    .any(|part| repaired_paths.contains(part))
+            })
+            .unwrap_or(false);
+
+        let result = conn
+            .execute(
+                "UPDATE meetings SET status = 'recorded', duration_seconds = ?1 WHERE id = ?2",
+                params![duration_seconds, meeting_id],
+            )
+            .and_then(|_| {
+                record_meeting_event(
+                    conn,
+                    &meeting_id,
+                    "recovered",
+                    duration_seconds.map(|d| d as f64),
+                    &serde_json::json!({ "header_repaired": header_repaired }),
+                )
+            });
+
+        if let Some(path) = &audio_path {
+            save_recording_segments(conn, &meeting_id, path);
+        }
+
+        match result {
+            Ok(()) => println!("Registrazione interrotta recuperata: {}", meeting_id),
+            Err(e) => eprintln!("Errore recupero riunione {}: {}", meeting_id, e),
+        }
+    }
+
+    // WAV riparati senza una riunione associata (es. crash prima del salvataggio)
+    for wav in repaired {
+        let Some(meeting_id) = wav
+            .path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .filter(|s| Uuid::parse_str(s).is_ok())
+        else {
+            continue;
+        };
+
+        let exists = conn
+            .query_row(
+                "SELECT 1 FROM meetings WHERE id = ?1",
+                params![meeting_id],
+                |_| Ok(()),
+            )
+            .optional();
+        if !matches!(exists, Ok(None)) {
+            continue;
+        }
+
+        let created_at = std::fs::metadata(&wav.path)
+            .and_then(|m| m.created().or_else(|_| m.modified()))
+            .map(chrono::DateTime::<Utc>::from)
+            .unwrap_or_else(|_| Utc::now());
+        let title = format!(
+            "Registrazione recuperata {}",
+            created_at.with_timezone(&Local).format("%d/%m/%Y %H:%M")
+        );
+
+        let result = conn.execute(
+            "INSERT INTO meetings (id, title, created_at, duration_seconds, audio_path, status) VALUES (?1, ?2, ?3, ?4, ?5, 'recorded')",
+            params![
+                meeting_id,
+                title,
+                created_at.to_rfc3339(),
+                wav_duration_seconds(&wav.path).unwrap_or(wav.duration_seconds as i64),
+                wav.path.to_string_lossy().to_string()
+            ],
+        );
+        if result.is_ok() {
+            save_recording_segments(conn, meeting_id, &wav.path);
+        }
+
+        match result {
+            Ok(_) => println!("Riunione ricreata per {}", wav.path.display()),
+            Err(e) => eprintln!("Errore ricreazione riunione {}: {}", meeting_id, e),
+        }
+    }
+}
+
+/// Inoltra al frontend gli eventi del thread di registrazione e li salva
+/// fra i metadati della riunione in corso.
+pub fn handle_recorder_event(app: &AppHandle, event: RecorderEvent) {
+    let (event_name, kind, offset_seconds) = match &event {
+        // I livelli servono solo al VU meter e non vengono salvati
+        RecorderEvent::Level { levels } => {
+            let _ = app.emit("audio-level", levels);
+            return;
+        }
+        // I picchi live servono solo alla forma d'onda: quelli completi
+        // vengono salvati allo stop
+        RecorderEvent::Waveform { .. } => {
+            let _ = app.emit("audio-waveform", &event);
+            return;
+        }
+        RecorderEvent::DeviceLost { offset_seconds, .. } => (
+            "recording-device-lost",
+            "device_lost",
+            Some(*offset_seconds),
+        ),
+        RecorderEvent::DeviceSwitched { offset_seconds, .. } => (
+            "recording-device-switched",
+            "device_switched",
+            Some(*offset_seconds),
+        ),
+        RecorderEvent::Paused { offset_seconds } => {
+            ("recording-paused", "paused", Some(*offset_seconds))
+        }
+        RecorderEvent::Resumed { offset_seconds, .. } => {
+            ("recording-resumed", "resumed", Some(*offset_seconds))
+        }
+        RecorderEvent::CaptureStats { .. } => ("recording-capture-stats", "capture_stats", None),
+        // Gli enunciati vanno solo alla trascrizione live: la mappa completa
+        // viene salvata a fine registrazione
+        RecorderEvent::Utterance {
+            start_seconds,
+            end_seconds,
+            audio_path,
+            segment_start_seconds,
+        } => {
+            let segment = SpeechSegment {
+                start_seconds: *start_seconds,
+                end_seconds: *end_seconds,
+            };
+            let audio_path = audio_path.clone();
+            let segment_start_seconds = *segment_start_seconds;
+            tauri::async_runtime::spawn(async move {
+                let sent =
+                    send_streaming_utterance(segment, &audio_path, segment_start_seconds).await;
+                if let Err(e) = sent {
+                    eprintln!("Errore invio enunciato alla trascrizione: {}", e);
+                }
+            });
+            return;
+        }
+        RecorderEvent::QualityWarning { warning, .. } => (
+            "recording-quality-warning",
+            "quality_warning",
+            Some(warning.offset_seconds),
+        ),
+        RecorderEvent::QualityReport { report } => {
+            let state = app.state::<AppState>();
+            let meeting_id = state
+                .current_meeting_id
+                .lock()
+                .ok()
+                .and_then(|current| current.clone());
+            if let (Some(meeting_id), Ok(conn)) = (meeting_id, state.db.conn.lock()) {
+                if let Err(e) = save_quality_report(&conn, &meeting_id, report) {
+                    eprintln!("Errore salvataggio qualità audio: {}", e);
+                }
+            }
+            return;
+        }
+        RecorderEvent::SpeechAnalysis { silence_map } => {
+            let state = app.state::<AppState>();
+            let meeting_id = state
+                .current_meeting_id
+                .lock()
+                .ok()
+                .and_then(|current| current.clone());
+            if let (Some(meeting_id), Ok(conn)) = (meeting_id, state.db.conn.lock()) {
+                if let Err(e) = save_silence_map(&conn, &meeting_id, silence_map) {
+                    eprintln!("Errore salvataggio mappa del silenzio: {}", e);
+                }
+            }
+            return;
+        }
+        RecorderEvent::SilenceTimeout { offset_seconds, .. } => (
+            "recording-silence-timeout",
+            "silence_timeout",
+            Some(*offset_seconds),
+        ),
+    };
+
+    let _ = app.emit(event_name, &event);
+
+    let state = app.state::<AppState>();
+    let meeting_id = state
+        .current_meeting_id
+        .lock()
+        .ok()
+        .and_then(|current| current.clone());
+
+    if let (Some(meeting_id), Ok(conn)) = (meeting_id, state.db.conn.lock()) {
+        let details = serde_json::to_value(&event).unwrap_or_default();
+        if let Err(e) = record_meeting_event(&conn, &meeting_id, kind, offset_seconds, &details) {
+            eprintln!("Errore salvataggio evento riunione: {}", e);
+        }
+    }
+
+    // Lo stop parte dopo il salvataggio dell'evento, che richiede la
+    // riunione ancora attiva
+    if matches!(event, RecorderEvent::SilenceTimeout { .. }) {
+        spawn_auto_stop(app.clone());
+    }
+}
+
+/// Ferma la registrazione senza intervento dell'utente (silenzio prolungato
+/// o fine di una registrazione programmata). Il frontend riceve
+/// `recording-auto-stopped` con il percorso dell'audio, come dopo uno stop.
+pub(crate) fn spawn_auto_stop(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        let state = app.state::<AppState>();
+        match finish_recording(&state).await {
+            Ok(audio_path) => {
+                let _ = app.emit(
+                    "recording-auto-stopped",
+                    audio_path.to_string_lossy().to_string(),
+                );
+            }
+            Err(e) => eprintln!("Errore stop automatico della registrazione: {}", e),
+        }
+    });
 }
 
 #[tauri::command]
@@ -52,3 +1167,12 @@ pub fn is_recording(state: State<'_, AppState>) -> bool {
         .map(|r| r.is_recording())
         .unwrap_or(false)
 }
+
+#[tauri::command]
+pub fn is_recording_paused(state: State<'_, AppState>) -> bool {
+    state
+        .recorder
+        .lock()
+        .map(|r| r.is_paused())
+        .unwrap_or(false)
+}
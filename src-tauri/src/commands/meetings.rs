@@ -1,10 +1,19 @@
+use crate::audio::{
+    companion_files, segment_path, AudioSegment, QualityReport, SilenceMap, TimeRange,
+    WaveformPeaks, SAMPLES_PER_PEAK,
+};
+use crate::queue::{cancel_running_job, notify_jobs_changed};
+use crate::transcription::TranscriptionSegment;
 use crate::AppState;
 use chrono::Utc;
-use rusqlite::params;
+use rusqlite::{params, Connection, OptionalExtension};
 use serde::{Deserialize, Serialize};
-use tauri::State;
+use tauri::{AppHandle, State};
 use uuid::Uuid;
 
+/// Testo che prende il posto dei passaggi oscurati nella trascrizione
+const REDACTED_PLACEHOLDER: &str = "[omissis]";
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Meeting {
     pub id: String,
@@ -20,6 +29,32 @@ pub struct MeetingWithTranscript {
     pub meeting: Meeting,
     pub transcript: Option<String>,
     pub report: Option<MeetingReport>,
+    pub events: Vec<MeetingEvent>,
+    pub silence_map: Option<SilenceMap>,
+    pub quality_report: Option<QualityReport>,
+    /// Segmenti delle registrazioni oltre il limite di un singolo file;
+    /// vuoto se la registrazione è un file unico
+    pub audio_segments: Vec<AudioSegment>,
+}
+
+/// Evento registrato durante una riunione (cambio dispositivo, ecc.)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MeetingEvent {
+    pub kind: String,
+    pub offset_seconds: Option<f64>,
+    pub details: Option<serde_json::Value>,
+    pub created_at: String,
+}
+
+/// Esito dell'oscuramento delle trascrizioni di una riunione
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct TranscriptRedaction {
+    /// Segmenti oscurati, in tutte le trascrizioni della riunione
+    pub transcript_segments: usize,
+    /// Trascrizioni senza segmenti con i tempi (es. salvate senza
+    /// segmenti): non si può sapere quale testo oscurare e restano intatte,
+    /// quindi vanno corrette a mano
+    pub unredacted_transcriptions: usize,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,15 +223,273 @@ pub fn get_meeting(
         )
         .ok();
 
+    // Get events
+    let events = load_meeting_events(&conn, &id).map_err(|e| e.to_string())?;
+
+    let silence_map = load_silence_map(&conn, &id).map_err(|e| e.to_string())?;
+    let quality_report = load_quality_report(&conn, &id).map_err(|e| e.to_string())?;
+
+    let audio_segments = match &meeting.audio_path {
+        Some(path) => load_audio_segments(&conn, &id, std::path::Path::new(path))
+            .map_err(|e| e.to_string())?,
+        None => Vec::new(),
+    };
+
     Ok(MeetingWithTranscript {
         meeting,
         transcript,
         report,
+        events,
+        silence_map,
+        quality_report,
+        audio_segments,
     })
 }
 
+pub fn record_meeting_event(
+    conn: &Connection,
+    meeting_id: &str,
+    kind: &str,
+    offset_seconds: Option<f64>,
+    details: &serde_json::Value,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO meeting_events (meeting_id, kind, offset_seconds, details, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![
+            meeting_id,
+            kind,
+            offset_seconds,
+            details.to_string(),
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn load_meeting_events(
+    conn: &Connection,
+    meeting_id: &str,
+) -> rusqlite::Result<Vec<MeetingEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT kind, offset_seconds, details, created_at FROM meeting_events WHERE meeting_id = ?1 ORDER BY id",
+    )?;
+
+    let events = stmt
+        .query_map(params![meeting_id], |row| {
+            let details: Option<String> = row.get(2)?;
+            Ok(MeetingEvent {
+                kind: row.get(0)?,
+                offset_seconds: row.get(1)?,
+                details: details.and_then(|d| serde_json::from_str(&d).ok()),
+                created_at: row.get(3)?,
+            })
+        })?
+        .filter_map(|e| e.ok())
+        .collect();
+
+    Ok(events)
+}
+
+/// Salva (o sostituisce) la mappa del silenzio di una riunione
+pub fn save_silence_map(
+    conn: &Connection,
+    meeting_id: &str,
+    map: &SilenceMap,
+) -> rusqlite::Result<()> {
+    let speech_segments = serde_json::to_string(&map.speech).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT INTO silence_maps (meeting_id, total_seconds, speech_segments, created_at) VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT(meeting_id) DO UPDATE SET total_seconds = ?2, speech_segments = ?3, created_at = ?4",
+        params![
+            meeting_id,
+            map.total_seconds,
+            speech_segments,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn load_silence_map(
+    conn: &Connection,
+    meeting_id: &str,
+) -> rusqlite::Result<Option<SilenceMap>> {
+    conn.query_row(
+        "SELECT total_seconds, speech_segments FROM silence_maps WHERE meeting_id = ?1",
+        params![meeting_id],
+        |row| {
+            let speech_segments: String = row.get(1)?;
+            Ok(SilenceMap {
+                total_seconds: row.get(0)?,
+                speech: serde_json::from_str(&speech_segments).unwrap_or_default(),
+            })
+        },
+    )
+    .optional()
+}
+
+/// Salva (o sostituisce) il riepilogo della qualità audio di una riunione
+pub fn save_quality_report(
+    conn: &Connection,
+    meeting_id: &str,
+    report: &QualityReport,
+) -> rusqlite::Result<()> {
+    let report = serde_json::to_string(report).unwrap_or_else(|_| "{}".to_string());
+    conn.execute(
+        "INSERT INTO quality_reports (meeting_id, report, created_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(meeting_id) DO UPDATE SET report = ?2, created_at = ?3",
+        params![meeting_id, report, Utc::now().to_rfc3339()],
+    )?;
+    Ok(())
+}
+
+pub fn load_quality_report(
+    conn: &Connection,
+    meeting_id: &str,
+) -> rusqlite::Result<Option<QualityReport>> {
+    let report: Option<String> = conn
+        .query_row(
+            "SELECT report FROM quality_reports WHERE meeting_id = ?1",
+            params![meeting_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(report.and_then(|r| serde_json::from_str(&r).ok()))
+}
+
+/// Salva (o sostituisce) i picchi della forma d'onda di una riunione
+pub fn save_waveform(
+    conn: &Connection,
+    meeting_id: &str,
+    waveform: &WaveformPeaks,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO waveforms (meeting_id, sample_rate, samples_per_peak, total_samples, peaks, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(meeting_id) DO UPDATE SET sample_rate = ?2, samples_per_peak = ?3, total_samples = ?4, peaks = ?5, created_at = ?6",
+        params![
+            meeting_id,
+            waveform.sample_rate(),
+            SAMPLES_PER_PEAK,
+            waveform.total_samples() as i64,
+            waveform.to_bytes(),
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Carica i picchi salvati; quelli calcolati con una risoluzione diversa o
+/// illeggibili vengono ignorati, così vengono ricalcolati
+pub fn load_waveform(
+    conn: &Connection,
+    meeting_id: &str,
+) -> rusqlite::Result<Option<WaveformPeaks>> {
+    let row = conn
+        .query_row(
+            "SELECT sample_rate, samples_per_peak, total_samples, peaks FROM waveforms WHERE meeting_id = ?1",
+            params![meeting_id],
+            |row| {
+                Ok((
+                    row.get::<_, u32>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, Vec<u8>>(3)?,
+                ))
+            },
+        )
+        .optional()?;
+
+    Ok(
+        row.and_then(|(sample_rate, samples_per_peak, total_samples, peaks)| {
+            if samples_per_peak != SAMPLES_PER_PEAK || sample_rate == 0 {
+                return None;
+            }
+            WaveformPeaks::from_bytes(sample_rate, total_samples.max(0) as u64, &peaks)
+        }),
+    )
+}
+
+/// Salva (o sostituisce) i segmenti di una registrazione. I percorsi non
+/// vengono salvati: si ricavano da `audio_path`, così restano validi dopo
+/// la compressione.
+pub fn save_audio_segments(
+    conn: &Connection,
+    meeting_id: &str,
+    segments: &[AudioSegment],
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "DELETE FROM audio_segments WHERE meeting_id = ?1",
+        params![meeting_id],
+    )?;
+    for segment in segments {
+        conn.execute(
+            "INSERT INTO audio_segments (meeting_id, segment_index, start_seconds, duration_seconds) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                meeting_id,
+                segment.index as i64,
+                segment.start_seconds,
+                segment.duration_seconds
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Carica i segmenti della registrazione `audio_path`, nell'ordine
+pub fn load_audio_segments(
+    conn: &Connection,
+    meeting_id: &str,
+    audio_path: &std::path::Path,
+) -> rusqlite::Result<Vec<AudioSegment>> {
+    let mut stmt = conn.prepare(
+        "SELECT segment_index, start_seconds, duration_seconds FROM audio_segments WHERE meeting_id = ?1 ORDER BY segment_index",
+    )?;
+
+    let segments = stmt
+        .query_map(params![meeting_id], |row| {
+            let index = row.get::<_, i64>(0)?.max(0) as usize;
+            Ok(AudioSegment {
+                index,
+                path: segment_path(audio_path, index)
+                    .to_string_lossy()
+                    .to_string(),
+                start_seconds: row.get(1)?,
+                duration_seconds: row.get(2)?,
+            })
+        })?
+        .filter_map(|s| s.ok())
+        .collect();
+
+    Ok(segments)
+}
+
 #[tauri::command]
-pub fn delete_meeting(state: State<'_, AppState>, id: String) -> Result<(), String> {
+pub async fn delete_meeting(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    // Le trascrizioni in corso vanno fermate prima di togliere i job,
+    // altrimenti salverebbero il risultato su una riunione eliminata
+    let running_jobs: Vec<String> = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let mut stmt = conn
+            .prepare(
+                "SELECT id FROM transcription_jobs WHERE meeting_id = ?1 AND status = 'running'",
+            )
+            .map_err(|e| e.to_string())?;
+        let ids = stmt
+            .query_map(params![id], |row| row.get(0))
+            .map_err(|e| e.to_string())?
+            .filter_map(|r| r.ok())
+            .collect();
+        ids
+    };
+    for job_id in &running_jobs {
+        // Il job può essere finito nel frattempo
+        let _ = cancel_running_job(job_id).await;
+    }
+
     let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
 
     // Get audio path to delete file
@@ -208,12 +501,56 @@ pub fn delete_meeting(state: State<'_, AppState>, id: String) -> Result<(), Stri
         )
         .ok();
 
+    conn.execute(
+        "DELETE FROM transcript_segments WHERE transcription_id IN (SELECT id FROM transcriptions WHERE meeting_id = ?1)",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+
     // Delete from database (cascades to transcriptions and reports)
     conn.execute("DELETE FROM meetings WHERE id = ?1", params![id])
         .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM meeting_events WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM silence_maps WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute("DELETE FROM waveforms WHERE meeting_id = ?1", params![id])
+        .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM quality_reports WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM audio_segments WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "UPDATE scheduled_recordings SET meeting_id = NULL WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    conn.execute(
+        "DELETE FROM transcription_jobs WHERE meeting_id = ?1",
+        params![id],
+    )
+    .map_err(|e| e.to_string())?;
+    drop(conn);
+    notify_jobs_changed(&app);
 
-    // Delete audio file if exists
+    // Delete audio file (and its companion files) if exists
     if let Some(path) = audio_path {
+        let path = std::path::PathBuf::from(path);
+        for companion in companion_files(&path) {
+            let _ = std::fs::remove_file(companion);
+        }
         let _ = std::fs::remove_file(path);
     }
 
@@ -226,23 +563,198 @@ pub fn save_transcription(
     meeting_id: String,
     content: String,
     language: Option<String>,
+    segments: Option<Vec<TranscriptionSegment>>,
 ) -> Result<String, String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    insert_transcription(
+        &conn,
+        &meeting_id,
+        &content,
+        language.as_deref(),
+        &segments.unwrap_or_default(),
+    )
+    .map_err(|e| e.to_string())
+}
+
+/// Salva una trascrizione della riunione, con i suoi segmenti, e segna la
+/// riunione come trascritta. Restituisce l'id della trascrizione.
+pub fn insert_transcription(
+    conn: &Connection,
+    meeting_id: &str,
+    content: &str,
+    language: Option<&str>,
+    segments: &[TranscriptionSegment],
+) -> rusqlite::Result<String> {
     let id = Uuid::new_v4().to_string();
     let created_at = Utc::now().to_rfc3339();
 
-    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
-
     conn.execute(
         "INSERT INTO transcriptions (id, meeting_id, content, language, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
         params![id, meeting_id, content, language, created_at],
-    ).map_err(|e| e.to_string())?;
+    )?;
+
+    // I segmenti con i tempi servono a tenere allineati audio e testo
+    // quando un passaggio viene oscurato
+    for segment in segments {
+        conn.execute(
+            "INSERT INTO transcript_segments (transcription_id, start_seconds, end_seconds, text) VALUES (?1, ?2, ?3, ?4)",
+            params![id, segment.start, segment.end, segment.text],
+        )?;
+    }
 
     // Update meeting status
     conn.execute(
         "UPDATE meetings SET status = 'transcribed' WHERE id = ?1",
         params![meeting_id],
-    )
-    .map_err(|e| e.to_string())?;
+    )?;
 
     Ok(id)
 }
+
+/// Segna come oscurati i segmenti di tutte le trascrizioni della riunione
+/// che cadono negli intervalli, ne cancella il testo e ricostruisce il
+/// testo completo.
+///
+/// Gli intervalli sono sulla timeline dell'audio (vedi `TimeRange`), la
+/// stessa dei segmenti trascritti: le pause non sono registrate e i
+/// segmenti dei file successivi al primo vengono spostati del loro inizio
+/// (`transcribe_recording`). Non serve quindi alcuna conversione.
+pub fn redact_transcript(
+    conn: &Connection,
+    meeting_id: &str,
+    ranges: &[TimeRange],
+) -> rusqlite::Result<TranscriptRedaction> {
+    let mut stmt = conn.prepare(
+        "SELECT t.id, t.content, (SELECT COUNT(*) FROM transcript_segments s WHERE s.transcription_id = t.id)
+         FROM transcriptions t WHERE t.meeting_id = ?1",
+    )?;
+    let transcriptions = stmt
+        .query_map(params![meeting_id], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut redaction = TranscriptRedaction::default();
+    for (transcription_id, content, segments) in transcriptions {
+        if segments == 0 {
+            if !content.trim().is_empty() {
+                redaction.unredacted_transcriptions += 1;
+            }
+            continue;
+        }
+        redaction.transcript_segments += redact_transcription(conn, &transcription_id, ranges)?;
+    }
+
+    Ok(redaction)
+}
+
+/// Oscura i segmenti di una trascrizione e restituisce quanti ne ha oscurati
+fn redact_transcription(
+    conn: &Connection,
+    transcription_id: &str,
+    ranges: &[TimeRange],
+) -> rusqlite::Result<usize> {
+    let mut redacted = 0;
+    for range in ranges {
+        redacted += conn.execute(
+            "UPDATE transcript_segments SET redacted = 1, text = ''
+             WHERE transcription_id = ?1 AND redacted = 0 AND start_seconds < ?3 AND end_seconds > ?2",
+            params![transcription_id, range.start_seconds, range.end_seconds],
+        )?;
+    }
+    if redacted == 0 {
+        return Ok(0);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT text, redacted FROM transcript_segments WHERE transcription_id = ?1 ORDER BY start_seconds, id",
+    )?;
+    let segments = stmt
+        .query_map(params![transcription_id], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, bool>(1)?))
+        })?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+
+    // Passaggi oscurati consecutivi diventano un solo segnaposto
+    let mut parts: Vec<&str> = Vec::new();
+    for (text, is_redacted) in &segments {
+        if *is_redacted {
+            if parts.last() != Some(&REDACTED_PLACEHOLDER) {
+                parts.push(REDACTED_PLACEHOLDER);
+            }
+        } else if !text.is_empty() {
+            parts.push(text);
+        }
+    }
+
+    conn.execute(
+        "UPDATE transcriptions SET content = ?1 WHERE id = ?2",
+        params![parts.join(" "), transcription_id],
+    )?;
+
+    Ok(redacted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::schema::run_migrations;
+
+    fn segment(start: f64, end: f64, text: &str) -> TranscriptionSegment {
+        TranscriptionSegment {
+            start,
+            end,
+            text: text.to_string(),
+        }
+    }
+
+    fn content(conn: &Connection, transcription_id: &str) -> String {
+        conn.query_row(
+            "SELECT content FROM transcriptions WHERE id = ?1",
+            params![transcription_id],
+            |row| row.get(0),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn redaction_covers_every_transcription() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO meetings (id, title) VALUES ('m', 'Riunione')",
+            [],
+        )
+        .unwrap();
+
+        let segments = [
+            segment(0.0, 2.0, "Buongiorno."),
+            segment(2.0, 4.0, "Il codice è 1234."),
+            segment(4.0, 6.0, "Procediamo."),
+        ];
+        let first = insert_transcription(&conn, "m", "", Some("it"), &segments).unwrap();
+        let second = insert_transcription(&conn, "m", "", Some("it"), &segments).unwrap();
+        insert_transcription(&conn, "m", "Testo incollato a mano", None, &[]).unwrap();
+
+        let range = TimeRange {
+            start_seconds: 2.5,
+            end_seconds: 3.5,
+        };
+        let redaction = redact_transcript(&conn, "m", &[range]).unwrap();
+
+        assert_eq!(
+            redaction,
+            TranscriptRedaction {
+                transcript_segments: 2,
+                unredacted_transcriptions: 1,
+            }
+        );
+        for id in [first, second] {
+            assert_eq!(content(&conn, &id), "Buongiorno. [omissis] Procediamo.");
+        }
+    }
+}
@@ -1,5 +1,7 @@
 pub mod audio;
 pub mod llm;
 pub mod meetings;
+pub mod schedules;
 pub mod settings;
 pub mod transcription;
+pub mod transcription_jobs;
@@ -0,0 +1,296 @@
+use crate::commands::audio::spawn_auto_stop;
+use crate::AppState;
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+/// Registrazione programmata. Parte a `start_at` e si ferma al primo fra
+/// orario di fine, durata massima e silenzio prolungato; almeno una delle
+/// tre regole è sempre presente.
+///
+/// `status` vale `pending` finché non parte, poi `running`, e infine
+/// `completed`, `missed` (l'app non era aperta o l'orario era già passato)
+/// o `failed` (la registrazione non è partita, il motivo è in `error`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledRecording {
+    pub id: String,
+    pub title: String,
+    /// Dispositivi da registrare; vuoto per quello preferito
+    pub device_ids: Vec<String>,
+    pub start_at: DateTime<Utc>,
+    pub end_at: Option<DateTime<Utc>>,
+    pub max_duration_minutes: Option<u32>,
+    pub silence_stop_minutes: Option<u32>,
+    pub status: String,
+    pub meeting_id: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub error: Option<String>,
+    pub created_at: String,
+}
+
+impl ScheduledRecording {
+    /// Istante in cui la registrazione va fermata, se c'è un limite di
+    /// tempo. La durata massima conta dall'avvio effettivo, o da `start_at`
+    /// se non è ancora partita.
+    pub fn stop_at(&self) -> Option<DateTime<Utc>> {
+        let by_duration = self.max_duration_minutes.map(|minutes| {
+            self.started_at.unwrap_or(self.start_at) + Duration::minutes(minutes as i64)
+        });
+
+        match (self.end_at, by_duration) {
+            (Some(end), Some(limit)) => Some(end.min(limit)),
+            (end, limit) => end.or(limit),
+        }
+    }
+}
+
+fn parse_time(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|time| time.with_timezone(&Utc))
+        .map_err(|_| format!("Data non valida: {}", value))
+}
+
+const SCHEDULE_COLUMNS: &str = "id, title, device_ids, start_at, end_at, max_duration_minutes,
+     silence_stop_minutes, status, meeting_id, started_at, error, created_at";
+
+fn schedule_from_row(row: &Row) -> rusqlite::Result<ScheduledRecording> {
+    let time = |index: usize| -> rusqlite::Result<Option<DateTime<Utc>>> {
+        let value: Option<String> = row.get(index)?;
+        Ok(value.and_then(|v| parse_time(&v).ok()))
+    };
+    let device_ids: String = row.get(2)?;
+
+    Ok(ScheduledRecording {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        device_ids: serde_json::from_str(&device_ids).unwrap_or_default(),
+        start_at: time(3)?.unwrap_or_default(),
+        end_at: time(4)?,
+        max_duration_minutes: row.get(5)?,
+        silence_stop_minutes: row.get(6)?,
+        status: row.get(7)?,
+        meeting_id: row.get(8)?,
+        started_at: time(9)?,
+        error: row.get(10)?,
+        created_at: row.get(11)?,
+    })
+}
+
+/// Registrazioni programmate non ancora concluse, in ordine di avvio
+pub fn load_active_schedules(conn: &Connection) -> rusqlite::Result<Vec<ScheduledRecording>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM scheduled_recordings
+         WHERE status IN ('pending', 'running')
+         ORDER BY start_at",
+        SCHEDULE_COLUMNS
+    ))?;
+    let schedules = stmt
+        .query_map([], schedule_from_row)?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    Ok(schedules)
+}
+
+fn load_schedule(conn: &Connection, id: &str) -> rusqlite::Result<Option<ScheduledRecording>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM scheduled_recordings WHERE id = ?1",
+            SCHEDULE_COLUMNS
+        ),
+        params![id],
+        schedule_from_row,
+    )
+    .optional()
+}
+
+/// Segna come partita una registrazione programmata
+pub fn mark_schedule_running(
+    conn: &Connection,
+    id: &str,
+    meeting_id: &str,
+    started_at: DateTime<Utc>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE scheduled_recordings SET status = 'running', meeting_id = ?1, started_at = ?2
+         WHERE id = ?3",
+        params![meeting_id, started_at.to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// Chiude una registrazione programmata con uno stato finale
+pub fn finish_schedule(
+    conn: &Connection,
+    id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE scheduled_recordings SET status = ?1, error = ?2 WHERE id = ?3",
+        params![status, error, id],
+    )?;
+    Ok(())
+}
+
+/// Programma una registrazione. Gli orari sono in RFC 3339; serve almeno
+/// una regola di stop fra `end_at`, `max_duration_minutes` e
+/// `silence_stop_minutes`.
+#[tauri::command]
+pub fn create_scheduled_recording(
+    state: State<'_, AppState>,
+    title: String,
+    device_ids: Option<Vec<String>>,
+    start_at: String,
+    end_at: Option<String>,
+    max_duration_minutes: Option<u32>,
+    silence_stop_minutes: Option<u32>,
+) -> Result<ScheduledRecording, String> {
+    let start_at = parse_time(&start_at)?;
+    let end_at = end_at.as_deref().map(parse_time).transpose()?;
+    let max_duration_minutes = max_duration_minutes.filter(|m| *m > 0);
+    let silence_stop_minutes = silence_stop_minutes.filter(|m| *m > 0);
+
+    if end_at.is_none() && max_duration_minutes.is_none() && silence_stop_minutes.is_none() {
+        return Err(
+            "Indica un orario di fine, una durata massima o uno stop per silenzio".to_string(),
+        );
+    }
+    if end_at.is_some_and(|end| end <= start_at) {
+        return Err("L'orario di fine deve essere successivo all'inizio".to_string());
+    }
+
+    let schedule = ScheduledRecording {
+        id: Uuid::new_v4().to_string(),
+        title: title.trim().to_string(),
+        device_ids: device_ids.unwrap_or_default(),
+        start_at,
+        end_at,
+        max_duration_minutes,
+        silence_stop_minutes,
+        status: "pending".to_string(),
+        meeting_id: None,
+        started_at: None,
+        error: None,
+        created_at: Utc::now().to_rfc3339(),
+    };
+    if schedule.stop_at().is_some_and(|stop| stop <= Utc::now()) {
+        return Err("La registrazione programmata terminerebbe nel passato".to_string());
+    }
+
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    conn.execute(
+        "INSERT INTO scheduled_recordings (id, title, device_ids, start_at, end_at,
+             max_duration_minutes, silence_stop_minutes, status, created_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+        params![
+            schedule.id,
+            schedule.title,
+            serde_json::to_string(&schedule.device_ids).map_err(|e| e.to_string())?,
+            schedule.start_at.to_rfc3339(),
+            schedule.end_at.map(|end| end.to_rfc3339()),
+            schedule.max_duration_minutes,
+            schedule.silence_stop_minutes,
+            schedule.status,
+            schedule.created_at,
+        ],
+    )
+    .map_err(|e| e.to_string())?;
+
+    Ok(schedule)
+}
+
+/// Registrazioni programmate in attesa o in corso
+#[tauri::command]
+pub fn get_scheduled_recordings(
+    state: State<'_, AppState>,
+) -> Result<Vec<ScheduledRecording>, String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    load_active_schedules(&conn).map_err(|e| e.to_string())
+}
+
+/// Annulla una registrazione programmata. Se è già partita viene fermata
+/// come dopo uno stop automatico.
+#[tauri::command]
+pub fn cancel_scheduled_recording(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    id: String,
+) -> Result<(), String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    let schedule = load_schedule(&conn, &id)
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| "Registrazione programmata non trovata".to_string())?;
+
+    match schedule.status.as_str() {
+        "pending" => {
+            conn.execute(
+                "DELETE FROM scheduled_recordings WHERE id = ?1",
+                params![id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        "running" => {
+            finish_schedule(&conn, &id, "completed", None).map_err(|e| e.to_string())?;
+            let is_current = schedule.meeting_id.is_some()
+                && *state.current_meeting_id.lock().map_err(|e| e.to_string())?
+                    == schedule.meeting_id;
+            if is_current {
+                spawn_auto_stop(app);
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule(end_at: Option<&str>, max_duration_minutes: Option<u32>) -> ScheduledRecording {
+        ScheduledRecording {
+            id: "s".to_string(),
+            title: "Riunione".to_string(),
+            device_ids: Vec::new(),
+            start_at: parse_time("2026-03-02T09:00:00Z").unwrap(),
+            end_at: end_at.map(|value| parse_time(value).unwrap()),
+            max_duration_minutes,
+            silence_stop_minutes: None,
+            status: "pending".to_string(),
+            meeting_id: None,
+            started_at: None,
+            error: None,
+            created_at: "2026-03-01T12:00:00Z".to_string(),
+        }
+    }
+
+    #[test]
+    fn stop_at_takes_the_earlier_limit() {
+        // Durata massima più breve dell'orario di fine
+        let short = schedule(Some("2026-03-02T10:00:00Z"), Some(30));
+        assert_eq!(short.stop_at(), parse_time("2026-03-02T09:30:00Z").ok());
+
+        // Orario di fine prima della durata massima
+        let long = schedule(Some("2026-03-02T10:00:00Z"), Some(90));
+        assert_eq!(long.stop_at(), parse_time("2026-03-02T10:00:00Z").ok());
+    }
+
+    #[test]
+    fn stop_at_counts_duration_from_actual_start() {
+        let mut late = schedule(Some("2026-03-02T10:00:00Z"), Some(30));
+        late.started_at = parse_time("2026-03-02T09:45:00Z").ok();
+        assert_eq!(late.stop_at(), parse_time("2026-03-02T10:00:00Z").ok());
+
+        late.end_at = None;
+        assert_eq!(late.stop_at(), parse_time("2026-03-02T10:15:00Z").ok());
+    }
+
+    #[test]
+    fn stop_at_without_time_limits() {
+        let mut silence_only = schedule(None, None);
+        silence_only.silence_stop_minutes = Some(5);
+        assert_eq!(silence_only.stop_at(), None);
+    }
+}
@@ -1,14 +1,50 @@
+use crate::audio::{EnhancementSettings, StorageCodec};
+use crate::transcription::set_sidecar_paths;
 use crate::AppState;
-use rusqlite::params;
+use rusqlite::{params, Connection};
 use serde::{Deserialize, Serialize};
 use tauri::State;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct AppSettings {
     pub whisper_model: String,
     pub default_language: Option<String>,
     pub auto_transcribe: bool,
     pub auto_generate_report: bool,
+    pub keep_master_copy: bool,
+    pub preferred_input_device: Option<String>,
+    pub storage_codec: StorageCodec,
+    /// Minuti di silenzio dopo cui la registrazione si ferma da sola (0 = mai)
+    pub auto_stop_silence_minutes: u32,
+    /// Secondi di audio conservati mentre la registrazione è armata
+    pub pre_roll_seconds: u32,
+    /// Catena di miglioramento del parlato: ogni stadio si attiva a parte
+    pub high_pass_enabled: bool,
+    pub high_pass_cutoff_hz: u32,
+    pub noise_suppression_enabled: bool,
+    pub noise_reduction_db: u32,
+    pub agc_enabled: bool,
+    pub agc_target_db: i32,
+    /// Interprete Python e script del sidecar di trascrizione; se vuoti
+    /// vengono cercati automaticamente
+    pub sidecar_python_path: Option<String>,
+    pub sidecar_script_path: Option<String>,
+}
+
+impl AppSettings {
+    /// Stadi della catena di miglioramento attivi
+    pub fn enhancement(&self) -> EnhancementSettings {
+        EnhancementSettings {
+            high_pass_hz: self
+                .high_pass_enabled
+                .then_some(self.high_pass_cutoff_hz as f32),
+            noise_reduction_db: self
+                .noise_suppression_enabled
+                .then_some(self.noise_reduction_db as f32),
+            agc_target_db: self.agc_enabled.then_some(self.agc_target_db as f32),
+        }
+    }
 }
 
 impl Default for AppSettings {
@@ -18,6 +54,19 @@ impl Default for AppSettings {
             default_language: Some("it".to_string()),
             auto_transcribe: false,
             auto_generate_report: false,
+            keep_master_copy: false,
+            preferred_input_device: None,
+            storage_codec: StorageCodec::Wav,
+            auto_stop_silence_minutes: 0,
+            pre_roll_seconds: 10,
+            high_pass_enabled: false,
+            high_pass_cutoff_hz: 80,
+            noise_suppression_enabled: false,
+            noise_reduction_db: 12,
+            agc_enabled: false,
+            agc_target_db: -20,
+            sidecar_python_path: None,
+            sidecar_script_path: None,
         }
     }
 }
@@ -25,7 +74,10 @@ impl Default for AppSettings {
 #[tauri::command]
 pub fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, String> {
     let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    Ok(load_app_settings(&conn))
+}
 
+pub fn load_app_settings(conn: &Connection) -> AppSettings {
     let get_setting = |key: &str| -> Option<String> {
         conn.query_row(
             "SELECT value FROM settings WHERE key = ?1",
@@ -35,7 +87,7 @@ pub fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, Strin
         .ok()
     };
 
-    Ok(AppSettings {
+    AppSettings {
         whisper_model: get_setting("whisper_model").unwrap_or_else(|| "base".to_string()),
         default_language: get_setting("default_language"),
         auto_transcribe: get_setting("auto_transcribe")
@@ -44,7 +96,40 @@ pub fn get_app_settings(state: State<'_, AppState>) -> Result<AppSettings, Strin
         auto_generate_report: get_setting("auto_generate_report")
             .map(|v| v == "true")
             .unwrap_or(false),
-    })
+        keep_master_copy: get_setting("keep_master_copy")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        preferred_input_device: get_setting("preferred_input_device"),
+        storage_codec: get_setting("storage_codec")
+            .and_then(|v| StorageCodec::from_setting(&v))
+            .unwrap_or_default(),
+        auto_stop_silence_minutes: get_setting("auto_stop_silence_minutes")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(0),
+        pre_roll_seconds: get_setting("pre_roll_seconds")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(10),
+        high_pass_enabled: get_setting("high_pass_enabled")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        high_pass_cutoff_hz: get_setting("high_pass_cutoff_hz")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(80),
+        noise_suppression_enabled: get_setting("noise_suppression_enabled")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        noise_reduction_db: get_setting("noise_reduction_db")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(12),
+        agc_enabled: get_setting("agc_enabled")
+            .map(|v| v == "true")
+            .unwrap_or(false),
+        agc_target_db: get_setting("agc_target_db")
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(-20),
+        sidecar_python_path: get_setting("sidecar_python_path"),
+        sidecar_script_path: get_setting("sidecar_script_path"),
+    }
 }
 
 #[tauri::command]
@@ -83,6 +168,60 @@ pub fn save_app_settings(state: State<'_, AppState>, settings: AppSettings) -> R
             "false"
         },
     )?;
+    if let Some(device_id) = &settings.preferred_input_device {
+        upsert("preferred_input_device", device_id)?;
+    }
+
+    upsert(
+        "keep_master_copy",
+        if settings.keep_master_copy {
+            "true"
+        } else {
+            "false"
+        },
+    )?;
+    upsert("storage_codec", settings.storage_codec.as_str())?;
+    upsert(
+        "auto_stop_silence_minutes",
+        &settings.auto_stop_silence_minutes.to_string(),
+    )?;
+
+    upsert("pre_roll_seconds", &settings.pre_roll_seconds.to_string())?;
+
+    let flag = |enabled: bool| if enabled { "true" } else { "false" };
+    upsert("high_pass_enabled", flag(settings.high_pass_enabled))?;
+    upsert(
+        "high_pass_cutoff_hz",
+        &settings.high_pass_cutoff_hz.to_string(),
+    )?;
+    upsert(
+        "noise_suppression_enabled",
+        flag(settings.noise_suppression_enabled),
+    )?;
+    upsert(
+        "noise_reduction_db",
+        &settings.noise_reduction_db.to_string(),
+    )?;
+    upsert("agc_enabled", flag(settings.agc_enabled))?;
+    upsert("agc_target_db", &settings.agc_target_db.to_string())?;
+
+    // Un percorso vuoto torna alla ricerca automatica
+    for (key, value) in [
+        ("sidecar_python_path", &settings.sidecar_python_path),
+        ("sidecar_script_path", &settings.sidecar_script_path),
+    ] {
+        match value.as_deref().map(str::trim).filter(|v| !v.is_empty()) {
+            Some(path) => upsert(key, path)?,
+            None => {
+                conn.execute("DELETE FROM settings WHERE key = ?1", params![key])
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+    }
+    set_sidecar_paths(
+        settings.sidecar_python_path.clone(),
+        settings.sidecar_script_path.clone(),
+    );
 
     Ok(())
 }
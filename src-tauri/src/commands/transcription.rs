@@ -1,31 +1,262 @@
+use crate::audio::{analyze_speech, segment_manifest, AudioSegment, SilenceMap, SpeechSegment};
+use crate::commands::meetings::{load_audio_segments, load_silence_map, save_silence_map};
+use crate::commands::transcription_jobs::{enqueue_job, TranscriptionJob};
+use crate::queue::{notify_jobs_changed, wake_transcription_worker};
 use crate::transcription::{
-    start_streaming_transcription, stop_streaming_transcription, transcribe_audio,
-    TranscriptionResult,
+    locate_sidecar, start_streaming_transcription, stop_streaming_transcription, transcribe_audio,
+    SidecarLaunch, TranscriptionError, TranscriptionProgress, TranscriptionResult,
 };
 use crate::AppState;
-use std::path::PathBuf;
-use tauri::{State, Window};
+use rusqlite::{params, OptionalExtension};
+use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+use tauri::{AppHandle, Emitter, Manager, State, Window};
+use tokio::sync::mpsc;
 
+/// Payload dell'evento "transcription-progress"
+#[derive(Clone, Serialize)]
+struct MeetingTranscriptionProgress {
+    job_id: String,
+    meeting_id: String,
+    #[serde(flatten)]
+    progress: TranscriptionProgress,
+}
+
+/// Mette in coda la trascrizione di una riunione e restituisce l'id del
+/// job. Al termine la trascrizione viene salvata e viene emesso l'evento
+/// "transcription-finished".
 #[tauri::command]
-pub async fn transcribe_meeting(
-    _state: State<'_, AppState>,
-    audio_path: String,
+pub fn transcribe_meeting(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    meeting_id: String,
     model_size: Option<String>,
     language: Option<String>,
-) -> Result<TranscriptionResult, String> {
-    // Use Python script from project directory
-    let python_script = std::env::current_dir()
+) -> Result<String, String> {
+    let job_id = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        enqueue_job(
+            &conn,
+            &meeting_id,
+            model_size.as_deref().unwrap_or("base"),
+            language.as_deref(),
+        )
         .map_err(|e| e.to_string())?
-        .join("python")
-        .join("src")
-        .join("main.py");
+    };
 
-    let audio = PathBuf::from(&audio_path);
-    let model = model_size.unwrap_or_else(|| "base".to_string());
+    notify_jobs_changed(&app);
+    wake_transcription_worker();
+    Ok(job_id)
+}
+
+/// Percorso attuale dell'audio di una riunione
+fn meeting_audio_path(state: &AppState, meeting_id: &str) -> Result<String, TranscriptionError> {
+    let conn = state
+        .db
+        .conn
+        .lock()
+        .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?;
+    conn.query_row(
+        "SELECT audio_path FROM meetings WHERE id = ?1",
+        params![meeting_id],
+        |row| row.get::<_, Option<String>>(0),
+    )
+    .optional()
+    .map_err(|e| TranscriptionError::TranscriptionFailed(e.to_string()))?
+    .flatten()
+    .ok_or_else(|| {
+        TranscriptionError::TranscriptionFailed("la riunione non ha una registrazione".to_string())
+    })
+}
+
+/// Trascrive la registrazione di un job, un segmento alla volta se è
+/// divisa in più file
+pub(crate) async fn transcribe_recording(
+    app: &AppHandle,
+    job: &TranscriptionJob,
+    cancelled: &AtomicBool,
+) -> Result<TranscriptionResult, TranscriptionError> {
+    let state = app.state::<AppState>();
+    // Il percorso si legge alla partenza: archiviazione e miglioramento
+    // dell'audio possono averlo cambiato mentre il job era in coda
+    let audio_path = meeting_audio_path(&state, &job.meeting_id)?;
+    let audio = Path::new(&audio_path);
+
+    // Solo le regioni di parlato vanno a Whisper; senza mappa (analisi
+    // fallita) si trascrive l'intero file
+    let speech_segments = speech_map(&state, Some(&job.meeting_id), audio)
+        .await
+        .map(|map| map.speech);
+
+    // Avanzamento e segmenti parziali, man mano che arrivano
+    let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+    let emitter = app.clone();
+    let (job_id, meeting_id) = (job.id.clone(), job.meeting_id.clone());
+    tokio::spawn(async move {
+        while let Some(progress) = progress_rx.recv().await {
+            let _ = emitter.emit(
+                "transcription-progress",
+                MeetingTranscriptionProgress {
+                    job_id: job_id.clone(),
+                    meeting_id: meeting_id.clone(),
+                    progress,
+                },
+            );
+        }
+    });
+
+    let segments = recording_segments(&state, Some(&job.meeting_id), audio);
+    if segments.len() <= 1 {
+        // Un annullamento arrivato durante l'analisi non raggiungerebbe il
+        // sidecar prima della richiesta
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(TranscriptionError::Cancelled);
+        }
+        return transcribe_audio(
+            audio,
+            &job.model_size,
+            job.language.clone(),
+            speech_segments,
+            Some(job.id.as_str()),
+            Some(progress_tx),
+        )
+        .await;
+    }
+    let total_seconds = segments
+        .last()
+        .map(|s| s.start_seconds + s.duration_seconds)
+        .unwrap_or_default();
+    let started = Instant::now();
+
+    // Registrazione divisa in più file: ogni segmento è trascritto a parte e
+    // i timestamp riportati sulla timeline dell'intera registrazione
+    let mut merged = TranscriptionResult {
+        text: String::new(),
+        language: None,
+        segments: Vec::new(),
+    };
+    for segment in segments {
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(TranscriptionError::Cancelled);
+        }
+
+        let end_seconds = segment.start_seconds + segment.duration_seconds;
+        let part_speech = speech_segments.as_ref().map(|speech| {
+            speech
+                .iter()
+                .filter(|s| s.start_seconds < end_seconds && s.end_seconds > segment.start_seconds)
+                .map(|s| SpeechSegment {
+                    start_seconds: s.start_seconds.max(segment.start_seconds)
+                        - segment.start_seconds,
+                    end_seconds: s.end_seconds.min(end_seconds) - segment.start_seconds,
+                })
+                .collect::<Vec<_>>()
+        });
+        if part_speech.as_ref().is_some_and(|speech| speech.is_empty()) {
+            continue;
+        }
 
-    transcribe_audio(&python_script, &audio, &model, language)
+        let (part_tx, mut part_rx) = mpsc::unbounded_channel::<TranscriptionProgress>();
+        let forward = progress_tx.clone();
+        let offset_seconds = segment.start_seconds;
+        tokio::spawn(async move {
+            while let Some(part) = part_rx.recv().await {
+                let _ = forward.send(part.on_timeline(
+                    offset_seconds,
+                    total_seconds,
+                    started.elapsed(),
+                ));
+            }
+        });
+
+        let result = transcribe_audio(
+            Path::new(&segment.path),
+            &job.model_size,
+            job.language.clone().or_else(|| merged.language.clone()),
+            part_speech,
+            Some(job.id.as_str()),
+            Some(part_tx),
+        )
+        .await?;
+
+        let text = result.text.trim();
+        if !text.is_empty() {
+            if !merged.text.is_empty() {
+                merged.text.push(' ');
+            }
+            merged.text.push_str(text);
+        }
+        merged.language = merged.language.or(result.language);
+        merged
+            .segments
+            .extend(result.segments.into_iter().map(|mut s| {
+                s.start += segment.start_seconds;
+                s.end += segment.start_seconds;
+                s
+            }));
+    }
+
+    Ok(merged)
+}
+
+/// Segmenti della registrazione: quelli salvati per la riunione o, se
+/// mancano, quelli letti dagli header WAV
+fn recording_segments(
+    state: &AppState,
+    meeting_id: Option<&str>,
+    audio_path: &Path,
+) -> Vec<AudioSegment> {
+    if let Some(meeting_id) = meeting_id {
+        if let Ok(conn) = state.db.conn.lock() {
+            if let Ok(segments) = load_audio_segments(&conn, meeting_id, audio_path) {
+                if !segments.is_empty() {
+                    return segments;
+                }
+            }
+        }
+    }
+
+    segment_manifest(audio_path).unwrap_or_default()
+}
+
+/// Mappa del silenzio della riunione: quella salvata durante la
+/// registrazione o, se manca, calcolata ora dal file e salvata
+async fn speech_map(
+    state: &AppState,
+    meeting_id: Option<&str>,
+    audio_path: &Path,
+) -> Option<SilenceMap> {
+    if let Some(meeting_id) = meeting_id {
+        let conn = state.db.conn.lock().ok()?;
+        if let Ok(Some(map)) = load_silence_map(&conn, meeting_id) {
+            return Some(map);
+        }
+    }
+
+    let path = audio_path.to_path_buf();
+    let analyzed = tokio::task::spawn_blocking(move || analyze_speech(&path))
         .await
         .map_err(|e| e.to_string())
+        .and_then(|result| result.map_err(|e| e.to_string()));
+    let map = match analyzed {
+        Ok(map) => map,
+        Err(e) => {
+            eprintln!("Errore analisi del parlato: {}", e);
+            return None;
+        }
+    };
+
+    if let Some(meeting_id) = meeting_id {
+        if let Ok(conn) = state.db.conn.lock() {
+            if let Err(e) = save_silence_map(&conn, meeting_id, &map) {
+                eprintln!("Errore salvataggio mappa del silenzio: {}", e);
+            }
+        }
+    }
+
+    Some(map)
 }
 
 #[tauri::command]
@@ -42,6 +273,13 @@ pub async fn start_streaming_transcription_command(
         .map_err(|e| e.to_string())
 }
 
+/// Dove verrà avviato il sidecar di trascrizione, o l'elenco dei percorsi
+/// provati se non è stato trovato
+#[tauri::command]
+pub fn check_transcription_sidecar() -> Result<SidecarLaunch, String> {
+    locate_sidecar().map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn stop_streaming_transcription_command() -> Result<(), String> {
     stop_streaming_transcription()
@@ -0,0 +1,323 @@
+use crate::queue::{
+    cancel_running_job, emit_job_finished, notify_jobs_changed, wake_transcription_worker,
+};
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::Serialize;
+use tauri::{AppHandle, State};
+use uuid::Uuid;
+
+/// Job terminati mostrati insieme alla coda
+const RECENT_FINISHED_JOBS: u32 = 20;
+
+/// Job della coda di trascrizione. I job in coda partono in ordine di
+/// `position`.
+///
+/// `status` vale `pending` finché è in coda (anche quando attende un nuovo
+/// tentativo dopo un crash del sidecar, fino a `next_attempt_at`), poi
+/// `running`, e infine `completed`, `failed` (il motivo è in `error`) o
+/// `cancelled`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TranscriptionJob {
+    pub id: String,
+    pub meeting_id: String,
+    pub meeting_title: Option<String>,
+    pub model_size: String,
+    pub language: Option<String>,
+    pub status: String,
+    pub position: i64,
+    /// Tentativi avviati finora
+    pub attempts: u32,
+    pub next_attempt_at: Option<String>,
+    pub error: Option<String>,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+}
+
+const JOB_COLUMNS: &str = "j.id, j.meeting_id, m.title, j.model_size, j.language, j.status,
+     j.position, j.attempts, j.next_attempt_at, j.error, j.created_at, j.started_at, j.finished_at";
+
+fn job_from_row(row: &Row) -> rusqlite::Result<TranscriptionJob> {
+    Ok(TranscriptionJob {
+        id: row.get(0)?,
+        meeting_id: row.get(1)?,
+        meeting_title: row.get(2)?,
+        model_size: row.get(3)?,
+        language: row.get(4)?,
+        status: row.get(5)?,
+        position: row.get(6)?,
+        attempts: row.get(7)?,
+        next_attempt_at: row.get(8)?,
+        error: row.get(9)?,
+        created_at: row.get(10)?,
+        started_at: row.get(11)?,
+        finished_at: row.get(12)?,
+    })
+}
+
+fn load_job(conn: &Connection, id: &str) -> rusqlite::Result<Option<TranscriptionJob>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM transcription_jobs j LEFT JOIN meetings m ON m.id = j.meeting_id
+             WHERE j.id = ?1",
+            JOB_COLUMNS
+        ),
+        params![id],
+        job_from_row,
+    )
+    .optional()
+}
+
+/// Posizione in fondo alla coda
+fn next_position(conn: &Connection) -> rusqlite::Result<i64> {
+    conn.query_row(
+        "SELECT COALESCE(MAX(position), 0) + 1 FROM transcription_jobs",
+        [],
+        |row| row.get(0),
+    )
+}
+
+/// Accoda la trascrizione di una riunione e restituisce l'id del job. Se la
+/// riunione è già in coda o in trascrizione restituisce il job esistente.
+pub fn enqueue_job(
+    conn: &Connection,
+    meeting_id: &str,
+    model_size: &str,
+    language: Option<&str>,
+) -> rusqlite::Result<String> {
+    let active = conn
+        .query_row(
+            "SELECT id FROM transcription_jobs
+             WHERE meeting_id = ?1 AND status IN ('pending', 'running')",
+            params![meeting_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(id) = active {
+        return Ok(id);
+    }
+
+    let id = Uuid::new_v4().to_string();
+    conn.execute(
+        "INSERT INTO transcription_jobs (id, meeting_id, model_size, language, status, position,
+             created_at)
+         VALUES (?1, ?2, ?3, ?4, 'pending', ?5, ?6)",
+        params![
+            id,
+            meeting_id,
+            model_size,
+            language,
+            next_position(conn)?,
+            Utc::now().to_rfc3339()
+        ],
+    )?;
+    Ok(id)
+}
+
+/// Primo job in coda che può partire adesso
+pub fn next_due_job(
+    conn: &Connection,
+    now: DateTime<Utc>,
+) -> rusqlite::Result<Option<TranscriptionJob>> {
+    conn.query_row(
+        &format!(
+            "SELECT {} FROM transcription_jobs j LEFT JOIN meetings m ON m.id = j.meeting_id
+             WHERE j.status = 'pending' AND (j.next_attempt_at IS NULL OR j.next_attempt_at <= ?1)
+             ORDER BY j.position, j.created_at
+             LIMIT 1",
+            JOB_COLUMNS
+        ),
+        params![now.to_rfc3339()],
+        job_from_row,
+    )
+    .optional()
+}
+
+/// Segna come avviato un job, contando il tentativo
+pub fn mark_job_running(conn: &Connection, id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE transcription_jobs
+         SET status = 'running', attempts = attempts + 1, next_attempt_at = NULL, started_at = ?1
+         WHERE id = ?2",
+        params![Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// Chiude un job con uno stato finale
+pub fn finish_job(
+    conn: &Connection,
+    id: &str,
+    status: &str,
+    error: Option<&str>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE transcription_jobs SET status = ?1, error = ?2, finished_at = ?3 WHERE id = ?4",
+        params![status, error, Utc::now().to_rfc3339(), id],
+    )?;
+    Ok(())
+}
+
+/// Rimette in coda un job fallito, da ritentare non prima di `at`
+pub fn retry_job_later(
+    conn: &Connection,
+    id: &str,
+    at: DateTime<Utc>,
+    error: &str,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE transcription_jobs SET status = 'pending', next_attempt_at = ?1, error = ?2
+         WHERE id = ?3",
+        params![at.to_rfc3339(), error, id],
+    )?;
+    Ok(())
+}
+
+/// Rimette in coda i job interrotti dalla chiusura (o da un crash)
+/// dell'app, così riprendono all'avvio successivo
+pub fn requeue_running_jobs(conn: &Connection) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE transcription_jobs SET status = 'pending' WHERE status = 'running'",
+        [],
+    )?;
+    Ok(())
+}
+
+/// Job in coda e in corso, seguiti dagli ultimi terminati
+#[tauri::command]
+pub fn get_transcription_jobs(state: State<'_, AppState>) -> Result<Vec<TranscriptionJob>, String> {
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM transcription_jobs j LEFT JOIN meetings m ON m.id = j.meeting_id
+             WHERE j.status IN ('pending', 'running')
+             ORDER BY j.status = 'pending', j.position, j.created_at",
+            JOB_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let mut jobs = stmt
+        .query_map([], job_from_row)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+
+    let mut stmt = conn
+        .prepare(&format!(
+            "SELECT {} FROM transcription_jobs j LEFT JOIN meetings m ON m.id = j.meeting_id
+             WHERE j.status NOT IN ('pending', 'running')
+             ORDER BY j.finished_at DESC
+             LIMIT ?1",
+            JOB_COLUMNS
+        ))
+        .map_err(|e| e.to_string())?;
+    let finished = stmt
+        .query_map(params![RECENT_FINISHED_JOBS], job_from_row)
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+    jobs.extend(finished);
+
+    Ok(jobs)
+}
+
+/// Riordina la coda: i job in attesa prendono l'ordine di `job_ids`, quelli
+/// non elencati restano dopo
+#[tauri::command]
+pub fn reorder_transcription_jobs(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    job_ids: Vec<String>,
+) -> Result<(), String> {
+    {
+        let mut conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let tx = conn.transaction().map_err(|e| e.to_string())?;
+
+        // I job non elencati finiscono in fondo, nell'ordine che avevano
+        let mut stmt = tx
+            .prepare(
+                "SELECT id FROM transcription_jobs WHERE status = 'pending'
+                 ORDER BY position, created_at",
+            )
+            .map_err(|e| e.to_string())?;
+        let pending = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+            .map_err(|e| e.to_string())?;
+        drop(stmt);
+        let unlisted = pending.iter().filter(|id| !job_ids.contains(id));
+
+        for (position, job_id) in job_ids.iter().chain(unlisted).enumerate() {
+            tx.execute(
+                "UPDATE transcription_jobs SET position = ?1 WHERE id = ?2 AND status = 'pending'",
+                params![position as i64 + 1, job_id],
+            )
+            .map_err(|e| e.to_string())?;
+        }
+        tx.commit().map_err(|e| e.to_string())?;
+    }
+
+    notify_jobs_changed(&app);
+    Ok(())
+}
+
+/// Rimette in fondo alla coda un job fallito o annullato
+#[tauri::command]
+pub fn retry_transcription_job(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    job_id: String,
+) -> Result<(), String> {
+    {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE transcription_jobs
+                 SET status = 'pending', position = ?1, attempts = 0, next_attempt_at = NULL,
+                     error = NULL, started_at = NULL, finished_at = NULL
+                 WHERE id = ?2 AND status IN ('failed', 'cancelled')",
+                params![next_position(&conn).map_err(|e| e.to_string())?, job_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated == 0 {
+            return Err("Solo i job falliti o annullati possono essere ritentati".to_string());
+        }
+    }
+
+    notify_jobs_changed(&app);
+    wake_transcription_worker();
+    Ok(())
+}
+
+/// Annulla un job: se è ancora in coda viene tolto, se è in corso il
+/// sidecar lo interrompe
+#[tauri::command]
+pub async fn cancel_transcription(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    job_id: String,
+) -> Result<(), String> {
+    let removed = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        let updated = conn
+            .execute(
+                "UPDATE transcription_jobs SET status = 'cancelled', finished_at = ?1
+                 WHERE id = ?2 AND status = 'pending'",
+                params![Utc::now().to_rfc3339(), job_id],
+            )
+            .map_err(|e| e.to_string())?;
+        if updated > 0 {
+            load_job(&conn, &job_id).map_err(|e| e.to_string())?
+        } else {
+            None
+        }
+    };
+
+    match removed {
+        Some(job) => {
+            emit_job_finished(&app, &job.id, &job.meeting_id, "cancelled", None);
+            notify_jobs_changed(&app);
+            Ok(())
+        }
+        None => cancel_running_job(&job_id).await,
+    }
+}
@@ -21,6 +21,16 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
             FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
         );
 
+        CREATE TABLE IF NOT EXISTS transcript_segments (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            transcription_id TEXT NOT NULL,
+            start_seconds REAL NOT NULL,
+            end_seconds REAL NOT NULL,
+            text TEXT NOT NULL,
+            redacted INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (transcription_id) REFERENCES transcriptions(id) ON DELETE CASCADE
+        );
+
         CREATE TABLE IF NOT EXISTS reports (
             id TEXT PRIMARY KEY,
             meeting_id TEXT NOT NULL,
@@ -34,14 +44,95 @@ pub fn run_migrations(conn: &Connection) -> Result<()> {
             FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
         );
 
+        CREATE TABLE IF NOT EXISTS meeting_events (
+            id INTEGER PRIMARY KEY AUTOINCREMENT,
+            meeting_id TEXT NOT NULL,
+            kind TEXT NOT NULL,
+            offset_seconds REAL,
+            details TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS silence_maps (
+            meeting_id TEXT PRIMARY KEY,
+            total_seconds REAL NOT NULL,
+            speech_segments TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS quality_reports (
+            meeting_id TEXT PRIMARY KEY,
+            report TEXT NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS waveforms (
+            meeting_id TEXT PRIMARY KEY,
+            sample_rate INTEGER NOT NULL,
+            samples_per_peak INTEGER NOT NULL,
+            total_samples INTEGER NOT NULL,
+            peaks BLOB NOT NULL,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS audio_segments (
+            meeting_id TEXT NOT NULL,
+            segment_index INTEGER NOT NULL,
+            start_seconds REAL NOT NULL,
+            duration_seconds REAL NOT NULL,
+            PRIMARY KEY (meeting_id, segment_index),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
+        CREATE TABLE IF NOT EXISTS scheduled_recordings (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            device_ids TEXT NOT NULL,
+            start_at TEXT NOT NULL,
+            end_at TEXT,
+            max_duration_minutes INTEGER,
+            silence_stop_minutes INTEGER,
+            status TEXT NOT NULL DEFAULT 'pending',
+            meeting_id TEXT,
+            started_at TEXT,
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE SET NULL
+        );
+
+        CREATE TABLE IF NOT EXISTS transcription_jobs (
+            id TEXT PRIMARY KEY,
+            meeting_id TEXT NOT NULL,
+            model_size TEXT NOT NULL,
+            language TEXT,
+            status TEXT NOT NULL DEFAULT 'pending',
+            position INTEGER NOT NULL DEFAULT 0,
+            attempts INTEGER NOT NULL DEFAULT 0,
+            next_attempt_at TEXT,
+            error TEXT,
+            created_at TEXT NOT NULL DEFAULT (datetime('now')),
+            started_at TEXT,
+            finished_at TEXT,
+            FOREIGN KEY (meeting_id) REFERENCES meetings(id) ON DELETE CASCADE
+        );
+
         CREATE TABLE IF NOT EXISTS settings (
             key TEXT PRIMARY KEY,
             value TEXT NOT NULL
         );
 
         CREATE INDEX IF NOT EXISTS idx_transcriptions_meeting_id ON transcriptions(meeting_id);
+        CREATE INDEX IF NOT EXISTS idx_transcript_segments_transcription_id ON transcript_segments(transcription_id);
         CREATE INDEX IF NOT EXISTS idx_reports_meeting_id ON reports(meeting_id);
         CREATE INDEX IF NOT EXISTS idx_meetings_created_at ON meetings(created_at);
+        CREATE INDEX IF NOT EXISTS idx_meeting_events_meeting_id ON meeting_events(meeting_id);
+        CREATE INDEX IF NOT EXISTS idx_scheduled_recordings_start_at ON scheduled_recordings(start_at);
+        CREATE INDEX IF NOT EXISTS idx_transcription_jobs_meeting_id ON transcription_jobs(meeting_id);
+        CREATE INDEX IF NOT EXISTS idx_transcription_jobs_status ON transcription_jobs(status, position);
         ",
     )?;
 
@@ -2,13 +2,15 @@ mod audio;
 mod commands;
 mod database;
 mod llm;
+mod queue;
+mod scheduler;
 mod transcription;
 
-use audio::AudioRecorder;
+use audio::{spawn_device_watcher, AudioRecorder};
 use database::Database;
 use std::path::PathBuf;
-use std::sync::Mutex;
-use tauri::Manager;
+use std::sync::{Arc, Mutex};
+use tauri::{Emitter, Manager};
 
 pub struct AppState {
     pub db: Database,
@@ -43,24 +45,85 @@ pub fn run() {
             let db =
                 Database::new(app_data_dir.clone()).expect("Impossibile inizializzare il database");
 
+            // Repair recordings left unfinalized by a crash
+            if let Ok(conn) = db.conn.lock() {
+                commands::audio::recover_interrupted_recordings(
+                    &conn,
+                    &app_data_dir.join("recordings"),
+                );
+                // Transcriptions cut short by the last exit resume from the queue
+                if let Err(e) = commands::transcription_jobs::requeue_running_jobs(&conn) {
+                    eprintln!("Errore ripristino job di trascrizione: {}", e);
+                }
+            }
+
+            // Locate the transcription sidecar: settings first, then the
+            // environment, the app bundle and the source tree
+            transcription::set_sidecar_resource_dir(app.path().resource_dir().ok());
+            if let Ok(conn) = db.conn.lock() {
+                let settings = commands::settings::load_app_settings(&conn);
+                transcription::set_sidecar_paths(
+                    settings.sidecar_python_path,
+                    settings.sidecar_script_path,
+                );
+            }
+            if let Err(e) = transcription::locate_sidecar() {
+                eprintln!("{}", e);
+            }
+
+            // Initialize recorder, forwarding its events to the frontend
+            let mut recorder = AudioRecorder::new();
+            let handle = app.handle().clone();
+            recorder.set_event_handler(Arc::new(move |event| {
+                commands::audio::handle_recorder_event(&handle, event)
+            }));
+
             // Initialize app state
             let state = AppState {
                 db,
-                recorder: Mutex::new(AudioRecorder::new()),
+                recorder: Mutex::new(recorder),
                 app_data_dir,
                 current_meeting_id: Mutex::new(None),
             };
 
             app.manage(state);
 
+            // Start and stop scheduled recordings
+            scheduler::spawn_scheduler(app.handle().clone());
+
+            // Run queued transcriptions in the background
+            queue::spawn_transcription_worker(app.handle().clone());
+
+            // Notify the frontend when audio devices are plugged or unplugged
+            let handle = app.handle().clone();
+            spawn_device_watcher(move |change| {
+                let _ = handle.emit("audio-device-changed", change);
+            });
+
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             // Audio commands
             commands::audio::get_audio_devices,
+            commands::audio::get_device_capabilities,
+            commands::audio::set_preferred_audio_device,
+            commands::audio::arm_recording,
+            commands::audio::disarm_recording,
+            commands::audio::is_recording_armed,
             commands::audio::start_recording,
             commands::audio::stop_recording,
             commands::audio::is_recording,
+            commands::audio::pause_recording,
+            commands::audio::resume_recording,
+            commands::audio::is_recording_paused,
+            commands::audio::import_media,
+            commands::audio::select_media_file,
+            commands::audio::archive_recordings,
+            commands::audio::enhance_meeting_audio,
+            commands::audio::analyze_meeting_quality,
+            commands::audio::get_waveform,
+            commands::audio::export_meeting_clip,
+            commands::audio::redact_meeting_audio,
             // Meeting commands
             commands::meetings::create_meeting,
             commands::meetings::update_meeting,
@@ -68,11 +131,21 @@ pub fn run() {
             commands::meetings::get_meeting,
             commands::meetings::delete_meeting,
             commands::meetings::save_transcription,
+            // Scheduled recording commands
+            commands::schedules::create_scheduled_recording,
+            commands::schedules::get_scheduled_recordings,
+            commands::schedules::cancel_scheduled_recording,
             // Transcription commands
             commands::transcription::transcribe_meeting,
             commands::transcription::get_available_models,
             commands::transcription::start_streaming_transcription_command,
             commands::transcription::stop_streaming_transcription_command,
+            commands::transcription::check_transcription_sidecar,
+            // Transcription queue commands
+            commands::transcription_jobs::get_transcription_jobs,
+            commands::transcription_jobs::reorder_transcription_jobs,
+            commands::transcription_jobs::retry_transcription_job,
+            commands::transcription_jobs::cancel_transcription,
             // LLM commands
             commands::llm::generate_meeting_report,
             commands::llm::get_llm_config,
@@ -83,6 +156,12 @@ pub fn run() {
             commands::settings::save_app_settings,
             commands::settings::get_app_data_dir,
         ])
-        .run(tauri::generate_context!())
-        .expect("Errore durante l'esecuzione dell'applicazione Tauri");
+        .build(tauri::generate_context!())
+        .expect("Errore durante l'esecuzione dell'applicazione Tauri")
+        .run(|app, event| {
+            // Il sidecar non deve sopravvivere all'app
+            if let tauri::RunEvent::Exit = event {
+                queue::shutdown_transcriptions(app);
+            }
+        });
 }
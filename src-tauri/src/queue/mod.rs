@@ -0,0 +1,304 @@
+//! Coda delle trascrizioni: un worker in background avvia i job in ordine,
+//! ritenta quelli interrotti da un crash del sidecar e riprende la coda
+//! all'avvio successivo dell'app.
+
+use crate::commands::meetings::insert_transcription;
+use crate::commands::transcription::transcribe_recording;
+use crate::commands::transcription_jobs::{
+    finish_job, mark_job_running, next_due_job, requeue_running_jobs, retry_job_later,
+    TranscriptionJob,
+};
+use crate::transcription::{
+    cancel_transcription_job, kill_sidecar, kill_sidecar_unless_streaming, TranscriptionError,
+    TranscriptionResult,
+};
+use crate::AppState;
+use chrono::Utc;
+use once_cell::sync::Lazy;
+use rusqlite::Connection;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+/// Intervallo di controllo dei job in attesa di un nuovo tentativo
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Job eseguiti insieme. Uno alla volta: Whisper occupa già tutta la CPU, e
+/// il sidecar trascrive con lo stesso modello un file alla volta
+const MAX_PARALLEL_JOBS: usize = 1;
+
+/// Tentativi per job quando il sidecar termina o non parte; l'attesa fra un
+/// tentativo e l'altro parte da `RETRY_DELAY_SECONDS` e raddoppia
+const MAX_ATTEMPTS: u32 = 4;
+const RETRY_DELAY_SECONDS: i64 = 15;
+
+/// Quanto attendere che il sidecar interrompa una trascrizione annullata
+/// prima di terminarlo: l'annullamento è controllato solo alla fine di ogni
+/// blocco di audio, che con i modelli grandi può richiedere parecchio
+const CANCEL_GRACE: Duration = Duration::from_secs(5);
+
+/// Job in esecuzione, con la richiesta di annullamento
+static RUNNING_JOBS: Lazy<Mutex<HashMap<String, Arc<AtomicBool>>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+static WAKE: Lazy<Notify> = Lazy::new(Notify::new);
+
+// All'uscita i job in corso restano in coda invece di essere chiusi
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// Payload dell'evento "transcription-finished"; `status` è `completed`,
+/// `failed` o `cancelled`
+#[derive(Clone, Serialize)]
+struct TranscriptionJobFinished<'a> {
+    job_id: &'a str,
+    meeting_id: &'a str,
+    status: &'a str,
+    error: Option<&'a str>,
+}
+
+/// Avvia il worker della coda. Il frontend riceve
+/// `transcription-jobs-changed` a ogni cambiamento della coda,
+/// `transcription-progress` durante i job e `transcription-finished` alla
+/// loro conclusione.
+pub fn spawn_transcription_worker(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = start_due_jobs(&app) {
+                eprintln!("Errore avvio job di trascrizione: {}", e);
+            }
+            tokio::select! {
+                _ = WAKE.notified() => {}
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+            }
+        }
+    });
+}
+
+/// Fa controllare subito la coda al worker
+pub fn wake_transcription_worker() {
+    WAKE.notify_one();
+}
+
+pub fn notify_jobs_changed(app: &AppHandle) {
+    let _ = app.emit("transcription-jobs-changed", ());
+}
+
+pub fn emit_job_finished(
+    app: &AppHandle,
+    job_id: &str,
+    meeting_id: &str,
+    status: &str,
+    error: Option<&str>,
+) {
+    let _ = app.emit(
+        "transcription-finished",
+        TranscriptionJobFinished {
+            job_id,
+            meeting_id,
+            status,
+            error,
+        },
+    );
+}
+
+/// Annulla un job in corso. Il sidecar interrompe la trascrizione alla fine
+/// del blocco di audio in corso, o viene terminato se non lo fa entro
+/// `CANCEL_GRACE`. Durante una trascrizione in tempo reale il sidecar non
+/// viene terminato: si attende che il job si fermi o che la sessione finisca.
+pub async fn cancel_running_job(job_id: &str) -> Result<(), String> {
+    let Some(cancelled) = RUNNING_JOBS.lock().unwrap().get(job_id).cloned() else {
+        return Err("Trascrizione non in corso".to_string());
+    };
+    cancelled.store(true, Ordering::SeqCst);
+
+    if let Err(e) = cancel_transcription_job(job_id).await {
+        eprintln!("Errore annullamento trascrizione: {}", e);
+    }
+
+    let job_id = job_id.to_string();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CANCEL_GRACE).await;
+            let still_running = RUNNING_JOBS.lock().unwrap().contains_key(&job_id);
+            if !still_running || kill_sidecar_unless_streaming().await {
+                return;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Alla chiusura dell'app: rimette in coda i job in corso, che
+/// ripartiranno al prossimo avvio, e termina il sidecar
+pub fn shutdown_transcriptions(app: &AppHandle) {
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    RUNNING_JOBS.lock().unwrap().clear();
+    if let Ok(conn) = app.state::<AppState>().db.conn.lock() {
+        if let Err(e) = requeue_running_jobs(&conn) {
+            eprintln!("Errore aggiornamento job di trascrizione: {}", e);
+        }
+    }
+
+    tauri::async_runtime::block_on(kill_sidecar());
+}
+
+fn start_due_jobs(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    loop {
+        if SHUTTING_DOWN.load(Ordering::SeqCst)
+            || RUNNING_JOBS.lock().unwrap().len() >= MAX_PARALLEL_JOBS
+        {
+            return Ok(());
+        }
+
+        let job = {
+            let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+            let Some(mut job) = next_due_job(&conn, Utc::now()).map_err(|e| e.to_string())? else {
+                return Ok(());
+            };
+            mark_job_running(&conn, &job.id).map_err(|e| e.to_string())?;
+            job.attempts += 1;
+            job.status = "running".to_string();
+            job
+        };
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        RUNNING_JOBS
+            .lock()
+            .unwrap()
+            .insert(job.id.clone(), cancelled.clone());
+        notify_jobs_changed(app);
+        tauri::async_runtime::spawn(run_job(app.clone(), job, cancelled));
+    }
+}
+
+async fn run_job(app: AppHandle, job: TranscriptionJob, cancelled: Arc<AtomicBool>) {
+    let result = transcribe_recording(&app, &job, &cancelled).await;
+    RUNNING_JOBS.lock().unwrap().remove(&job.id);
+    if SHUTTING_DOWN.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let outcome = match app.state::<AppState>().db.conn.lock() {
+        Ok(conn) => record_outcome(&conn, &job, result, cancelled.load(Ordering::SeqCst))
+            .map_err(|e| e.to_string()),
+        Err(e) => Err(e.to_string()),
+    };
+    match outcome {
+        Ok(Some((status, error))) => {
+            emit_job_finished(&app, &job.id, &job.meeting_id, status, error.as_deref());
+        }
+        // Da ritentare: il frontend continua ad attendere
+        Ok(None) => {}
+        Err(e) => eprintln!("Errore salvataggio trascrizione: {}", e),
+    }
+
+    notify_jobs_changed(&app);
+    wake_transcription_worker();
+}
+
+/// Salva l'esito di un job e restituisce lo stato finale, o `None` se il
+/// job è tornato in coda per un nuovo tentativo
+fn record_outcome(
+    conn: &Connection,
+    job: &TranscriptionJob,
+    result: Result<TranscriptionResult, TranscriptionError>,
+    cancelled: bool,
+) -> rusqlite::Result<Option<(&'static str, Option<String>)>> {
+    // Annullato anche se la trascrizione è finita nel frattempo, o è
+    // fallita perché il sidecar è stato terminato
+    if cancelled || matches!(result, Err(TranscriptionError::Cancelled)) {
+        finish_job(conn, &job.id, "cancelled", None)?;
+        return Ok(Some(("cancelled", None)));
+    }
+
+    let error = match result {
+        Ok(result) => match insert_transcription(
+            conn,
+            &job.meeting_id,
+            &result.text,
+            result.language.as_deref(),
+            &result.segments,
+        ) {
+            Ok(_) => {
+                finish_job(conn, &job.id, "completed", None)?;
+                return Ok(Some(("completed", None)));
+            }
+            Err(e) => e.to_string(),
+        },
+        // Il sidecar è terminato o non è partito: può essere un problema
+        // passeggero (memoria esaurita, crash di Python)
+        Err(
+            e @ (TranscriptionError::CommunicationError(_)
+            | TranscriptionError::SidecarStartError(_)),
+        ) if job.attempts < MAX_ATTEMPTS => {
+            let delay = RETRY_DELAY_SECONDS << (job.attempts.max(1) - 1);
+            let at = Utc::now() + chrono::Duration::seconds(delay);
+            retry_job_later(conn, &job.id, at, &e.to_string())?;
+            return Ok(None);
+        }
+        Err(e) => e.to_string(),
+    };
+
+    finish_job(conn, &job.id, "failed", Some(&error))?;
+    Ok(Some(("failed", Some(error))))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::transcription_jobs::enqueue_job;
+    use crate::database::schema::run_migrations;
+    use chrono::DateTime;
+
+    #[test]
+    fn sidecar_crashes_are_retried_with_doubling_delay() {
+        let conn = Connection::open_in_memory().unwrap();
+        run_migrations(&conn).unwrap();
+        conn.execute(
+            "INSERT INTO meetings (id, title) VALUES ('m', 'Riunione')",
+            [],
+        )
+        .unwrap();
+        let id = enqueue_job(&conn, "m", "base", None).unwrap();
+
+        let mut delays = Vec::new();
+        for attempt in 1..=MAX_ATTEMPTS {
+            // I job in attesa di un nuovo tentativo partono più avanti
+            let later = Utc::now() + chrono::Duration::hours(1);
+            let mut job = next_due_job(&conn, later).unwrap().unwrap();
+            mark_job_running(&conn, &job.id).unwrap();
+            job.attempts += 1;
+            assert_eq!(job.attempts, attempt);
+
+            let failed_at = Utc::now();
+            let crash = Err(TranscriptionError::CommunicationError("crash".to_string()));
+            let outcome = record_outcome(&conn, &job, crash, false).unwrap();
+            let (status, next_attempt_at): (String, Option<String>) = conn
+                .query_row(
+                    "SELECT status, next_attempt_at FROM transcription_jobs WHERE id = ?1",
+                    [&id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .unwrap();
+
+            if attempt < MAX_ATTEMPTS {
+                assert!(outcome.is_none());
+                assert_eq!(status, "pending");
+                let at = DateTime::parse_from_rfc3339(&next_attempt_at.unwrap()).unwrap();
+                delays.push((at.with_timezone(&Utc) - failed_at).num_seconds());
+            } else {
+                let (status, error) = outcome.unwrap();
+                assert_eq!(status, "failed");
+                assert!(error.unwrap().contains("crash"));
+            }
+        }
+
+        assert_eq!(delays, vec![15, 30, 60]);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::commands::audio::{begin_recording, spawn_auto_stop};
+use crate::commands::schedules::{
+    finish_schedule, load_active_schedules, mark_schedule_running, ScheduledRecording,
+};
+use crate::AppState;
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Intervallo di controllo delle registrazioni programmate
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Ritardo oltre il quale una registrazione senza orario di fine non
+/// viene più avviata (per esempio se l'app era chiusa all'ora prevista)
+const LATE_START_MINUTES: i64 = 10;
+
+/// Avvia il ciclo che fa partire e fermare le registrazioni programmate.
+/// All'avvio di una registrazione il frontend riceve
+/// `scheduled-recording-started` con la riunione creata; lo stop arriva
+/// come `recording-auto-stopped`, lo stesso evento dello stop per silenzio.
+pub fn spawn_scheduler(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            if let Err(e) = check_schedules(&app) {
+                eprintln!("Errore controllo registrazioni programmate: {}", e);
+            }
+        }
+    });
+}
+
+fn check_schedules(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<AppState>();
+    let schedules = {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        load_active_schedules(&conn).map_err(|e| e.to_string())?
+    };
+
+    let now = Utc::now();
+    for schedule in schedules {
+        match schedule.status.as_str() {
+            "running" => check_running(app, &state, &schedule, now)?,
+            "pending" if schedule.start_at <= now => start_schedule(app, &state, &schedule, now)?,
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn start_schedule(
+    app: &AppHandle,
+    state: &AppState,
+    schedule: &ScheduledRecording,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let too_late = match schedule.stop_at() {
+        Some(stop_at) => stop_at <= now,
+        None => now - schedule.start_at > chrono::Duration::minutes(LATE_START_MINUTES),
+    };
+    if too_late {
+        let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+        return finish_schedule(&conn, &schedule.id, "missed", None).map_err(|e| e.to_string());
+    }
+
+    // Il silenzio segue solo la regola della programmazione, non
+    // l'impostazione globale
+    let started = begin_recording(
+        state,
+        Some(schedule.device_ids.clone()),
+        None,
+        Some(schedule.title.clone()),
+        None,
+        Some(schedule.silence_stop_minutes.unwrap_or(0)),
+    );
+
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    match started {
+        Ok(meeting) => {
+            mark_schedule_running(&conn, &schedule.id, &meeting.id, now)
+                .map_err(|e| e.to_string())?;
+            let _ = app.emit("scheduled-recording-started", &meeting);
+        }
+        Err(e) => {
+            finish_schedule(&conn, &schedule.id, "failed", Some(&e)).map_err(|e| e.to_string())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn check_running(
+    app: &AppHandle,
+    state: &AppState,
+    schedule: &ScheduledRecording,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    // Fermata a mano, per silenzio o da un crash: non c'è altro da fare
+    let is_current = schedule.meeting_id.is_some()
+        && *state.current_meeting_id.lock().map_err(|e| e.to_string())? == schedule.meeting_id;
+    let expired = schedule.stop_at().is_some_and(|stop_at| stop_at <= now);
+
+    if is_current && !expired {
+        return Ok(());
+    }
+
+    // Lo stato cambia prima dello stop, così il prossimo controllo non
+    // ferma di nuovo la stessa registrazione
+    let conn = state.db.conn.lock().map_err(|e| e.to_string())?;
+    finish_schedule(&conn, &schedule.id, "completed", None).map_err(|e| e.to_string())?;
+    if is_current {
+        spawn_auto_stop(app.clone());
+    }
+
+    Ok(())
+}
@@ -1,6 +1,11 @@
+mod resolver;
+mod rpc;
 pub mod sidecar;
 
+pub use resolver::{locate_sidecar, set_sidecar_paths, set_sidecar_resource_dir, SidecarLaunch};
 pub use sidecar::{
+    cancel_transcription_job, kill_sidecar, kill_sidecar_unless_streaming,
+    pause_streaming_transcription, resume_streaming_transcription, send_streaming_utterance,
     start_streaming_transcription, stop_streaming_transcription, transcribe_audio,
-    TranscriptionResult,
+    TranscriptionError, TranscriptionProgress, TranscriptionResult, TranscriptionSegment,
 };
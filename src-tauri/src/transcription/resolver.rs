@@ -0,0 +1,301 @@
+//! Ricerca dell'interprete Python e dello script del sidecar.
+//!
+//! L'ordine è: percorsi indicati nelle impostazioni, variabili d'ambiente,
+//! risorse dell'app installata (o un sidecar già compilato distribuito come
+//! `externalBin`), e infine l'albero dei sorgenti in sviluppo.
+
+use super::sidecar::TranscriptionError;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Interprete Python da usare al posto di quello trovato automaticamente
+pub const PYTHON_ENV: &str = "MEET_TRANSCRIBER_PYTHON";
+/// Script del sidecar da usare al posto di quello trovato automaticamente
+pub const SCRIPT_ENV: &str = "MEET_TRANSCRIBER_SIDECAR";
+
+/// Nome del sidecar compilato (per esempio con PyInstaller) che Tauri
+/// copia accanto all'eseguibile dell'app
+const EXTERNAL_BIN: &str = "transcription-sidecar";
+
+/// Percorsi da cui cercare il sidecar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SidecarConfig {
+    /// Interprete indicato nelle impostazioni
+    pub python_path: Option<PathBuf>,
+    /// Script indicato nelle impostazioni
+    pub script_path: Option<PathBuf>,
+    /// Cartella delle risorse dell'app installata
+    pub resource_dir: Option<PathBuf>,
+}
+
+/// Come avviare il sidecar, e dove è stato trovato
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SidecarLaunch {
+    pub program: PathBuf,
+    /// Script da passare all'interprete; assente per il sidecar compilato
+    pub script: Option<PathBuf>,
+    pub source: String,
+}
+
+// La configurazione cambia con le impostazioni: il numero di versione fa
+// riavviare il processo alla richiesta successiva
+static CONFIG: Lazy<Mutex<(u64, SidecarConfig)>> =
+    Lazy::new(|| Mutex::new((0, SidecarConfig::default())));
+
+fn update_config(update: impl FnOnce(&mut SidecarConfig)) {
+    let mut guard = CONFIG.lock().unwrap();
+    let mut config = guard.1.clone();
+    update(&mut config);
+    if config != guard.1 {
+        *guard = (guard.0 + 1, config);
+    }
+}
+
+/// Imposta la cartella delle risorse dell'app, all'avvio
+pub fn set_sidecar_resource_dir(resource_dir: Option<PathBuf>) {
+    update_config(|config| config.resource_dir = resource_dir);
+}
+
+/// Imposta i percorsi scelti dall'utente (vuoti per la ricerca automatica)
+pub fn set_sidecar_paths(python_path: Option<String>, script_path: Option<String>) {
+    let path = |value: Option<String>| {
+        value
+            .filter(|v| !v.trim().is_empty())
+            .map(|v| PathBuf::from(v.trim()))
+    };
+    update_config(|config| {
+        config.python_path = path(python_path);
+        config.script_path = path(script_path);
+    });
+}
+
+/// Configurazione attuale con la sua versione
+pub(crate) fn sidecar_config() -> (u64, SidecarConfig) {
+    CONFIG.lock().unwrap().clone()
+}
+
+/// Cerca il sidecar con la configurazione attuale
+pub fn locate_sidecar() -> Result<SidecarLaunch, TranscriptionError> {
+    resolve_sidecar(&sidecar_config().1)
+}
+
+/// Percorso dell'interprete all'interno di un virtualenv
+fn venv_python(python_dir: &Path) -> PathBuf {
+    if cfg!(windows) {
+        python_dir.join("venv").join("Scripts").join("python.exe")
+    } else {
+        python_dir.join("venv").join("bin").join("python3")
+    }
+}
+
+fn sidecar_script(python_dir: &Path) -> PathBuf {
+    python_dir.join("src").join("main.py")
+}
+
+/// Cartelle `python/` dell'albero dei sorgenti: quella accanto al crate
+/// e quella della directory corrente
+fn dev_python_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("..")
+        .join("python")];
+    if let Ok(current_dir) = std::env::current_dir() {
+        dirs.push(current_dir.join("python"));
+        dirs.push(current_dir.join("..").join("python"));
+    }
+    dirs
+}
+
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = path.metadata() else {
+        return false;
+    };
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.is_file() && metadata.permissions().mode() & 0o111 != 0
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.is_file()
+    }
+}
+
+/// Percorsi provati, con il motivo per cui sono stati scartati
+#[derive(Default)]
+struct Attempts(Vec<String>);
+
+impl Attempts {
+    /// L'interprete, se esiste ed è eseguibile
+    fn python(&mut self, source: &str, path: &Path) -> bool {
+        if is_executable(path) {
+            return true;
+        }
+        let reason = if path.exists() {
+            "non eseguibile"
+        } else {
+            "non trovato"
+        };
+        self.0
+            .push(format!("{} ({}, {})", path.display(), source, reason));
+        false
+    }
+
+    fn script(&mut self, source: &str, path: &Path) -> bool {
+        if path.is_file() {
+            return true;
+        }
+        self.0.push(format!(
+            "{} ({}, script non trovato)",
+            path.display(),
+            source
+        ));
+        false
+    }
+
+    fn into_error(self) -> TranscriptionError {
+        TranscriptionError::SidecarNotFound(format!("percorsi provati:\n- {}", self.0.join("\n- ")))
+    }
+}
+
+/// Cerca il sidecar e verifica che interprete e script esistano. I
+/// percorsi indicati esplicitamente (impostazioni o variabili d'ambiente)
+/// devono essere validi: non si ripiega su quelli trovati da soli.
+pub fn resolve_sidecar(config: &SidecarConfig) -> Result<SidecarLaunch, TranscriptionError> {
+    let mut attempts = Attempts::default();
+
+    let env_path = |name: &str| {
+        std::env::var_os(name)
+            .filter(|v| !v.is_empty())
+            .map(PathBuf::from)
+    };
+    let explicit_script = config
+        .script_path
+        .clone()
+        .map(|path| (path, "impostazioni"))
+        .or_else(|| env_path(SCRIPT_ENV).map(|path| (path, SCRIPT_ENV)));
+
+    let mut python_dirs: Vec<(PathBuf, &str)> = Vec::new();
+    if let Some(resource_dir) = &config.resource_dir {
+        python_dirs.push((resource_dir.join("python"), "risorse dell'app"));
+    }
+    python_dirs.extend(dev_python_dirs().into_iter().map(|dir| (dir, "sorgenti")));
+
+    // Lo script esplicito vale per qualsiasi interprete; altrimenti si usa
+    // il primo trovato fra risorse e sorgenti
+    let find_script = |attempts: &mut Attempts| -> Option<PathBuf> {
+        if let Some((path, source)) = &explicit_script {
+            return attempts.script(source, path).then(|| path.clone());
+        }
+        python_dirs.iter().find_map(|(dir, source)| {
+            let path = sidecar_script(dir);
+            attempts.script(source, &path).then_some(path)
+        })
+    };
+
+    let explicit_python = config
+        .python_path
+        .clone()
+        .map(|path| (path, "impostazioni"))
+        .or_else(|| env_path(PYTHON_ENV).map(|path| (path, PYTHON_ENV)));
+
+    if let Some((python, source)) = explicit_python {
+        let valid = attempts.python(source, &python);
+        let script = find_script(&mut attempts);
+        return match (valid, script) {
+            (true, Some(script)) => Ok(SidecarLaunch {
+                program: python,
+                script: Some(script),
+                source: source.to_string(),
+            }),
+            _ => Err(attempts.into_error()),
+        };
+    }
+
+    // Sidecar compilato distribuito con l'app: non serve un interprete
+    if explicit_script.is_none() {
+        if let Some(exe_dir) = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf))
+        {
+            let name = format!("{}{}", EXTERNAL_BIN, std::env::consts::EXE_SUFFIX);
+            let program = exe_dir.join(name);
+            if attempts.python("externalBin", &program) {
+                return Ok(SidecarLaunch {
+                    program,
+                    script: None,
+                    source: "externalBin".to_string(),
+                });
+            }
+        }
+    }
+
+    for (dir, source) in &python_dirs {
+        let python = venv_python(dir);
+        if !attempts.python(source, &python) {
+            continue;
+        }
+        let script = match &explicit_script {
+            Some((path, script_source)) => {
+                attempts.script(script_source, path).then(|| path.clone())
+            }
+            None => {
+                let path = sidecar_script(dir);
+                attempts.script(source, &path).then_some(path)
+            }
+        };
+        if let Some(script) = script {
+            return Ok(SidecarLaunch {
+                program: python,
+                script: Some(script),
+                source: source.to_string(),
+            });
+        }
+    }
+
+    Err(attempts.into_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_explicit_python_does_not_fall_back() {
+        // Risorse dell'app con un virtualenv e uno script validi
+        let resource_dir =
+            std::env::temp_dir().join(format!("resolver-test-{}", std::process::id()));
+        let python_dir = resource_dir.join("python");
+        let python = venv_python(&python_dir);
+        let script = sidecar_script(&python_dir);
+        std::fs::create_dir_all(python.parent().unwrap()).unwrap();
+        std::fs::create_dir_all(script.parent().unwrap()).unwrap();
+        std::fs::write(&python, "").unwrap();
+        std::fs::write(&script, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&python, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let mut config = SidecarConfig {
+            python_path: None,
+            script_path: None,
+            resource_dir: Some(resource_dir.clone()),
+        };
+        let found = resolve_sidecar(&config);
+
+        config.python_path = Some(resource_dir.join("missing-python"));
+        let result = resolve_sidecar(&config);
+        std::fs::remove_dir_all(&resource_dir).ok();
+
+        assert_eq!(found.unwrap().program, python);
+        match result {
+            Err(TranscriptionError::SidecarNotFound(message)) => {
+                assert!(message.contains("missing-python"), "{}", message);
+            }
+            other => panic!("interprete non valido accettato: {:?}", other),
+        }
+    }
+}
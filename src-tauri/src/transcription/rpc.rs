@@ -0,0 +1,367 @@
+//! Client del sidecar Python: un unico processo, avviato alla prima
+//! richiesta e riavviato se termina, che tiene in memoria i modelli fra una
+//! trascrizione e l'altra.
+//!
+//! Il protocollo è JSON-RPC 2.0 su righe (un messaggio JSON per riga):
+//! - richiesta: `{"jsonrpc":"2.0","id":7,"method":"transcribe","params":{..}}`
+//! - risposta: `{"jsonrpc":"2.0","id":7,"result":{..}}` oppure
+//!   `{"jsonrpc":"2.0","id":7,"error":{"code":-32000,"message":".."}}`
+//! - notifica del sidecar: `{"jsonrpc":"2.0","method":"progress","params":{"id":7,..}}`,
+//!   dove `params.id` è la richiesta a cui si riferisce.
+//!
+//! La prima richiesta di ogni processo è `initialize`, che verifica che il
+//! sidecar parli la stessa versione del protocollo.
+
+use super::resolver::{resolve_sidecar, sidecar_config};
+use super::sidecar::TranscriptionError;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+use tokio::sync::{mpsc, oneshot};
+
+/// Versione del protocollo, da aggiornare insieme a `python/src/main.py`
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Codice di errore di una richiesta annullata con `cancel`
+const REQUEST_CANCELLED: i64 = -32800;
+
+#[derive(Serialize)]
+struct RpcRequest<'a> {
+    jsonrpc: &'static str,
+    id: u64,
+    method: &'a str,
+    params: Value,
+}
+
+/// Qualsiasi messaggio del sidecar: risposta se ha `id`, notifica se ha
+/// `method`
+#[derive(Deserialize)]
+struct RpcMessage {
+    id: Option<u64>,
+    method: Option<String>,
+    #[serde(default)]
+    params: Value,
+    result: Option<Value>,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize)]
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+/// Notifica del sidecar legata a una richiesta (avanzamento, segmenti
+/// della trascrizione streaming, ...)
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub method: String,
+    pub params: Value,
+}
+
+type Responder = oneshot::Sender<Result<Value, TranscriptionError>>;
+
+/// Destinatari dei messaggi in arrivo, per id di richiesta
+#[derive(Default)]
+struct Routes {
+    responses: HashMap<u64, Responder>,
+    notifications: HashMap<u64, mpsc::UnboundedSender<Notification>>,
+}
+
+struct SidecarProcess {
+    child: Child,
+    stdin: ChildStdin,
+    routes: Arc<Mutex<Routes>>,
+    alive: Arc<AtomicBool>,
+    /// Versione della configurazione con cui è stato avviato
+    generation: u64,
+}
+
+impl SidecarProcess {
+    async fn write(&mut self, id: u64, method: &str, params: Value) -> std::io::Result<()> {
+        let request = RpcRequest {
+            jsonrpc: "2.0",
+            id,
+            method,
+            params,
+        };
+        let mut line = serde_json::to_vec(&request)?;
+        line.push(b'\n');
+
+        self.stdin.write_all(&line).await?;
+        self.stdin.flush().await
+    }
+}
+
+/// Richiesta inviata, in attesa di risposta
+struct Sent {
+    id: u64,
+    response: oneshot::Receiver<Result<Value, TranscriptionError>>,
+    routes: Arc<Mutex<Routes>>,
+}
+
+pub struct SidecarClient {
+    process: tokio::sync::Mutex<Option<SidecarProcess>>,
+    next_id: AtomicU64,
+}
+
+static SIDECAR: Lazy<SidecarClient> = Lazy::new(|| SidecarClient {
+    process: tokio::sync::Mutex::new(None),
+    next_id: AtomicU64::new(1),
+});
+
+/// Client condiviso da trascrizioni batch e streaming
+pub fn sidecar() -> &'static SidecarClient {
+    &SIDECAR
+}
+
+impl SidecarClient {
+    /// Esegue una richiesta e ne attende il risultato. Le notifiche legate
+    /// alla richiesta, se c'è un destinatario, arrivano fino alla risposta.
+    pub async fn call(
+        &self,
+        method: &str,
+        params: Value,
+        notifications: Option<mpsc::UnboundedSender<Notification>>,
+    ) -> Result<Value, TranscriptionError> {
+        let sent = self.send(method, params, notifications).await?;
+        let result = wait_response(sent.response).await;
+        remove_route(&sent.routes, sent.id);
+        result
+    }
+
+    /// Come `call`, ma le notifiche continuano ad arrivare anche dopo la
+    /// risposta, finché non si chiama `close_stream` con l'id restituito
+    pub async fn open_stream(
+        &self,
+        method: &str,
+        params: Value,
+        notifications: mpsc::UnboundedSender<Notification>,
+    ) -> Result<(u64, Value), TranscriptionError> {
+        let sent = self.send(method, params, Some(notifications)).await?;
+        match wait_response(sent.response).await {
+            Ok(result) => Ok((sent.id, result)),
+            Err(e) => {
+                remove_route(&sent.routes, sent.id);
+                Err(e)
+            }
+        }
+    }
+
+    /// Smette di inoltrare le notifiche di uno stream
+    pub async fn close_stream(&self, id: u64) {
+        if let Some(process) = self.process.lock().await.as_ref() {
+            remove_route(&process.routes, id);
+        }
+    }
+
+    /// Se il processo è attivo; serve a non avviarlo solo per una richiesta
+    /// che non avrebbe effetto
+    pub async fn is_running(&self) -> bool {
+        self.process
+            .lock()
+            .await
+            .as_ref()
+            .is_some_and(|p| p.alive.load(Ordering::SeqCst))
+    }
+
+    /// Termina il processo: le richieste in corso falliscono e la prossima
+    /// ne avvia uno nuovo
+    pub async fn kill(&self) {
+        if let Some(mut process) = self.process.lock().await.take() {
+            process.alive.store(false, Ordering::SeqCst);
+            if let Err(e) = process.child.kill().await {
+                eprintln!("Errore chiusura sidecar: {}", e);
+            }
+        }
+    }
+
+    async fn send(
+        &self,
+        method: &str,
+        params: Value,
+        notifications: Option<mpsc::UnboundedSender<Notification>>,
+    ) -> Result<Sent, TranscriptionError> {
+        let mut guard = self.process.lock().await;
+        let generation = sidecar_config().0;
+        if !guard
+            .as_ref()
+            .is_some_and(|p| p.alive.load(Ordering::SeqCst) && p.generation == generation)
+        {
+            *guard = None;
+            *guard = Some(self.spawn().await?);
+        }
+        let process = guard.as_mut().expect("processo appena avviato");
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut routes = process.routes.lock().unwrap();
+            routes.responses.insert(id, tx);
+            if let Some(notifications) = notifications {
+                routes.notifications.insert(id, notifications);
+            }
+        }
+
+        if let Err(e) = process.write(id, method, params).await {
+            // Pipe chiusa: il processo verrà riavviato alla prossima richiesta
+            process.alive.store(false, Ordering::SeqCst);
+            remove_route(&process.routes, id);
+            return Err(TranscriptionError::CommunicationError(e.to_string()));
+        }
+
+        Ok(Sent {
+            id,
+            response: rx,
+            routes: process.routes.clone(),
+        })
+    }
+
+    /// Avvia il processo e verifica la versione del protocollo
+    async fn spawn(&self) -> Result<SidecarProcess, TranscriptionError> {
+        let (generation, config) = sidecar_config();
+        let launch = resolve_sidecar(&config)?;
+
+        let mut child = Command::new(&launch.program)
+            .args(&launch.script)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| {
+                TranscriptionError::SidecarStartError(format!(
+                    "{}: {}",
+                    launch.program.display(),
+                    e
+                ))
+            })?;
+
+        let stdin = child.stdin.take().ok_or_else(|| {
+            TranscriptionError::CommunicationError("Impossibile accedere a stdin".to_string())
+        })?;
+        let stdout = child.stdout.take().ok_or_else(|| {
+            TranscriptionError::CommunicationError("Impossibile accedere a stdout".to_string())
+        })?;
+
+        let routes = Arc::new(Mutex::new(Routes::default()));
+        let alive = Arc::new(AtomicBool::new(true));
+        tokio::spawn(read_messages(stdout, routes.clone(), alive.clone()));
+
+        let mut process = SidecarProcess {
+            child,
+            stdin,
+            routes,
+            alive,
+            generation,
+        };
+
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        process.routes.lock().unwrap().responses.insert(id, tx);
+        process
+            .write(
+                id,
+                "initialize",
+                json!({ "protocol_version": PROTOCOL_VERSION }),
+            )
+            .await
+            .map_err(|e| TranscriptionError::SidecarStartError(e.to_string()))?;
+
+        let result = wait_response(rx).await?;
+        let version = result.get("protocol_version").and_then(Value::as_u64);
+        if version != Some(PROTOCOL_VERSION as u64) {
+            return Err(TranscriptionError::SidecarStartError(format!(
+                "versione del protocollo {:?} non supportata (attesa {})",
+                version, PROTOCOL_VERSION
+            )));
+        }
+
+        Ok(process)
+    }
+}
+
+async fn wait_response(
+    response: oneshot::Receiver<Result<Value, TranscriptionError>>,
+) -> Result<Value, TranscriptionError> {
+    response.await.unwrap_or_else(|_| {
+        Err(TranscriptionError::CommunicationError(
+            "Il sidecar è terminato prima di rispondere".to_string(),
+        ))
+    })
+}
+
+fn remove_route(routes: &Mutex<Routes>, id: u64) {
+    let mut routes = routes.lock().unwrap();
+    routes.responses.remove(&id);
+    routes.notifications.remove(&id);
+}
+
+/// Smista i messaggi del sidecar finché il processo non termina, poi fa
+/// fallire le richieste rimaste senza risposta
+async fn read_messages(stdout: ChildStdout, routes: Arc<Mutex<Routes>>, alive: Arc<AtomicBool>) {
+    let mut lines = BufReader::new(stdout).lines();
+
+    loop {
+        let line = match lines.next_line().await {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(e) => {
+                eprintln!("Errore lettura output sidecar: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let message: RpcMessage = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                eprintln!("Messaggio sidecar non valido ({}): {}", e, line);
+                continue;
+            }
+        };
+
+        let mut guard = routes.lock().unwrap();
+        match (message.id, message.method) {
+            (Some(id), None) => {
+                let result = match message.error {
+                    Some(error) if error.code == REQUEST_CANCELLED => {
+                        Err(TranscriptionError::Cancelled)
+                    }
+                    Some(error) => Err(TranscriptionError::TranscriptionFailed(format!(
+                        "{} (codice {})",
+                        error.message, error.code
+                    ))),
+                    None => Ok(message.result.unwrap_or(Value::Null)),
+                };
+                if let Some(responder) = guard.responses.remove(&id) {
+                    let _ = responder.send(result);
+                }
+            }
+            (None, Some(method)) => {
+                let target = message.params.get("id").and_then(Value::as_u64);
+                if let Some(sender) = target.and_then(|id| guard.notifications.get(&id)) {
+                    let _ = sender.send(Notification {
+                        method,
+                        params: message.params,
+                    });
+                }
+            }
+            _ => eprintln!("Messaggio sidecar non riconosciuto: {}", line),
+        }
+    }
+
+    // Chiudere i canali sblocca chi attende una risposta o una notifica
+    let mut routes = routes.lock().unwrap();
+    routes.responses.clear();
+    routes.notifications.clear();
+    alive.store(false, Ordering::SeqCst);
+}
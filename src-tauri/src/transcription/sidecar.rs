@@ -1,20 +1,26 @@
+use super::rpc::{sidecar, Notification};
+use crate::audio::SpeechSegment;
+use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use std::path::Path;
-use std::process::Stdio;
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tauri::{Emitter, Window};
 use thiserror::Error;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use tokio::process::Command;
+use tokio::sync::mpsc;
 
 #[derive(Error, Debug)]
 pub enum TranscriptionError {
     #[error("Errore avvio sidecar: {0}")]
     SidecarStartError(String),
+    #[error("Sidecar di trascrizione non trovato, {0}")]
+    SidecarNotFound(String),
     #[error("Errore comunicazione: {0}")]
     CommunicationError(String),
     #[error("Errore trascrizione: {0}")]
     TranscriptionFailed(String),
+    #[error("Trascrizione annullata")]
+    Cancelled,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,114 +37,160 @@ pub struct TranscriptionSegment {
     pub text: String,
 }
 
+/// Avanzamento di una trascrizione batch, con i segmenti trascritti
+/// dall'aggiornamento precedente
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranscriptionProgress {
+    pub percent: f64,
+    pub processed_seconds: f64,
+    pub total_seconds: f64,
+    /// Secondi stimati alla fine; assente prima del primo blocco
+    pub eta_seconds: Option<f64>,
+    #[serde(default)]
+    pub segments: Vec<TranscriptionSegment>,
+}
+
+impl TranscriptionProgress {
+    /// Riporta l'avanzamento di un segmento della registrazione, iniziato a
+    /// `offset_seconds`, sull'intera registrazione. La stima del tempo
+    /// restante si basa su `elapsed`, il tempo trascorso dall'inizio.
+    pub fn on_timeline(
+        mut self,
+        offset_seconds: f64,
+        total_seconds: f64,
+        elapsed: Duration,
+    ) -> Self {
+        self.processed_seconds = (offset_seconds + self.processed_seconds).min(total_seconds);
+        self.total_seconds = total_seconds;
+        self.percent = if total_seconds > 0.0 {
+            100.0 * self.processed_seconds / total_seconds
+        } else {
+            100.0
+        };
+        self.eta_seconds = (self.processed_seconds > 0.0).then(|| {
+            elapsed.as_secs_f64() * (total_seconds - self.processed_seconds)
+                / self.processed_seconds
+        });
+        for segment in &mut self.segments {
+            segment.start += offset_seconds;
+            segment.end += offset_seconds;
+        }
+        self
+    }
+}
+
+/// Parametri di `transcribe` e `start_streaming`
 #[derive(Serialize)]
-struct TranscriptionRequest {
-    command: String,
+struct TranscriptionParams {
     audio_path: String,
     model_size: String,
     language: Option<String>,
+    /// Regioni di parlato: se presenti viene trascritto solo il parlato
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speech_segments: Option<Vec<SpeechSegment>>,
+    /// Job a cui appartiene la trascrizione, per poterla annullare
+    #[serde(skip_serializing_if = "Option::is_none")]
+    job_id: Option<String>,
 }
 
-#[derive(Deserialize)]
-struct SidecarResponse {
-    success: bool,
-    error: Option<String>,
-    result: Option<serde_json::Value>,
+/// Enunciato concluso da trascrivere durante lo streaming
+#[derive(Serialize)]
+struct UtteranceParams {
+    start_seconds: f64,
+    end_seconds: f64,
+    /// Segmento della registrazione da cui leggere l'enunciato
+    audio_path: String,
+    /// Inizio del segmento nella registrazione
+    segment_start_seconds: f64,
 }
 
 #[derive(Deserialize)]
 struct StreamingUpdate {
-    #[serde(rename = "type")]
-    update_type: String,
     segments: Vec<TranscriptionSegment>,
 }
 
-// Sidecar process globale per streaming
-use once_cell::sync::Lazy;
-static STREAMING_PROCESS: Lazy<Arc<Mutex<Option<tokio::process::Child>>>> =
-    Lazy::new(|| Arc::new(Mutex::new(None)));
+// Richiesta `start_streaming` della sessione in corso: le sue notifiche
+// portano i segmenti trascritti
+static STREAMING_SESSION: Lazy<tokio::sync::Mutex<Option<u64>>> =
+    Lazy::new(|| tokio::sync::Mutex::new(None));
 
+fn to_params<T: Serialize>(params: &T) -> Result<serde_json::Value, TranscriptionError> {
+    serde_json::to_value(params).map_err(|e| TranscriptionError::CommunicationError(e.to_string()))
+}
+
+/// Trascrive un file; se c'è un destinatario, gli aggiornamenti di
+/// avanzamento arrivano fino alla fine della trascrizione. Con `job_id` la
+/// trascrizione può essere interrotta da `cancel_transcription_job`.
 pub async fn transcribe_audio(
-    sidecar_path: &Path,
     audio_path: &Path,
     model_size: &str,
     language: Option<String>,
+    speech_segments: Option<Vec<SpeechSegment>>,
+    job_id: Option<&str>,
+    progress: Option<mpsc::UnboundedSender<TranscriptionProgress>>,
 ) -> Result<TranscriptionResult, TranscriptionError> {
-    // Usa il Python del venv
-    let current_dir = std::env::current_dir()
-        .map_err(|e| TranscriptionError::SidecarStartError(e.to_string()))?;
-
-    let python_executable = current_dir
-        .join("python")
-        .join("venv")
-        .join("bin")
-        .join("python3");
-
-    let mut child = Command::new(&python_executable)
-        .arg(sidecar_path)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| TranscriptionError::SidecarStartError(e.to_string()))?;
-
-    let stdin = child.stdin.as_mut().ok_or_else(|| {
-        TranscriptionError::CommunicationError("Impossibile accedere a stdin".to_string())
-    })?;
-
-    let request = TranscriptionRequest {
-        command: "transcribe".to_string(),
+    let params = to_params(&TranscriptionParams {
         audio_path: audio_path.to_string_lossy().to_string(),
         model_size: model_size.to_string(),
         language,
-    };
-
-    let request_json = serde_json::to_string(&request)
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
-
-    stdin
-        .write_all(request_json.as_bytes())
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
-    stdin
-        .write_all(b"\n")
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
-    stdin
-        .flush()
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
+        speech_segments,
+        job_id: job_id.map(str::to_string),
+    })?;
 
-    drop(child.stdin.take());
+    let notifications = progress.map(|progress| {
+        let (tx, mut notifications) = mpsc::unbounded_channel::<Notification>();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                if notification.method != "progress" {
+                    continue;
+                }
+                match serde_json::from_value(notification.params) {
+                    Ok(update) => {
+                        let _ = progress.send(update);
+                    }
+                    Err(e) => eprintln!("Avanzamento trascrizione non valido: {}", e),
+                }
+            }
+        });
+        tx
+    });
 
-    let stdout = child.stdout.take().ok_or_else(|| {
-        TranscriptionError::CommunicationError("Impossibile accedere a stdout".to_string())
-    })?;
+    let result = sidecar().call("transcribe", params, notifications).await?;
 
-    let mut reader = BufReader::new(stdout);
-    let mut response_line = String::new();
+    serde_json::from_value(result)
+        .map_err(|e| TranscriptionError::CommunicationError(format!("Parsing result: {}", e)))
+}
 
-    reader
-        .read_line(&mut response_line)
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
+/// Chiede al sidecar di interrompere le trascrizioni del job: si fermano
+/// alla fine del blocco di audio in corso, con l'errore `Cancelled`
+pub async fn cancel_transcription_job(job_id: &str) -> Result<(), TranscriptionError> {
+    if !sidecar().is_running().await {
+        return Ok(());
+    }
+    sidecar()
+        .call("cancel", json!({ "job_id": job_id }), None)
+        .await?;
+    Ok(())
+}
 
-    let response: SidecarResponse = serde_json::from_str(&response_line)
-        .map_err(|e| TranscriptionError::CommunicationError(format!("Parsing risposta: {}", e)))?;
+/// Termina il sidecar, interrompendo subito qualsiasi trascrizione
+pub async fn kill_sidecar() {
+    *STREAMING_SESSION.lock().await = None;
+    sidecar().kill().await;
+}
 
-    if response.success {
-        let result: TranscriptionResult = serde_json::from_value(response.result.unwrap())
-            .map_err(|e| {
-                TranscriptionError::CommunicationError(format!("Parsing result: {}", e))
-            })?;
-        Ok(result)
-    } else {
-        Err(TranscriptionError::TranscriptionFailed(
-            response
-                .error
-                .unwrap_or_else(|| "Errore sconosciuto".to_string()),
-        ))
+/// Termina il sidecar solo se non c'è una trascrizione in tempo reale in
+/// corso, che verrebbe persa insieme al processo. Restituisce se l'ha
+/// terminato.
+pub async fn kill_sidecar_unless_streaming() -> bool {
+    // Il lock resta preso fino alla fine, così nel frattempo non può
+    // partire una nuova sessione
+    let session = STREAMING_SESSION.lock().await;
+    if session.is_some() {
+        return false;
     }
+    sidecar().kill().await;
+    true
 }
 
 pub async fn start_streaming_transcription(
@@ -147,86 +199,36 @@ pub async fn start_streaming_transcription(
     model_size: &str,
     language: Option<String>,
 ) -> Result<(), TranscriptionError> {
-    // Trova il percorso dello script Python e del venv
-    let current_dir = std::env::current_dir()
-        .map_err(|e| TranscriptionError::SidecarStartError(e.to_string()))?;
-
-    let python_script = current_dir.join("python").join("src").join("main.py");
-
-    let python_executable = current_dir
-        .join("python")
-        .join("venv")
-        .join("bin")
-        .join("python3");
-
-    let mut child = Command::new(&python_executable)
-        .arg(&python_script)
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::inherit())
-        .spawn()
-        .map_err(|e| TranscriptionError::SidecarStartError(e.to_string()))?;
-
-    let mut stdin = child.stdin.take().ok_or_else(|| {
-        TranscriptionError::CommunicationError("Impossibile accedere a stdin".to_string())
-    })?;
-
-    let stdout = child.stdout.take().ok_or_else(|| {
-        TranscriptionError::CommunicationError("Impossibile accedere a stdout".to_string())
-    })?;
-
-    // Store process reference PRIMA di usarlo
-    {
-        let mut process_guard = STREAMING_PROCESS.lock().unwrap();
-        *process_guard = Some(child);
+    // Una sola sessione alla volta
+    if let Err(e) = stop_streaming_transcription().await {
+        eprintln!("Errore chiusura sessione di streaming precedente: {}", e);
     }
 
-    // Invia comando start_streaming
-    let request = TranscriptionRequest {
-        command: "start_streaming".to_string(),
+    let params = to_params(&TranscriptionParams {
         audio_path: audio_path.to_string(),
         model_size: model_size.to_string(),
         language,
-    };
-
-    let request_json = serde_json::to_string(&request)
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
+        speech_segments: None,
+        job_id: None,
+    })?;
 
-    stdin
-        .write_all(request_json.as_bytes())
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
-    stdin
-        .write_all(b"\n")
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
-    stdin
-        .flush()
-        .await
-        .map_err(|e| TranscriptionError::CommunicationError(e.to_string()))?;
+    let (updates_tx, mut updates) = mpsc::unbounded_channel();
+    let (session, _) = sidecar()
+        .open_stream("start_streaming", params, updates_tx)
+        .await?;
+    *STREAMING_SESSION.lock().await = Some(session);
 
-    // Spawn task per leggere gli update
+    // Inoltra i segmenti al frontend finché la sessione non viene chiusa
     tokio::spawn(async move {
-        let mut reader = BufReader::new(stdout);
-        let mut line = String::new();
-
-        loop {
-            line.clear();
-            match reader.read_line(&mut line).await {
-                Ok(0) => break, // EOF
-                Ok(_) => {
-                    // Parse response
-                    if let Ok(update) = serde_json::from_str::<StreamingUpdate>(&line) {
-                        if update.update_type == "streaming_update" {
-                            // Emit event to frontend
-                            let _ = window.emit("transcription-update", update.segments);
-                        }
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Error reading streaming output: {}", e);
-                    break;
+        while let Some(notification) = updates.recv().await {
+            if notification.method != "streaming_update" {
+                continue;
+            }
+            match serde_json::from_value::<StreamingUpdate>(notification.params) {
+                Ok(update) => {
+                    let _ = window.emit("transcription-update", update.segments);
                 }
+                Err(e) => eprintln!("Aggiornamento streaming non valido: {}", e),
             }
         }
     });
@@ -234,33 +236,53 @@ pub async fn start_streaming_transcription(
     Ok(())
 }
 
-pub async fn stop_streaming_transcription() -> Result<(), TranscriptionError> {
-    // Prendi il child dal mutex e rilascia subito il lock
-    let child_opt = {
-        let mut process_guard = STREAMING_PROCESS.lock().unwrap();
-        process_guard.take()
-    };
+/// Invia una richiesta alla sessione di streaming, se attiva
+async fn send_streaming_request(
+    method: &str,
+    params: serde_json::Value,
+) -> Result<(), TranscriptionError> {
+    if STREAMING_SESSION.lock().await.is_none() {
+        return Ok(());
+    }
+    sidecar().call(method, params, None).await?;
+    Ok(())
+}
 
-    if let Some(mut child) = child_opt {
-        // Invia comando stop al processo
-        if let Some(mut stdin) = child.stdin.take() {
-            let request = TranscriptionRequest {
-                command: "stop_streaming".to_string(),
-                audio_path: String::new(),
-                model_size: String::new(),
-                language: None,
-            };
+/// Passa alla trascrizione streaming un enunciato appena concluso: solo il
+/// parlato viene trascritto, il silenzio fra un enunciato e l'altro no.
+/// `audio_path` è il segmento che contiene l'enunciato, iniziato a
+/// `segment_start_seconds`.
+pub async fn send_streaming_utterance(
+    segment: SpeechSegment,
+    audio_path: &Path,
+    segment_start_seconds: f64,
+) -> Result<(), TranscriptionError> {
+    let params = to_params(&UtteranceParams {
+        start_seconds: segment.start_seconds,
+        end_seconds: segment.end_seconds,
+        audio_path: audio_path.to_string_lossy().to_string(),
+        segment_start_seconds,
+    })?;
+    send_streaming_request("utterance", params).await
+}
 
-            if let Ok(request_json) = serde_json::to_string(&request) {
-                let _ = stdin.write_all(request_json.as_bytes()).await;
-                let _ = stdin.write_all(b"\n").await;
-                let _ = stdin.flush().await;
-            }
-        }
+/// Mette in attesa la trascrizione streaming mentre la registrazione è in pausa
+pub async fn pause_streaming_transcription() -> Result<(), TranscriptionError> {
+    send_streaming_request("pause_streaming", json!({})).await
+}
 
-        // Termina il processo
-        let _ = child.kill().await;
-    }
+pub async fn resume_streaming_transcription() -> Result<(), TranscriptionError> {
+    send_streaming_request("resume_streaming", json!({})).await
+}
 
-    Ok(())
+/// Chiude la sessione di streaming. Il sidecar resta attivo, con il modello
+/// già caricato per le trascrizioni successive.
+pub async fn stop_streaming_transcription() -> Result<(), TranscriptionError> {
+    let Some(session) = STREAMING_SESSION.lock().await.take() else {
+        return Ok(());
+    };
+
+    let stopped = sidecar().call("stop_streaming", json!({}), None).await;
+    sidecar().close_stream(session).await;
+    stopped.map(|_| ())
 }
@@ -1,7 +1,9 @@
 import { useState } from "react";
 import { RecordingControls } from "./components/recording/RecordingControls";
+import { ScheduledRecordings } from "./components/recording/ScheduledRecordings";
 import { MeetingList } from "./components/history/MeetingList";
 import { MeetingDetail } from "./components/history/MeetingDetail";
+import { TranscriptionQueue } from "./components/history/TranscriptionQueue";
 import { LLMSettings } from "./components/settings/LLMSettings";
 import { AudioSettings } from "./components/settings/AudioSettings";
 import { Mic, History, Settings } from "lucide-react";
@@ -87,20 +89,24 @@ function App() {
       {/* Main Content */}
       <main className="container mx-auto px-4 py-6">
         {currentView === "recording" && (
-          <div className="max-w-2xl mx-auto">
+          <div className="max-w-2xl mx-auto space-y-6">
             <RecordingControls />
+            <ScheduledRecordings />
           </div>
         )}
 
         {currentView === "history" && (
-          <div className="max-w-4xl mx-auto">
+          <div className="max-w-4xl mx-auto space-y-6">
             {selectedMeetingId ? (
               <MeetingDetail
                 meetingId={selectedMeetingId}
                 onBack={handleBackToList}
               />
             ) : (
-              <MeetingList onSelectMeeting={handleSelectMeeting} />
+              <>
+                <TranscriptionQueue />
+                <MeetingList onSelectMeeting={handleSelectMeeting} />
+              </>
             )}
           </div>
         )}
@@ -0,0 +1,170 @@
+import { useState } from "react";
+import { Button } from "@/components/ui/button";
+import { Input } from "@/components/ui/input";
+import {
+  exportMeetingClip,
+  redactMeetingAudio,
+  type RedactionMode,
+} from "@/lib/tauri-commands";
+import { formatDuration } from "@/lib/utils";
+import { Loader2, Scissors, VolumeX, AudioLines } from "lucide-react";
+
+// Durata proposta per una nuova clip
+const DEFAULT_CLIP_SECONDS = 30;
+
+// Accetta "ss", "mm:ss" e "hh:mm:ss"
+function parseTimestamp(value: string): number | null {
+  const parts = value.trim().split(":");
+  if (parts.length > 3 || parts.some((p) => p === "" || isNaN(Number(p)))) {
+    return null;
+  }
+  return parts.reduce((total, part) => total * 60 + Number(part), 0);
+}
+
+interface ClipToolsProps {
+  meetingId: string;
+  currentTime: number;
+  onRedacted: () => void;
+}
+
+export function ClipTools({
+  meetingId,
+  currentTime,
+  onRedacted,
+}: ClipToolsProps) {
+  const [start, setStart] = useState("0:00");
+  const [end, setEnd] = useState(formatDuration(DEFAULT_CLIP_SECONDS));
+  const [busy, setBusy] = useState<"clip" | RedactionMode | null>(null);
+
+  const range = () => {
+    const startSeconds = parseTimestamp(start);
+    const endSeconds = parseTimestamp(end);
+    if (startSeconds === null || endSeconds === null) {
+      alert("Formato non valido: usa mm:ss");
+      return null;
+    }
+    if (endSeconds <= startSeconds) {
+      alert("La fine deve essere successiva all'inizio");
+      return null;
+    }
+    return { start_seconds: startSeconds, end_seconds: endSeconds };
+  };
+
+  const handleFromPlayer = () => {
+    const position = Math.floor(currentTime);
+    setStart(formatDuration(position));
+    setEnd(formatDuration(position + DEFAULT_CLIP_SECONDS));
+  };
+
+  const handleExport = async () => {
+    const selected = range();
+    if (!selected) return;
+
+    setBusy("clip");
+    try {
+      const path = await exportMeetingClip(
+        meetingId,
+        selected.start_seconds,
+        selected.end_seconds,
+      );
+      if (path) alert(`Clip salvata in ${path}`);
+    } catch (e) {
+      alert(`Errore durante l'esportazione della clip: ${e}`);
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  const handleRedact = async (mode: RedactionMode) => {
+    const selected = range();
+    if (!selected) return;
+    if (
+      !confirm(
+        `L'audio da ${start} a ${end} verrà sostituito definitivamente e le copie master della registrazione verranno eliminate. Continuare?`,
+      )
+    ) {
+      return;
+    }
+
+    setBusy(mode);
+    try {
+      const redaction = await redactMeetingAudio(meetingId, [selected], mode);
+      onRedacted();
+      if (redaction.unredacted_transcriptions > 0) {
+        alert(
+          `Audio oscurato, ma ${redaction.unredacted_transcriptions} trascrizioni non hanno i tempi dei segmenti e non sono state modificate: correggile a mano.`,
+        );
+      } else {
+        alert(
+          `Passaggio oscurato (${redaction.transcript_segments} segmenti della trascrizione)`,
+        );
+      }
+    } catch (e) {
+      alert(`Errore durante l'oscuramento: ${e}`);
+    } finally {
+      setBusy(null);
+    }
+  };
+
+  return (
+    <div className="flex flex-wrap items-center gap-2 pt-2">
+      <Input
+        className="w-20"
+        value={start}
+        onChange={(e) => setStart(e.target.value)}
+        aria-label="Inizio"
+      />
+      <span className="text-sm text-muted-foreground">-</span>
+      <Input
+        className="w-20"
+        value={end}
+        onChange={(e) => setEnd(e.target.value)}
+        aria-label="Fine"
+      />
+      <Button variant="ghost" size="sm" onClick={handleFromPlayer}>
+        Dalla posizione attuale
+      </Button>
+      <div className="ml-auto flex gap-2">
+        <Button
+          variant="outline"
+          size="sm"
+          onClick={handleExport}
+          disabled={busy !== null}
+        >
+          {busy === "clip" ? (
+            <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+          ) : (
+            <Scissors className="mr-2 h-4 w-4" />
+          )}
+          Esporta Clip
+        </Button>
+        <Button
+          variant="outline"
+          size="sm"
+          onClick={() => handleRedact("silence")}
+          disabled={busy !== null}
+        >
+          {busy === "silence" ? (
+            <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+          ) : (
+            <VolumeX className="mr-2 h-4 w-4" />
+          )}
+          Silenzia
+        </Button>
+        <Button
+          variant="outline"
+          size="sm"
+          onClick={() => handleRedact("bleep")}
+          disabled={busy !== null}
+        >
+          {busy === "bleep" ? (
+            <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+          ) : (
+            <AudioLines className="mr-2 h-4 w-4" />
+          )}
+          Bip
+        </Button>
+      </div>
+    </div>
+  );
+}
@@ -1,4 +1,4 @@
-import { useEffect, useState } from "react";
+import { useEffect, useRef, useState } from "react";
 import { useMeetingsStore } from "@/stores/meetingsStore";
 import { useSettingsStore } from "@/stores/settingsStore";
 import {
@@ -10,10 +10,36 @@ import {
 } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { TranscriptViewer } from "./TranscriptViewer";
+import { WaveformView } from "./WaveformView";
+import { ClipTools } from "./ClipTools";
+import { QualitySummary } from "./QualitySummary";
 import { ReportViewer } from "../report/ReportViewer";
 import { formatDate, formatDuration } from "@/lib/utils";
-import { ArrowLeft, FileText, Sparkles, Loader2, Volume2 } from "lucide-react";
+import {
+  ArrowLeft,
+  FileText,
+  Sparkles,
+  Loader2,
+  Volume2,
+  Wand2,
+  X,
+} from "lucide-react";
 import { convertFileSrc } from "@tauri-apps/api/core";
+import { enhanceMeetingAudio } from "@/lib/tauri-commands";
+import type { TranscriptionProgressEvent } from "@/lib/tauri-commands";
+
+function describeProgress(progress: TranscriptionProgressEvent): string {
+  const parts = [
+    `${Math.round(progress.percent)}%`,
+    `${formatDuration(Math.round(progress.processed_seconds))} di ${formatDuration(Math.round(progress.total_seconds))}`,
+  ];
+  if (progress.eta_seconds != null) {
+    parts.push(
+      `circa ${formatDuration(Math.round(progress.eta_seconds))} alla fine`,
+    );
+  }
+  return parts.join(" • ");
+}
 
 interface MeetingDetailProps {
   meetingId: string;
@@ -25,19 +51,38 @@ export function MeetingDetail({ meetingId, onBack }: MeetingDetailProps) {
     currentMeeting,
     loadMeeting,
     transcribe,
+    cancelTranscription,
     generateReport,
     isTranscribing,
+    transcriptionJobId,
+    transcriptionProgress,
+    partialSegments,
     isGeneratingReport,
   } = useMeetingsStore();
   const { appSettings } = useSettingsStore();
   const [activeTab, setActiveTab] = useState<"transcript" | "report">(
     "transcript",
   );
+  const [isEnhancing, setIsEnhancing] = useState(false);
+  const [currentTime, setCurrentTime] = useState(0);
+  // L'audio può essere riscritto allo stesso percorso: la versione evita
+  // che il player e la forma d'onda mostrino il file in cache
+  const [audioVersion, setAudioVersion] = useState(0);
+  const audioRef = useRef<HTMLAudioElement>(null);
+  // Le registrazioni lunghe sono divise in più file, riprodotti in sequenza
+  const [segmentIndex, setSegmentIndex] = useState(0);
+  const pendingSeek = useRef<number | null>(null);
+  const resumePlayback = useRef(false);
 
   useEffect(() => {
     loadMeeting(meetingId);
   }, [meetingId, loadMeeting]);
 
+  useEffect(() => {
+    setSegmentIndex(0);
+    setCurrentTime(0);
+  }, [meetingId, audioVersion]);
+
   const handleTranscribe = async () => {
     if (!currentMeeting?.meeting.audio_path) return;
 
@@ -45,14 +90,22 @@ export function MeetingDetail({ meetingId, onBack }: MeetingDetailProps) {
       const modelSize = appSettings?.whisper_model || "base";
       const language = appSettings?.default_language || "it";
 
-      await transcribe(
-        meetingId,
-        currentMeeting.meeting.audio_path,
-        modelSize,
-        language,
-      );
+      await transcribe(meetingId, modelSize, language);
+    } catch (e) {
+      alert(`Errore durante la trascrizione: ${e}`);
+    }
+  };
+
+  const handleEnhance = async () => {
+    setIsEnhancing(true);
+    try {
+      await enhanceMeetingAudio(meetingId);
+      setAudioVersion((v) => v + 1);
+      await loadMeeting(meetingId);
     } catch (e) {
-      alert("Errore durante la trascrizione");
+      alert(`Errore durante il miglioramento dell'audio: ${e}`);
+    } finally {
+      setIsEnhancing(false);
     }
   };
 
@@ -78,6 +131,48 @@ export function MeetingDetail({ meetingId, onBack }: MeetingDetailProps) {
   }
 
   const { meeting, transcript, report } = currentMeeting;
+  const segments = currentMeeting.audio_segments ?? [];
+  const segment = segments.length > 1 ? segments[segmentIndex] : undefined;
+  const segmentStart = segment?.start_seconds ?? 0;
+  const playbackPath = segment?.path ?? meeting.audio_path;
+
+  // Porta il player all'istante `seconds` dell'intera registrazione,
+  // cambiando file se cade in un altro segmento
+  const seekTo = (seconds: number) => {
+    const audio = audioRef.current;
+    if (!audio) return;
+
+    let target = 0;
+    segments.forEach((s, i) => {
+      if (s.start_seconds <= seconds) target = i;
+    });
+    if (segments.length <= 1 || target === segmentIndex) {
+      audio.currentTime = seconds - segmentStart;
+      return;
+    }
+
+    pendingSeek.current = seconds - segments[target].start_seconds;
+    resumePlayback.current = !audio.paused;
+    setSegmentIndex(target);
+  };
+
+  const handleSegmentEnded = () => {
+    if (segments.length <= 1 || segmentIndex + 1 >= segments.length) return;
+    pendingSeek.current = 0;
+    resumePlayback.current = true;
+    setSegmentIndex(segmentIndex + 1);
+  };
+
+  const handleLoadedMetadata = (audio: HTMLAudioElement) => {
+    if (pendingSeek.current !== null) {
+      audio.currentTime = pendingSeek.current;
+      pendingSeek.current = null;
+    }
+    if (resumePlayback.current) {
+      resumePlayback.current = false;
+      audio.play().catch(() => {});
+    }
+  };
 
   return (
     <div className="space-y-4">
@@ -110,14 +205,57 @@ export function MeetingDetail({ meetingId, onBack }: MeetingDetailProps) {
               <div className="flex items-center gap-3 mb-2">
                 <Volume2 className="h-4 w-4 text-muted-foreground" />
                 <span className="text-sm font-medium">Registrazione Audio</span>
+                <Button
+                  variant="outline"
+                  size="sm"
+                  className="ml-auto"
+                  onClick={handleEnhance}
+                  disabled={isEnhancing}
+                  title="Applica passa-alto, riduzione del rumore e controllo del volume configurati nelle impostazioni"
+                >
+                  {isEnhancing ? (
+                    <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+                  ) : (
+                    <Wand2 className="mr-2 h-4 w-4" />
+                  )}
+                  Migliora Audio
+                </Button>
               </div>
+              <WaveformView
+                key={audioVersion}
+                meetingId={meeting.id}
+                audioPath={meeting.audio_path}
+                currentTime={currentTime}
+                onSeek={seekTo}
+              />
               <audio
+                ref={audioRef}
                 controls
                 className="w-full"
-                src={convertFileSrc(meeting.audio_path)}
+                src={`${convertFileSrc(playbackPath ?? meeting.audio_path)}?v=${audioVersion}`}
+                onTimeUpdate={(e) =>
+                  setCurrentTime(segmentStart + e.currentTarget.currentTime)
+                }
+                onEnded={handleSegmentEnded}
+                onLoadedMetadata={(e) => handleLoadedMetadata(e.currentTarget)}
               >
                 Il tuo browser non supporta la riproduzione audio.
               </audio>
+              <ClipTools
+                meetingId={meeting.id}
+                currentTime={currentTime}
+                onRedacted={() => {
+                  setAudioVersion((v) => v + 1);
+                  loadMeeting(meetingId);
+                }}
+              />
+              <div className="mt-4 border-t pt-4">
+                <QualitySummary
+                  meetingId={meeting.id}
+                  report={currentMeeting.quality_report}
+                  onAnalyzed={() => loadMeeting(meetingId)}
+                />
+              </div>
             </div>
           )}
 
@@ -141,7 +279,42 @@ export function MeetingDetail({ meetingId, onBack }: MeetingDetailProps) {
                     </>
                   )}
                 </Button>
+                {isTranscribing && transcriptionJobId && (
+                  <Button
+                    variant="ghost"
+                    className="ml-2"
+                    onClick={cancelTranscription}
+                  >
+                    <X className="mr-2 h-4 w-4" />
+                    Annulla
+                  </Button>
+                )}
               </div>
+              {isTranscribing && transcriptionProgress && (
+                <div className="max-w-md mx-auto space-y-2 text-left">
+                  <div className="h-2 rounded-full bg-muted overflow-hidden">
+                    <div
+                      className="h-full bg-primary transition-all"
+                      style={{ width: `${transcriptionProgress.percent}%` }}
+                    />
+                  </div>
+                  <p className="text-xs text-muted-foreground text-center">
+                    {describeProgress(transcriptionProgress)}
+                  </p>
+                </div>
+              )}
+              {isTranscribing && partialSegments.length > 0 && (
+                <div className="max-h-64 overflow-y-auto space-y-1 text-left text-sm">
+                  {partialSegments.map((segment, index) => (
+                    <p key={index}>
+                      <span className="text-xs text-muted-foreground mr-2">
+                        {formatDuration(Math.round(segment.start))}
+                      </span>
+                      {segment.text}
+                    </p>
+                  ))}
+                </div>
+              )}
             </div>
           )}
 
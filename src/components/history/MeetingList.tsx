@@ -1,16 +1,21 @@
 import { useEffect } from "react";
 import { useMeetingsStore } from "@/stores/meetingsStore";
+import { useSettingsStore } from "@/stores/settingsStore";
 import { Card, CardContent, CardDescription, CardHeader, CardTitle } from "@/components/ui/card";
 import { Button } from "@/components/ui/button";
 import { formatDate, formatDuration } from "@/lib/utils";
-import { FileAudio, Trash2, Eye } from "lucide-react";
+import { transcribeMeeting } from "@/lib/tauri-commands";
+import type { Meeting } from "@/lib/tauri-commands";
+import { FileAudio, Trash2, Eye, Upload, Loader2, ListPlus } from "lucide-react";
 
 interface MeetingListProps {
   onSelectMeeting: (id: string) => void;
 }
 
 export function MeetingList({ onSelectMeeting }: MeetingListProps) {
-  const { meetings, loadMeetings, remove, isLoading } = useMeetingsStore();
+  const { meetings, loadMeetings, remove, importFile, isLoading, isImporting } =
+    useMeetingsStore();
+  const { appSettings } = useSettingsStore();
 
   useEffect(() => {
     loadMeetings();
@@ -19,6 +24,7 @@ export function MeetingList({ onSelectMeeting }: MeetingListProps) {
   const getStatusBadge = (status: string) => {
     const statusMap: Record<string, { label: string; className: string }> = {
       recording: { label: "Registrazione", className: "bg-destructive text-destructive-foreground" },
+      paused: { label: "In pausa", className: "bg-muted text-muted-foreground" },
       recorded: { label: "Registrata", className: "bg-secondary text-secondary-foreground" },
       transcribing: { label: "Trascrizione", className: "bg-primary text-primary-foreground" },
       transcribed: { label: "Trascritta", className: "bg-accent text-accent-foreground" },
@@ -44,6 +50,46 @@ export function MeetingList({ onSelectMeeting }: MeetingListProps) {
     }
   };
 
+  // Riunioni con audio non ancora trascritto
+  const canEnqueue = (meeting: Meeting) =>
+    !!meeting.audio_path &&
+    !["recording", "paused", "transcribed", "completed"].includes(meeting.status);
+
+  const handleEnqueue = async (meeting: Meeting, e: React.MouseEvent) => {
+    e.stopPropagation();
+    try {
+      await transcribeMeeting(
+        meeting.id,
+        appSettings?.whisper_model || "base",
+        appSettings?.default_language || "it",
+      );
+    } catch (e) {
+      alert(`Impossibile mettere in coda la trascrizione: ${e}`);
+    }
+  };
+
+  const handleImport = async () => {
+    try {
+      const meeting = await importFile();
+      if (meeting) {
+        onSelectMeeting(meeting.id);
+      }
+    } catch (e) {
+      alert(`Errore durante l'importazione: ${e}`);
+    }
+  };
+
+  const importButton = (
+    <Button variant="outline" size="sm" onClick={handleImport} disabled={isImporting}>
+      {isImporting ? (
+        <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+      ) : (
+        <Upload className="mr-2 h-4 w-4" />
+      )}
+      {isImporting ? "Importazione..." : "Importa File"}
+    </Button>
+  );
+
   if (isLoading) {
     return (
       <Card>
@@ -60,9 +106,12 @@ export function MeetingList({ onSelectMeeting }: MeetingListProps) {
   if (meetings.length === 0) {
     return (
       <Card>
-        <CardHeader>
-          <CardTitle>Storico Riunioni</CardTitle>
-          <CardDescription>Le tue riunioni registrate appariranno qui</CardDescription>
+        <CardHeader className="flex flex-row items-start justify-between space-y-0">
+          <div className="space-y-1.5">
+            <CardTitle>Storico Riunioni</CardTitle>
+            <CardDescription>Le tue riunioni registrate appariranno qui</CardDescription>
+          </div>
+          {importButton}
         </CardHeader>
         <CardContent>
           <p className="text-center text-muted-foreground py-8">
@@ -75,9 +124,12 @@ export function MeetingList({ onSelectMeeting }: MeetingListProps) {
 
   return (
     <Card>
-      <CardHeader>
-        <CardTitle>Storico Riunioni</CardTitle>
-        <CardDescription>{meetings.length} riunioni registrate</CardDescription>
+      <CardHeader className="flex flex-row items-start justify-between space-y-0">
+        <div className="space-y-1.5">
+          <CardTitle>Storico Riunioni</CardTitle>
+          <CardDescription>{meetings.length} riunioni registrate</CardDescription>
+        </div>
+        {importButton}
       </CardHeader>
       <CardContent className="space-y-2">
         {meetings.map((meeting) => (
@@ -102,6 +154,16 @@ export function MeetingList({ onSelectMeeting }: MeetingListProps) {
             </div>
 
             <div className="flex items-center gap-2">
+              {canEnqueue(meeting) && (
+                <Button
+                  size="icon"
+                  variant="ghost"
+                  title="Metti in coda la trascrizione"
+                  onClick={(e) => handleEnqueue(meeting, e)}
+                >
+                  <ListPlus className="h-4 w-4" />
+                </Button>
+              )}
               <Button
                 size="icon"
                 variant="ghost"
@@ -0,0 +1,118 @@
+import { useState } from "react";
+import { Button } from "@/components/ui/button";
+import {
+  analyzeMeetingQuality,
+  type QualityIssue,
+  type QualityReport,
+  type QualityWarning,
+} from "@/lib/tauri-commands";
+import { formatDuration } from "@/lib/utils";
+import { Activity, AlertTriangle, CheckCircle2, Loader2 } from "lucide-react";
+
+export const QUALITY_ISSUE_LABELS: Record<QualityIssue, string> = {
+  clipping: "Clipping",
+  low_signal: "Segnale debole",
+  dc_offset: "Offset DC",
+  dropout: "Interruzioni",
+  low_snr: "Rumore di fondo elevato",
+};
+
+// Testo breve per un avviso ricevuto durante la registrazione
+export function describeQualityWarning(warning: QualityWarning): string {
+  const at = formatDuration(Math.floor(warning.offset_seconds));
+  switch (warning.issue) {
+    case "clipping":
+      return `${at} • Clipping (${warning.value.toFixed(2)}% dei campioni): abbassa il guadagno`;
+    case "low_signal":
+      return `${at} • Segnale debole (${warning.value.toFixed(0)} dBFS): avvicina il microfono`;
+    case "dc_offset":
+      return `${at} • Offset DC (${warning.value.toFixed(3)}): controlla il dispositivo`;
+    case "dropout":
+      return `${at} • Interruzione di ${warning.value.toFixed(1)} s nell'audio`;
+    case "low_snr":
+      return `${at} • Rapporto segnale/rumore basso (${warning.value.toFixed(0)} dB)`;
+  }
+}
+
+function formatDb(value: number | null): string {
+  return value === null ? "–" : `${value.toFixed(1)} dB`;
+}
+
+interface QualitySummaryProps {
+  meetingId: string;
+  report: QualityReport | null;
+  onAnalyzed: () => void;
+}
+
+export function QualitySummary({
+  meetingId,
+  report,
+  onAnalyzed,
+}: QualitySummaryProps) {
+  const [isAnalyzing, setIsAnalyzing] = useState(false);
+
+  const handleAnalyze = async () => {
+    setIsAnalyzing(true);
+    try {
+      await analyzeMeetingQuality(meetingId);
+      onAnalyzed();
+    } catch (e) {
+      alert(`Errore durante l'analisi della qualità: ${e}`);
+    } finally {
+      setIsAnalyzing(false);
+    }
+  };
+
+  if (!report) {
+    return (
+      <div className="flex items-center gap-2 text-sm text-muted-foreground">
+        <Activity className="h-4 w-4" />
+        <span>Qualità audio non analizzata</span>
+        <Button
+          variant="outline"
+          size="sm"
+          className="ml-auto"
+          onClick={handleAnalyze}
+          disabled={isAnalyzing}
+        >
+          {isAnalyzing && <Loader2 className="mr-2 h-4 w-4 animate-spin" />}
+          Analizza qualità
+        </Button>
+      </div>
+    );
+  }
+
+  return (
+    <div className="space-y-2">
+      {report.tracks.map((track) => (
+        <div key={track.track} className="text-sm space-y-1">
+          <div className="flex items-center gap-2">
+            {track.issues.length === 0 ? (
+              <CheckCircle2 className="h-4 w-4 text-green-600" />
+            ) : (
+              <AlertTriangle className="h-4 w-4 text-yellow-600" />
+            )}
+            <span className="font-medium">
+              {report.tracks.length > 1
+                ? `Traccia ${track.track + 1}`
+                : "Qualità audio"}
+            </span>
+            <span className="text-muted-foreground">
+              {track.issues.length === 0
+                ? "nessun problema rilevato"
+                : track.issues.map((i) => QUALITY_ISSUE_LABELS[i]).join(", ")}
+            </span>
+          </div>
+          <p className="text-xs text-muted-foreground">
+            Picco {formatDb(track.peak_db)} • Livello mediano{" "}
+            {formatDb(track.median_db)} • SNR {formatDb(track.snr_db)}
+            {track.clipped_samples > 0 &&
+              ` • Clipping ${track.clipping_percent.toFixed(3)}%`}
+            {track.dropouts > 0 &&
+              ` • ${track.dropouts} interruzioni (${track.dropout_seconds.toFixed(1)} s)`}
+          </p>
+        </div>
+      ))}
+    </div>
+  );
+}
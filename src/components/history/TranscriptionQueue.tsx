@@ -0,0 +1,196 @@
+import { useCallback, useEffect, useState } from "react";
+import { Button } from "@/components/ui/button";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { useMeetingsStore } from "@/stores/meetingsStore";
+import { formatDate } from "@/lib/utils";
+import { listen } from "@tauri-apps/api/event";
+import {
+  cancelTranscription,
+  getTranscriptionJobs,
+  reorderTranscriptionJobs,
+  retryTranscriptionJob,
+  type TranscriptionFinishedEvent,
+  type TranscriptionJob,
+  type TranscriptionProgressEvent,
+} from "@/lib/tauri-commands";
+import { ArrowDown, ArrowUp, Loader2, RotateCcw, X } from "lucide-react";
+
+const statusLabels: Record<TranscriptionJob["status"], string> = {
+  pending: "In coda",
+  running: "In corso",
+  completed: "Completata",
+  failed: "Non riuscita",
+  cancelled: "Annullata",
+};
+
+function describeJob(job: TranscriptionJob, percent?: number): string {
+  const parts = [statusLabels[job.status], `modello ${job.model_size}`];
+  if (job.status === "running" && percent != null) {
+    parts.push(`${Math.round(percent)}%`);
+  }
+  if (job.status === "pending" && job.next_attempt_at) {
+    parts.push(`nuovo tentativo alle ${formatDate(job.next_attempt_at)}`);
+  }
+  if (job.finished_at) parts.push(formatDate(job.finished_at));
+  return parts.join(" • ");
+}
+
+export function TranscriptionQueue() {
+  const [jobs, setJobs] = useState<TranscriptionJob[]>([]);
+  // Percentuale dei job in corso, dagli eventi di avanzamento
+  const [progress, setProgress] = useState<Record<string, number>>({});
+  const { loadMeetings } = useMeetingsStore();
+
+  const loadJobs = useCallback(async () => {
+    try {
+      setJobs(await getTranscriptionJobs());
+    } catch (e) {
+      console.error("Errore caricamento coda di trascrizione:", e);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadJobs();
+  }, [loadJobs]);
+
+  useEffect(() => {
+    const unlistenChanged = listen("transcription-jobs-changed", loadJobs);
+    const unlistenProgress = listen<TranscriptionProgressEvent>(
+      "transcription-progress",
+      (event) => {
+        const { job_id, percent } = event.payload;
+        setProgress((current) => ({ ...current, [job_id]: percent }));
+      },
+    );
+    // Lo stato della riunione cambia quando la trascrizione viene salvata
+    const unlistenFinished = listen<TranscriptionFinishedEvent>(
+      "transcription-finished",
+      (event) => {
+        if (event.payload.status === "completed") loadMeetings();
+      },
+    );
+
+    return () => {
+      unlistenChanged.then((fn) => fn());
+      unlistenProgress.then((fn) => fn());
+      unlistenFinished.then((fn) => fn());
+    };
+  }, [loadJobs, loadMeetings]);
+
+  const pending = jobs.filter((job) => job.status === "pending");
+
+  const handleMove = async (jobId: string, offset: number) => {
+    const ids = pending.map((job) => job.id);
+    const from = ids.indexOf(jobId);
+    const to = from + offset;
+    if (from < 0 || to < 0 || to >= ids.length) return;
+    [ids[from], ids[to]] = [ids[to], ids[from]];
+    try {
+      await reorderTranscriptionJobs(ids);
+    } catch (e) {
+      alert(`Errore durante il riordino: ${e}`);
+    }
+  };
+
+  const handleRetry = async (jobId: string) => {
+    try {
+      await retryTranscriptionJob(jobId);
+    } catch (e) {
+      alert(`Impossibile ritentare la trascrizione: ${e}`);
+    }
+  };
+
+  const handleCancel = async (jobId: string) => {
+    try {
+      await cancelTranscription(jobId);
+    } catch (e) {
+      alert(`Errore durante l'annullamento: ${e}`);
+    }
+  };
+
+  if (jobs.length === 0) return null;
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle>Coda di Trascrizione</CardTitle>
+        <CardDescription>
+          Le trascrizioni partono una alla volta, nell'ordine della coda
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-2">
+        {jobs.map((job) => {
+          const index = pending.indexOf(job);
+          return (
+            <div
+              key={job.id}
+              className="flex items-center gap-3 p-3 rounded-lg border text-sm"
+            >
+              {job.status === "running" && (
+                <Loader2 className="h-4 w-4 animate-spin text-muted-foreground flex-shrink-0" />
+              )}
+              <div className="flex-1 min-w-0">
+                <p className="font-medium truncate">
+                  {job.meeting_title || "Riunione"}
+                </p>
+                <p className="text-muted-foreground">
+                  {describeJob(job, progress[job.id])}
+                </p>
+                {job.error && job.status !== "completed" && (
+                  <p className="text-destructive">{job.error}</p>
+                )}
+              </div>
+
+              <div className="flex items-center gap-1">
+                {job.status === "pending" && (
+                  <>
+                    <Button
+                      size="icon"
+                      variant="ghost"
+                      disabled={index === 0}
+                      onClick={() => handleMove(job.id, -1)}
+                    >
+                      <ArrowUp className="h-4 w-4" />
+                    </Button>
+                    <Button
+                      size="icon"
+                      variant="ghost"
+                      disabled={index === pending.length - 1}
+                      onClick={() => handleMove(job.id, 1)}
+                    >
+                      <ArrowDown className="h-4 w-4" />
+                    </Button>
+                  </>
+                )}
+                {(job.status === "failed" || job.status === "cancelled") && (
+                  <Button
+                    size="icon"
+                    variant="ghost"
+                    onClick={() => handleRetry(job.id)}
+                  >
+                    <RotateCcw className="h-4 w-4" />
+                  </Button>
+                )}
+                {(job.status === "pending" || job.status === "running") && (
+                  <Button
+                    size="icon"
+                    variant="ghost"
+                    onClick={() => handleCancel(job.id)}
+                  >
+                    <X className="h-4 w-4" />
+                  </Button>
+                )}
+              </div>
+            </div>
+          );
+        })}
+      </CardContent>
+    </Card>
+  );
+}
@@ -0,0 +1,95 @@
+import { useEffect, useRef, useState, type MouseEvent } from "react";
+import { getWaveform, type WaveformSlice } from "@/lib/tauri-commands";
+
+const HEIGHT = 64;
+
+interface WaveformViewProps {
+  meetingId: string;
+  // Cambia quando l'audio viene sostituito (es. dopo il miglioramento)
+  audioPath: string;
+  currentTime: number;
+  onSeek: (seconds: number) => void;
+}
+
+export function WaveformView({
+  meetingId,
+  audioPath,
+  currentTime,
+  onSeek,
+}: WaveformViewProps) {
+  const containerRef = useRef<HTMLDivElement>(null);
+  const canvasRef = useRef<HTMLCanvasElement>(null);
+  const [width, setWidth] = useState(0);
+  const [waveform, setWaveform] = useState<WaveformSlice | null>(null);
+
+  useEffect(() => {
+    const container = containerRef.current;
+    if (!container) return;
+
+    const observer = new ResizeObserver(([entry]) => {
+      setWidth(Math.floor(entry.contentRect.width));
+    });
+    observer.observe(container);
+    return () => observer.disconnect();
+  }, []);
+
+  // Un bucket per pixel: il backend restituisce solo quello che serve
+  useEffect(() => {
+    if (width === 0) return;
+
+    let cancelled = false;
+    getWaveform(meetingId, Math.round(width * window.devicePixelRatio))
+      .then((slice) => {
+        if (!cancelled) setWaveform(slice);
+      })
+      .catch((e) => console.error("Errore caricamento forma d'onda:", e));
+
+    return () => {
+      cancelled = true;
+    };
+  }, [meetingId, audioPath, width]);
+
+  useEffect(() => {
+    const canvas = canvasRef.current;
+    if (!canvas || !waveform || width === 0) return;
+
+    const ratio = window.devicePixelRatio;
+    canvas.width = Math.round(width * ratio);
+    canvas.height = HEIGHT * ratio;
+    const ctx = canvas.getContext("2d");
+    if (!ctx) return;
+
+    const mid = canvas.height / 2;
+    const progress =
+      waveform.duration_seconds > 0
+        ? (currentTime / waveform.duration_seconds) * waveform.peaks.length
+        : 0;
+
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    ctx.fillStyle = getComputedStyle(canvas).getPropertyValue("color");
+    waveform.peaks.forEach(([min, max], x) => {
+      ctx.globalAlpha = x < progress ? 1 : 0.35;
+      const top = mid - max * mid;
+      const height = Math.max((max - min) * mid, 1);
+      ctx.fillRect(x, top, 1, height);
+    });
+  }, [waveform, width, currentTime]);
+
+  const handleClick = (e: MouseEvent<HTMLCanvasElement>) => {
+    if (!waveform) return;
+    const rect = e.currentTarget.getBoundingClientRect();
+    const fraction = (e.clientX - rect.left) / rect.width;
+    onSeek(fraction * waveform.duration_seconds);
+  };
+
+  return (
+    <div ref={containerRef} className="w-full">
+      <canvas
+        ref={canvasRef}
+        className="w-full cursor-pointer text-primary"
+        style={{ height: HEIGHT }}
+        onClick={handleClick}
+      />
+    </div>
+  );
+}
@@ -1,21 +1,113 @@
-import { useEffect } from "react";
+import { useEffect, useState } from "react";
 import { Select } from "@/components/ui/select";
 import { useRecordingStore } from "@/stores/recordingStore";
-import { Mic } from "lucide-react";
+import { getDeviceCapabilities } from "@/lib/tauri-commands";
+import type { DeviceCapabilities, InputConfig } from "@/lib/tauri-commands";
+import { Mic, Settings2, Speaker } from "lucide-react";
+
+// Frequenze proposte all'utente, filtrate in base a quelle supportate
+const COMMON_SAMPLE_RATES = [8000, 16000, 22050, 32000, 44100, 48000, 96000];
+
+function configKey(config: InputConfig): string {
+  return `${config.sample_rate}:${config.channels}`;
+}
+
+function configOptions(capabilities: DeviceCapabilities): InputConfig[] {
+  const seen = new Set<string>();
+  const configs: InputConfig[] = [];
+
+  for (const range of capabilities.supported_configs) {
+    for (const sampleRate of COMMON_SAMPLE_RATES) {
+      if (
+        sampleRate < range.min_sample_rate ||
+        sampleRate > range.max_sample_rate
+      ) {
+        continue;
+      }
+      const config = { sample_rate: sampleRate, channels: range.channels };
+      if (!seen.has(configKey(config))) {
+        seen.add(configKey(config));
+        configs.push(config);
+      }
+    }
+  }
+
+  return configs.sort(
+    (a, b) => a.channels - b.channels || a.sample_rate - b.sample_rate,
+  );
+}
 
 export function AudioSourceSelector() {
-  const { devices, selectedDeviceId, setSelectedDevice, loadDevices } =
-    useRecordingStore();
+  const {
+    devices,
+    selectedDeviceId,
+    selectedLoopbackId,
+    streamConfigs,
+    setSelectedDevice,
+    setSelectedLoopback,
+    setStreamConfig,
+    loadDevices,
+  } = useRecordingStore();
+  const [capabilities, setCapabilities] = useState<DeviceCapabilities | null>(
+    null,
+  );
 
   useEffect(() => {
     loadDevices();
   }, [loadDevices]);
 
-  const options = devices.map((device) => ({
+  useEffect(() => {
+    setCapabilities(null);
+    if (!selectedDeviceId) return;
+
+    getDeviceCapabilities(selectedDeviceId)
+      .then(setCapabilities)
+      .catch((e) => console.error("Errore lettura capacità dispositivo:", e));
+  }, [selectedDeviceId]);
+
+  const inputs = devices.filter((device) => !device.is_loopback);
+  const loopbacks = devices.filter((device) => device.is_loopback);
+
+  const options = inputs.map((device) => ({
     value: device.id,
     label: `${device.name}${device.is_default ? " (predefinito)" : ""}`,
   }));
 
+  const selectedConfig = selectedDeviceId
+    ? streamConfigs[selectedDeviceId]
+    : undefined;
+  const defaultConfig = capabilities?.default_config;
+  const formatOptions = [
+    {
+      value: "",
+      label: defaultConfig
+        ? `Predefinito (${defaultConfig.sample_rate} Hz, ${defaultConfig.channels} ch)`
+        : "Predefinito",
+    },
+    ...(capabilities ? configOptions(capabilities) : []).map((config) => ({
+      value: configKey(config),
+      label: `${config.sample_rate} Hz, ${config.channels} ch`,
+    })),
+  ];
+
+  const handleFormatChange = (value: string) => {
+    if (!selectedDeviceId) return;
+    if (!value) {
+      setStreamConfig(selectedDeviceId, null);
+      return;
+    }
+    const [sampleRate, channels] = value.split(":").map(Number);
+    setStreamConfig(selectedDeviceId, {
+      sample_rate: sampleRate,
+      channels,
+    });
+  };
+
+  const loopbackOptions = [
+    { value: "", label: "Nessuno" },
+    ...loopbacks.map((device) => ({ value: device.id, label: device.name })),
+  ];
+
   if (devices.length === 0) {
     return (
       <div className="flex items-center gap-2 text-sm text-muted-foreground">
@@ -26,16 +118,46 @@ export function AudioSourceSelector() {
   }
 
   return (
-    <div className="space-y-2">
-      <label className="text-sm font-medium flex items-center gap-2">
-        <Mic className="h-4 w-4" />
-        Sorgente Audio
-      </label>
-      <Select
-        options={options}
-        value={selectedDeviceId || ""}
-        onChange={(e) => setSelectedDevice(e.target.value)}
-      />
+    <div className="space-y-4">
+      <div className="space-y-2">
+        <label className="text-sm font-medium flex items-center gap-2">
+          <Mic className="h-4 w-4" />
+          Sorgente Audio
+        </label>
+        <Select
+          options={options}
+          value={selectedDeviceId || ""}
+          onChange={(e) => setSelectedDevice(e.target.value)}
+        />
+      </div>
+
+      {capabilities && formatOptions.length > 1 && (
+        <div className="space-y-2">
+          <label className="text-sm font-medium flex items-center gap-2">
+            <Settings2 className="h-4 w-4" />
+            Formato di Acquisizione
+          </label>
+          <Select
+            options={formatOptions}
+            value={selectedConfig ? configKey(selectedConfig) : ""}
+            onChange={(e) => handleFormatChange(e.target.value)}
+          />
+        </div>
+      )}
+
+      {loopbacks.length > 0 && (
+        <div className="space-y-2">
+          <label className="text-sm font-medium flex items-center gap-2">
+            <Speaker className="h-4 w-4" />
+            Audio di Sistema
+          </label>
+          <Select
+            options={loopbackOptions}
+            value={selectedLoopbackId || ""}
+            onChange={(e) => setSelectedLoopback(e.target.value || null)}
+          />
+        </div>
+      )}
     </div>
   );
 }
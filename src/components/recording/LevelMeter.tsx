@@ -0,0 +1,100 @@
+import { useEffect, useRef, useState } from "react";
+import { listen } from "@tauri-apps/api/event";
+import { AlertTriangle, MicOff } from "lucide-react";
+import type { TrackLevel } from "@/lib/tauri-commands";
+
+// Sotto questa soglia l'ingresso è considerato muto
+const SILENCE_DB = -60;
+// Per quanto tempo il silenzio deve durare prima dell'avviso
+const SILENCE_WARNING_MS = 3000;
+// Per quanto resta visibile l'avviso di clipping
+const CLIP_HOLD_MS = 1500;
+// Estremo inferiore della scala del meter
+const MIN_DB = -60;
+
+function toDb(value: number): number {
+  return value > 0 ? 20 * Math.log10(value) : -Infinity;
+}
+
+function toPercent(value: number): number {
+  const db = Math.max(toDb(value), MIN_DB);
+  return ((db - MIN_DB) / -MIN_DB) * 100;
+}
+
+interface LevelMeterProps {
+  labels?: string[];
+}
+
+export function LevelMeter({ labels = [] }: LevelMeterProps) {
+  const [levels, setLevels] = useState<TrackLevel[]>([]);
+  const [silent, setSilent] = useState(false);
+  const [clipping, setClipping] = useState(false);
+  const silentSince = useRef<number | null>(null);
+  const lastClip = useRef(0);
+
+  useEffect(() => {
+    const unlisten = listen<TrackLevel[]>("audio-level", (event) => {
+      const now = Date.now();
+      setLevels(event.payload);
+
+      const loudest = Math.max(0, ...event.payload.map((l) => l.rms));
+      if (toDb(loudest) < SILENCE_DB) {
+        silentSince.current ??= now;
+      } else {
+        silentSince.current = null;
+      }
+      setSilent(
+        silentSince.current !== null &&
+          now - silentSince.current > SILENCE_WARNING_MS,
+      );
+
+      if (event.payload.some((l) => l.clipping)) {
+        lastClip.current = now;
+      }
+      setClipping(now - lastClip.current < CLIP_HOLD_MS);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  return (
+    <div className="space-y-2">
+      {levels.map((level) => (
+        <div key={level.track} className="space-y-1">
+          {levels.length > 1 && (
+            <p className="text-xs text-muted-foreground">
+              {labels[level.track] ?? `Traccia ${level.track + 1}`}
+            </p>
+          )}
+          <div className="relative h-2 rounded-full bg-muted overflow-hidden">
+            <div
+              className={`absolute inset-y-0 left-0 transition-[width] duration-75 ${
+                level.clipping ? "bg-destructive" : "bg-green-500"
+              }`}
+              style={{ width: `${toPercent(level.rms)}%` }}
+            />
+            <div
+              className="absolute inset-y-0 w-0.5 bg-foreground/60"
+              style={{ left: `${toPercent(level.peak)}%` }}
+            />
+          </div>
+        </div>
+      ))}
+
+      {clipping && (
+        <p className="flex items-center text-sm text-destructive">
+          <AlertTriangle className="mr-2 h-4 w-4" />
+          Segnale in clipping: riduci il guadagno del microfono
+        </p>
+      )}
+      {silent && !clipping && (
+        <p className="flex items-center text-sm text-muted-foreground">
+          <MicOff className="mr-2 h-4 w-4" />
+          Nessun segnale: il microfono potrebbe essere disattivato
+        </p>
+      )}
+    </div>
+  );
+}
@@ -0,0 +1,60 @@
+import { useEffect, useRef } from "react";
+import { listen } from "@tauri-apps/api/event";
+import type { WaveformUpdate } from "@/lib/tauri-commands";
+
+// Secondi di audio visibili, scorrendo da destra a sinistra
+const WINDOW_SECONDS = 10;
+const HEIGHT = 48;
+
+export function LiveWaveform() {
+  const canvasRef = useRef<HTMLCanvasElement>(null);
+  const peaks = useRef<[number, number][]>([]);
+
+  useEffect(() => {
+    const draw = (secondsPerPeak: number) => {
+      const canvas = canvasRef.current;
+      const ctx = canvas?.getContext("2d");
+      if (!canvas || !ctx) return;
+
+      const ratio = window.devicePixelRatio;
+      canvas.width = canvas.clientWidth * ratio;
+      canvas.height = HEIGHT * ratio;
+
+      const visible = Math.ceil(WINDOW_SECONDS / secondsPerPeak);
+      const step = canvas.width / visible;
+      const mid = canvas.height / 2;
+      const offset = visible - peaks.current.length;
+
+      ctx.clearRect(0, 0, canvas.width, canvas.height);
+      ctx.fillStyle = getComputedStyle(canvas).getPropertyValue("color");
+      peaks.current.forEach(([min, max], i) => {
+        const height = Math.max((max - min) * mid, 1);
+        ctx.fillRect(
+          (offset + i) * step,
+          mid - max * mid,
+          Math.max(step, 1),
+          height,
+        );
+      });
+    };
+
+    const unlisten = listen<WaveformUpdate>("audio-waveform", (event) => {
+      const { peaks: update, seconds_per_peak } = event.payload;
+      const visible = Math.ceil(WINDOW_SECONDS / seconds_per_peak);
+      peaks.current = [...peaks.current, ...update].slice(-visible);
+      draw(seconds_per_peak);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  return (
+    <canvas
+      ref={canvasRef}
+      className="w-full rounded-md bg-muted text-primary"
+      style={{ height: HEIGHT }}
+    />
+  );
+}
@@ -10,22 +10,25 @@ import {
 import { Input } from "@/components/ui/input";
 import { AudioSourceSelector } from "./AudioSourceSelector";
 import { RecordingStatus } from "./RecordingStatus";
+import { LevelMeter } from "./LevelMeter";
+import { LiveWaveform } from "./LiveWaveform";
 import { useRecordingStore } from "@/stores/recordingStore";
 import { useMeetingsStore } from "@/stores/meetingsStore";
-import { Circle, Square } from "lucide-react";
+import { describeQualityWarning } from "../history/QualitySummary";
+import { AlertTriangle, Circle, Pause, Play, Square } from "lucide-react";
 import { formatDuration } from "@/lib/utils";
 import { listen } from "@tauri-apps/api/event";
 import {
   startStreamingTranscription,
   stopStreamingTranscription,
   saveTranscription,
+  type Meeting,
+  type QualityWarningEvent,
+  type TranscriptionSegment,
 } from "@/lib/tauri-commands";
 
-interface TranscriptionSegment {
-  start: number;
-  end: number;
-  text: string;
-}
+// Avvisi di qualità mostrati durante la registrazione, i più recenti
+const MAX_QUALITY_WARNINGS = 5;
 
 export function RecordingControls() {
   const [meetingTitle, setMeetingTitle] = useState("");
@@ -33,20 +36,38 @@ export function RecordingControls() {
     [],
   );
   const [enableLiveTranscription, setEnableLiveTranscription] = useState(false);
+  const [qualityWarnings, setQualityWarnings] = useState<
+    QualityWarningEvent[]
+  >([]);
 
-  const { isRecording, elapsedSeconds, start, stop, tick, reset } =
-    useRecordingStore();
-  const { create, update } = useMeetingsStore();
+  const {
+    isRecording,
+    isPaused,
+    isArmed,
+    currentMeetingId,
+    elapsedSeconds,
+    start,
+    stop,
+    pause,
+    resume,
+    arm,
+    disarm,
+    attach,
+    tick,
+    reset,
+    loadDevices,
+  } = useRecordingStore();
+  const { loadMeetings } = useMeetingsStore();
 
   useEffect(() => {
-    if (!isRecording) return;
+    if (!isRecording || isPaused) return;
 
     const interval = setInterval(() => {
       tick();
     }, 1000);
 
     return () => clearInterval(interval);
-  }, [isRecording, tick]);
+  }, [isRecording, isPaused, tick]);
 
   // Listen for transcription updates
   useEffect(() => {
@@ -68,6 +89,33 @@ export function RecordingControls() {
     };
   }, []);
 
+  // Problemi di qualità segnalati dal backend mentre si registra
+  useEffect(() => {
+    const unlisten = listen<QualityWarningEvent>(
+      "recording-quality-warning",
+      (event) => {
+        setQualityWarnings((prev) =>
+          [...prev, event.payload].slice(-MAX_QUALITY_WARNINGS),
+        );
+      },
+    );
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, []);
+
+  // Aggiorna l'elenco quando un dispositivo viene collegato o scollegato
+  useEffect(() => {
+    const unlisten = listen("audio-device-changed", () => {
+      loadDevices();
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [loadDevices]);
+
   const handleStart = async () => {
     if (!meetingTitle.trim()) {
       alert("Inserisci un titolo per la riunione");
@@ -75,18 +123,16 @@ export function RecordingControls() {
     }
 
     try {
-      await start();
-      const meeting = await create(meetingTitle);
+      setQualityWarnings([]);
+      const meeting = await start(meetingTitle);
       console.log("Registrazione avviata:", meeting.id);
 
       // Start live transcription if enabled
       if (enableLiveTranscription) {
-        const meetings = useMeetingsStore.getState().meetings;
-        const latestMeeting = meetings[0];
-        if (latestMeeting?.audio_path) {
+        if (meeting.audio_path) {
           try {
             await startStreamingTranscription(
-              latestMeeting.audio_path,
+              meeting.audio_path,
               "base",
               "it",
             );
@@ -104,33 +150,95 @@ export function RecordingControls() {
     }
   };
 
-  const handleStop = async () => {
+  const handleToggleArmed = async (armed: boolean) => {
     try {
-      // Stop streaming transcription
-      if (enableLiveTranscription) {
-        await stopStreamingTranscription();
+      if (armed) {
+        await arm();
+      } else {
+        await disarm();
+      }
+    } catch (e) {
+      console.error("Errore pre-roll:", e);
+      alert(`Impossibile armare la registrazione: ${e}`);
+    }
+  };
+
+  const handleTogglePause = async () => {
+    try {
+      if (isPaused) {
+        await resume();
+      } else {
+        await pause();
       }
+    } catch (e) {
+      console.error("Errore pausa registrazione:", e);
+      alert("Errore durante la pausa della registrazione");
+    }
+  };
 
-      const audioPath = await stop();
+  // Salva la trascrizione live e riporta il form allo stato iniziale
+  const finishRecording = async (
+    meetingId: string | null,
+    audioPath: string,
+  ) => {
+    if (meetingId && liveTranscript.length > 0) {
+      const fullText = liveTranscript.map((s) => s.text).join(" ");
+      await saveTranscription(meetingId, fullText, "it", liveTranscript);
+      console.log("Trascrizione live salvata:", liveTranscript.length, "segmenti");
+    }
 
-      const meetings = useMeetingsStore.getState().meetings;
-      const latestMeeting = meetings[0];
+    await loadMeetings();
 
-      if (latestMeeting) {
-        await update(latestMeeting.id, undefined, elapsedSeconds, "recorded", audioPath);
+    reset();
+    setMeetingTitle("");
+    setLiveTranscript([]);
+    console.log("Registrazione salvata:", audioPath);
+  };
 
-        // Save live transcript if available
-        if (liveTranscript.length > 0) {
-          const fullText = liveTranscript.map((s) => s.text).join(" ");
-          await saveTranscription(latestMeeting.id, fullText, "it");
-          console.log("Trascrizione live salvata:", liveTranscript.length, "segmenti");
+  // Le registrazioni programmate partono dal backend
+  useEffect(() => {
+    const unlisten = listen<Meeting>("scheduled-recording-started", (event) => {
+      attach(event.payload);
+      setMeetingTitle(event.payload.title);
+      setLiveTranscript([]);
+      setQualityWarnings([]);
+    });
+
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  }, [attach]);
+
+  // Il backend ferma da solo la registrazione dopo un silenzio prolungato
+  // o alla fine di una registrazione programmata
+  useEffect(() => {
+    const unlisten = listen<string>("recording-auto-stopped", async (event) => {
+      try {
+        if (enableLiveTranscription) {
+          await stopStreamingTranscription();
         }
+        await finishRecording(currentMeetingId, event.payload);
+      } catch (e) {
+        console.error("Errore stop automatico:", e);
       }
+    });
 
-      reset();
-      setMeetingTitle("");
-      setLiveTranscript([]);
-      console.log("Registrazione salvata:", audioPath);
+    return () => {
+      unlisten.then((fn) => fn());
+    };
+  });
+
+  const handleStop = async () => {
+    try {
+      // Stop streaming transcription
+      if (enableLiveTranscription) {
+        await stopStreamingTranscription();
+      }
+
+      const meetingId = currentMeetingId;
+      const audioPath = await stop();
+
+      await finishRecording(meetingId, audioPath);
     } catch (e) {
       console.error("Errore stop registrazione:", e);
       alert("Errore durante l'arresto della registrazione");
@@ -173,6 +281,24 @@ export function RecordingControls() {
               />
             </div>
 
+            <div className="flex items-center justify-between p-4 rounded-lg border">
+              <div>
+                <p className="font-medium">Registrazione Armata</p>
+                <p className="text-sm text-muted-foreground">
+                  I microfoni restano in ascolto e i secondi prima dell'avvio
+                  non vanno persi
+                </p>
+              </div>
+              <input
+                type="checkbox"
+                className="h-5 w-5"
+                checked={isArmed}
+                onChange={(e) => handleToggleArmed(e.target.checked)}
+              />
+            </div>
+
+            {isArmed && <LevelMeter />}
+
             <Button
               onClick={handleStart}
               className="w-full"
@@ -188,8 +314,27 @@ export function RecordingControls() {
             <RecordingStatus
               title={meetingTitle}
               duration={formatDuration(elapsedSeconds)}
+              paused={isPaused}
             />
 
+            <LiveWaveform />
+
+            <LevelMeter />
+
+            {qualityWarnings.length > 0 && (
+              <div className="space-y-1 rounded-lg border border-yellow-500/50 bg-yellow-500/10 p-3">
+                {qualityWarnings.map((w, i) => (
+                  <p key={i} className="flex items-center gap-2 text-sm">
+                    <AlertTriangle className="h-4 w-4 shrink-0 text-yellow-600" />
+                    {qualityWarnings.some((q) => q.track > 0)
+                      ? `Traccia ${w.track + 1}: `
+                      : ""}
+                    {describeQualityWarning(w.warning)}
+                  </p>
+                ))}
+              </div>
+            )}
+
             {enableLiveTranscription && (
               <div className="space-y-2">
                 <h3 className="text-sm font-medium">Trascrizione Live</h3>
@@ -207,15 +352,35 @@ export function RecordingControls() {
               </div>
             )}
 
-            <Button
-              onClick={handleStop}
-              variant="destructive"
-              className="w-full"
-              size="lg"
-            >
-              <Square className="mr-2 h-5 w-5" />
-              Ferma Registrazione
-            </Button>
+            <div className="flex gap-2">
+              <Button
+                onClick={handleTogglePause}
+                variant="outline"
+                className="flex-1"
+                size="lg"
+              >
+                {isPaused ? (
+                  <>
+                    <Play className="mr-2 h-5 w-5" />
+                    Riprendi
+                  </>
+                ) : (
+                  <>
+                    <Pause className="mr-2 h-5 w-5" />
+                    Pausa
+                  </>
+                )}
+              </Button>
+              <Button
+                onClick={handleStop}
+                variant="destructive"
+                className="flex-1"
+                size="lg"
+              >
+                <Square className="mr-2 h-5 w-5" />
+                Ferma Registrazione
+              </Button>
+            </div>
           </>
         )}
       </CardContent>
@@ -3,13 +3,24 @@ import { Circle } from "lucide-react";
 interface RecordingStatusProps {
   title: string;
   duration: string;
+  paused?: boolean;
 }
 
-export function RecordingStatus({ title, duration }: RecordingStatusProps) {
+export function RecordingStatus({
+  title,
+  duration,
+  paused = false,
+}: RecordingStatusProps) {
   return (
     <div className="flex flex-col items-center justify-center py-8 space-y-4">
       <div className="relative">
-        <Circle className="h-20 w-20 text-destructive fill-destructive animate-pulse" />
+        <Circle
+          className={
+            paused
+              ? "h-20 w-20 text-muted-foreground fill-muted-foreground"
+              : "h-20 w-20 text-destructive fill-destructive animate-pulse"
+          }
+        />
         <div className="absolute inset-0 flex items-center justify-center">
           <Circle className="h-16 w-16 text-background fill-background" />
         </div>
@@ -18,7 +29,9 @@ export function RecordingStatus({ title, duration }: RecordingStatusProps) {
       <div className="text-center space-y-2">
         <h3 className="text-2xl font-bold">{title}</h3>
         <p className="text-3xl font-mono tabular-nums">{duration}</p>
-        <p className="text-sm text-muted-foreground">Registrazione in corso...</p>
+        <p className="text-sm text-muted-foreground">
+          {paused ? "Registrazione in pausa" : "Registrazione in corso..."}
+        </p>
       </div>
     </div>
   );
@@ -0,0 +1,230 @@
+import { useCallback, useEffect, useState } from "react";
+import { Button } from "@/components/ui/button";
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from "@/components/ui/card";
+import { Input } from "@/components/ui/input";
+import { Select } from "@/components/ui/select";
+import { useRecordingStore } from "@/stores/recordingStore";
+import { formatDate } from "@/lib/utils";
+import { listen } from "@tauri-apps/api/event";
+import {
+  cancelScheduledRecording,
+  createScheduledRecording,
+  getScheduledRecordings,
+  type ScheduledRecording,
+} from "@/lib/tauri-commands";
+import { CalendarClock, Loader2, X } from "lucide-react";
+
+const durationOptions = [
+  { value: "0", label: "Nessun limite" },
+  { value: "30", label: "30 minuti" },
+  { value: "60", label: "1 ora" },
+  { value: "90", label: "1 ora e 30" },
+  { value: "120", label: "2 ore" },
+  { value: "240", label: "4 ore" },
+];
+
+const silenceOptions = [
+  { value: "0", label: "Mai" },
+  { value: "2", label: "Dopo 2 minuti" },
+  { value: "5", label: "Dopo 5 minuti" },
+  { value: "10", label: "Dopo 10 minuti" },
+  { value: "15", label: "Dopo 15 minuti" },
+];
+
+// I campi datetime-local sono in ora locale, il backend vuole RFC 3339
+function toIso(value: string): string | undefined {
+  return value ? new Date(value).toISOString() : undefined;
+}
+
+function describeStop(schedule: ScheduledRecording): string {
+  const rules = [];
+  if (schedule.end_at) rules.push(`fine alle ${formatDate(schedule.end_at)}`);
+  if (schedule.max_duration_minutes)
+    rules.push(`massimo ${schedule.max_duration_minutes} min`);
+  if (schedule.silence_stop_minutes)
+    rules.push(`stop dopo ${schedule.silence_stop_minutes} min di silenzio`);
+  return rules.join(" • ");
+}
+
+export function ScheduledRecordings() {
+  const [schedules, setSchedules] = useState<ScheduledRecording[]>([]);
+  const [title, setTitle] = useState("");
+  const [startAt, setStartAt] = useState("");
+  const [endAt, setEndAt] = useState("");
+  const [maxDuration, setMaxDuration] = useState("0");
+  const [silenceStop, setSilenceStop] = useState("0");
+  const [isSaving, setIsSaving] = useState(false);
+
+  const { selectedDeviceId, selectedLoopbackId } = useRecordingStore();
+
+  const loadSchedules = useCallback(async () => {
+    try {
+      setSchedules(await getScheduledRecordings());
+    } catch (e) {
+      console.error("Errore caricamento registrazioni programmate:", e);
+    }
+  }, []);
+
+  useEffect(() => {
+    loadSchedules();
+  }, [loadSchedules]);
+
+  // Lo stato cambia quando il backend avvia o ferma una registrazione
+  useEffect(() => {
+    const unlistenStart = listen("scheduled-recording-started", loadSchedules);
+    const unlistenStop = listen("recording-auto-stopped", loadSchedules);
+
+    return () => {
+      unlistenStart.then((fn) => fn());
+      unlistenStop.then((fn) => fn());
+    };
+  }, [loadSchedules]);
+
+  const handleCreate = async () => {
+    if (!startAt) {
+      alert("Indica l'orario di inizio");
+      return;
+    }
+
+    setIsSaving(true);
+    try {
+      const deviceIds = [selectedDeviceId, selectedLoopbackId].filter(
+        (id): id is string => !!id,
+      );
+      await createScheduledRecording(title, toIso(startAt)!, {
+        deviceIds,
+        endAt: toIso(endAt),
+        maxDurationMinutes: Number(maxDuration) || undefined,
+        silenceStopMinutes: Number(silenceStop) || undefined,
+      });
+      setTitle("");
+      setStartAt("");
+      setEndAt("");
+      await loadSchedules();
+    } catch (e) {
+      alert(`Impossibile programmare la registrazione: ${e}`);
+    } finally {
+      setIsSaving(false);
+    }
+  };
+
+  const handleCancel = async (id: string) => {
+    try {
+      await cancelScheduledRecording(id);
+      await loadSchedules();
+    } catch (e) {
+      alert(`Errore durante l'annullamento: ${e}`);
+    }
+  };
+
+  return (
+    <Card>
+      <CardHeader>
+        <CardTitle>Registrazioni Programmate</CardTitle>
+        <CardDescription>
+          Avvia e ferma una registrazione a orari prestabiliti con i
+          dispositivi selezionati sopra
+        </CardDescription>
+      </CardHeader>
+      <CardContent className="space-y-4">
+        <Input
+          placeholder="Titolo (facoltativo)"
+          value={title}
+          onChange={(e) => setTitle(e.target.value)}
+        />
+
+        <div className="grid grid-cols-2 gap-4">
+          <div className="space-y-2">
+            <label className="text-sm font-medium">Inizio</label>
+            <Input
+              type="datetime-local"
+              value={startAt}
+              onChange={(e) => setStartAt(e.target.value)}
+            />
+          </div>
+          <div className="space-y-2">
+            <label className="text-sm font-medium">Fine</label>
+            <Input
+              type="datetime-local"
+              value={endAt}
+              onChange={(e) => setEndAt(e.target.value)}
+            />
+          </div>
+          <div className="space-y-2">
+            <label className="text-sm font-medium">Durata massima</label>
+            <Select
+              options={durationOptions}
+              value={maxDuration}
+              onChange={(e) => setMaxDuration(e.target.value)}
+            />
+          </div>
+          <div className="space-y-2">
+            <label className="text-sm font-medium">Stop per silenzio</label>
+            <Select
+              options={silenceOptions}
+              value={silenceStop}
+              onChange={(e) => setSilenceStop(e.target.value)}
+            />
+          </div>
+        </div>
+
+        <Button
+          onClick={handleCreate}
+          className="w-full"
+          variant="outline"
+          disabled={isSaving || !startAt}
+        >
+          {isSaving ? (
+            <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+          ) : (
+            <CalendarClock className="mr-2 h-4 w-4" />
+          )}
+          Programma Registrazione
+        </Button>
+
+        {schedules.length > 0 && (
+          <div className="space-y-2">
+            {schedules.map((schedule) => (
+              <div
+                key={schedule.id}
+                className="flex items-center gap-3 p-3 rounded-lg border text-sm"
+              >
+                <div className="flex-1">
+                  <p className="font-medium">
+                    {schedule.title || "Riunione"}
+                    {schedule.status === "running" && (
+                      <span className="ml-2 text-xs px-2 py-0.5 rounded-full bg-red-500/10 text-red-600">
+                        in corso
+                      </span>
+                    )}
+                  </p>
+                  <p className="text-xs text-muted-foreground">
+                    {formatDate(schedule.start_at)} • {describeStop(schedule)}
+                  </p>
+                </div>
+                <Button
+                  variant="ghost"
+                  size="sm"
+                  onClick={() => handleCancel(schedule.id)}
+                  title={
+                    schedule.status === "running"
+                      ? "Ferma la registrazione"
+                      : "Annulla"
+                  }
+                >
+                  <X className="h-4 w-4" />
+                </Button>
+              </div>
+            ))}
+          </div>
+        )}
+      </CardContent>
+    </Card>
+  );
+}
@@ -9,18 +9,41 @@ import {
 } from "@/components/ui/card";
 import { Select } from "@/components/ui/select";
 import { Button } from "@/components/ui/button";
-import { getAvailableModels } from "@/lib/tauri-commands";
-import type { WhisperModel } from "@/lib/tauri-commands";
-import { Save } from "lucide-react";
+import { Input } from "@/components/ui/input";
+import {
+  archiveRecordings,
+  checkTranscriptionSidecar,
+  getAvailableModels,
+} from "@/lib/tauri-commands";
+import type { StorageCodec, WhisperModel } from "@/lib/tauri-commands";
+import { Archive, Loader2, Save, SearchCheck } from "lucide-react";
 
 export function AudioSettings() {
   const { appSettings, loadSettings, updateAppSettings } = useSettingsStore();
   const [models, setModels] = useState<WhisperModel[]>([]);
+  const [isArchiving, setIsArchiving] = useState(false);
+  const [isChecking, setIsChecking] = useState(false);
+  const [sidecarStatus, setSidecarStatus] = useState<{
+    ok: boolean;
+    message: string;
+  } | null>(null);
   const [formData, setFormData] = useState({
     whisper_model: "base",
     default_language: "it",
     auto_transcribe: false,
     auto_generate_report: false,
+    keep_master_copy: false,
+    storage_codec: "wav" as StorageCodec,
+    auto_stop_silence_minutes: 0,
+    pre_roll_seconds: 10,
+    high_pass_enabled: false,
+    high_pass_cutoff_hz: 80,
+    noise_suppression_enabled: false,
+    noise_reduction_db: 12,
+    agc_enabled: false,
+    agc_target_db: -20,
+    sidecar_python_path: "",
+    sidecar_script_path: "",
   });
 
   useEffect(() => {
@@ -35,6 +58,19 @@ export function AudioSettings() {
         default_language: appSettings.default_language || "it",
         auto_transcribe: appSettings.auto_transcribe,
         auto_generate_report: appSettings.auto_generate_report,
+        keep_master_copy: appSettings.keep_master_copy,
+        storage_codec: appSettings.storage_codec ?? "wav",
+        auto_stop_silence_minutes: appSettings.auto_stop_silence_minutes ?? 0,
+        pre_roll_seconds: appSettings.pre_roll_seconds ?? 10,
+        high_pass_enabled: appSettings.high_pass_enabled ?? false,
+        high_pass_cutoff_hz: appSettings.high_pass_cutoff_hz ?? 80,
+        noise_suppression_enabled:
+          appSettings.noise_suppression_enabled ?? false,
+        noise_reduction_db: appSettings.noise_reduction_db ?? 12,
+        agc_enabled: appSettings.agc_enabled ?? false,
+        agc_target_db: appSettings.agc_target_db ?? -20,
+        sidecar_python_path: appSettings.sidecar_python_path ?? "",
+        sidecar_script_path: appSettings.sidecar_script_path ?? "",
       });
     }
   }, [appSettings]);
@@ -51,6 +87,18 @@ export function AudioSettings() {
         default_language: formData.default_language || null,
         auto_transcribe: formData.auto_transcribe,
         auto_generate_report: formData.auto_generate_report,
+        keep_master_copy: formData.keep_master_copy,
+        storage_codec: formData.storage_codec,
+        auto_stop_silence_minutes: formData.auto_stop_silence_minutes,
+        pre_roll_seconds: formData.pre_roll_seconds,
+        high_pass_enabled: formData.high_pass_enabled,
+        high_pass_cutoff_hz: formData.high_pass_cutoff_hz,
+        noise_suppression_enabled: formData.noise_suppression_enabled,
+        noise_reduction_db: formData.noise_reduction_db,
+        agc_enabled: formData.agc_enabled,
+        agc_target_db: formData.agc_target_db,
+        sidecar_python_path: formData.sidecar_python_path || null,
+        sidecar_script_path: formData.sidecar_script_path || null,
       });
       alert("Impostazioni salvate!");
     } catch (e) {
@@ -58,6 +106,78 @@ export function AudioSettings() {
     }
   };
 
+  const handleArchive = async () => {
+    setIsArchiving(true);
+    try {
+      const count = await archiveRecordings(formData.storage_codec);
+      alert(`${count} registrazioni convertite`);
+    } catch (e) {
+      alert("Errore durante l'archiviazione");
+    } finally {
+      setIsArchiving(false);
+    }
+  };
+
+  // Verifica i percorsi salvati, non quelli ancora da salvare
+  const handleCheckSidecar = async () => {
+    setIsChecking(true);
+    try {
+      const launch = await checkTranscriptionSidecar();
+      setSidecarStatus({
+        ok: true,
+        message: [launch.program, launch.script, `(${launch.source})`]
+          .filter(Boolean)
+          .join(" "),
+      });
+    } catch (e) {
+      setSidecarStatus({ ok: false, message: String(e) });
+    } finally {
+      setIsChecking(false);
+    }
+  };
+
+  const storageOptions = [
+    { value: "wav", label: "WAV (non compresso)" },
+    { value: "flac", label: "FLAC (senza perdita, circa metà spazio)" },
+    { value: "opus", label: "Opus (ottimizzato per la voce, minimo spazio)" },
+  ];
+
+  const preRollOptions = [
+    { value: "0", label: "Disattivato" },
+    { value: "5", label: "5 secondi" },
+    { value: "10", label: "10 secondi" },
+    { value: "20", label: "20 secondi" },
+    { value: "30", label: "30 secondi" },
+    { value: "60", label: "60 secondi" },
+  ];
+
+  const autoStopOptions = [
+    { value: "0", label: "Mai" },
+    { value: "5", label: "Dopo 5 minuti di silenzio" },
+    { value: "10", label: "Dopo 10 minuti di silenzio" },
+    { value: "15", label: "Dopo 15 minuti di silenzio" },
+    { value: "30", label: "Dopo 30 minuti di silenzio" },
+  ];
+
+  const highPassOptions = [
+    { value: "60", label: "60 Hz" },
+    { value: "80", label: "80 Hz" },
+    { value: "100", label: "100 Hz" },
+    { value: "150", label: "150 Hz" },
+  ];
+
+  const noiseReductionOptions = [
+    { value: "6", label: "Leggera (6 dB)" },
+    { value: "12", label: "Media (12 dB)" },
+    { value: "18", label: "Forte (18 dB)" },
+  ];
+
+  const agcTargetOptions = [
+    { value: "-26", label: "Basso (-26 dBFS)" },
+    { value: "-20", label: "Normale (-20 dBFS)" },
+    { value: "-16", label: "Alto (-16 dBFS)" },
+  ];
+
   const languageOptions = [
     { value: "it", label: "Italiano" },
     { value: "en", label: "Inglese" },
@@ -146,6 +266,237 @@ export function AudioSettings() {
           />
         </div>
 
+        {/* Keep Master Copy */}
+        <div className="flex items-center justify-between p-4 rounded-lg border">
+          <div>
+            <p className="font-medium">Copia Master</p>
+            <p className="text-sm text-muted-foreground">
+              Conserva anche una copia alla qualità originale del dispositivo
+              (le registrazioni per la trascrizione sono a 16 kHz mono)
+            </p>
+          </div>
+          <input
+            type="checkbox"
+            className="h-5 w-5"
+            checked={formData.keep_master_copy}
+            onChange={(e) =>
+              setFormData({ ...formData, keep_master_copy: e.target.checked })
+            }
+          />
+        </div>
+
+        {/* Speech Enhancement */}
+        <div className="space-y-3 p-4 rounded-lg border">
+          <div>
+            <p className="font-medium">Miglioramento del Parlato</p>
+            <p className="text-sm text-muted-foreground">
+              Filtri applicati durante la registrazione, prima del salvataggio.
+              Utili con microfoni economici e sale riunioni rumorose
+            </p>
+          </div>
+
+          <div className="flex items-center justify-between gap-4">
+            <label className="flex items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                className="h-4 w-4"
+                checked={formData.high_pass_enabled}
+                onChange={(e) =>
+                  setFormData({
+                    ...formData,
+                    high_pass_enabled: e.target.checked,
+                  })
+                }
+              />
+              Filtro passa-alto (rimbombo, vibrazioni)
+            </label>
+            <Select
+              className="w-40"
+              options={highPassOptions}
+              value={String(formData.high_pass_cutoff_hz)}
+              disabled={!formData.high_pass_enabled}
+              onChange={(e) =>
+                setFormData({
+                  ...formData,
+                  high_pass_cutoff_hz: Number(e.target.value),
+                })
+              }
+            />
+          </div>
+
+          <div className="flex items-center justify-between gap-4">
+            <label className="flex items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                className="h-4 w-4"
+                checked={formData.noise_suppression_enabled}
+                onChange={(e) =>
+                  setFormData({
+                    ...formData,
+                    noise_suppression_enabled: e.target.checked,
+                  })
+                }
+              />
+              Riduzione del rumore (ventole, condizionatori)
+            </label>
+            <Select
+              className="w-40"
+              options={noiseReductionOptions}
+              value={String(formData.noise_reduction_db)}
+              disabled={!formData.noise_suppression_enabled}
+              onChange={(e) =>
+                setFormData({
+                  ...formData,
+                  noise_reduction_db: Number(e.target.value),
+                })
+              }
+            />
+          </div>
+
+          <div className="flex items-center justify-between gap-4">
+            <label className="flex items-center gap-2 text-sm">
+              <input
+                type="checkbox"
+                className="h-4 w-4"
+                checked={formData.agc_enabled}
+                onChange={(e) =>
+                  setFormData({ ...formData, agc_enabled: e.target.checked })
+                }
+              />
+              Controllo automatico del volume
+            </label>
+            <Select
+              className="w-40"
+              options={agcTargetOptions}
+              value={String(formData.agc_target_db)}
+              disabled={!formData.agc_enabled}
+              onChange={(e) =>
+                setFormData({
+                  ...formData,
+                  agc_target_db: Number(e.target.value),
+                })
+              }
+            />
+          </div>
+        </div>
+
+        {/* Auto Stop */}
+        <div className="space-y-2">
+          <label className="text-sm font-medium">Stop Automatico</label>
+          <Select
+            options={autoStopOptions}
+            value={String(formData.auto_stop_silence_minutes)}
+            onChange={(e) =>
+              setFormData({
+                ...formData,
+                auto_stop_silence_minutes: Number(e.target.value),
+              })
+            }
+          />
+          <p className="text-xs text-muted-foreground">
+            Ferma la registrazione quando nessuno parla per il tempo indicato
+          </p>
+        </div>
+
+        {/* Pre-roll */}
+        <div className="space-y-2">
+          <label className="text-sm font-medium">Pre-roll</label>
+          <Select
+            options={preRollOptions}
+            value={String(formData.pre_roll_seconds)}
+            onChange={(e) =>
+              setFormData({
+                ...formData,
+                pre_roll_seconds: Number(e.target.value),
+              })
+            }
+          />
+          <p className="text-xs text-muted-foreground">
+            Con la registrazione armata i microfoni restano in ascolto e gli
+            ultimi secondi prima dell'avvio finiscono all'inizio del file
+          </p>
+        </div>
+
+        {/* Storage Codec */}
+        <div className="space-y-2">
+          <label className="text-sm font-medium">Formato di Archiviazione</label>
+          <Select
+            options={storageOptions}
+            value={formData.storage_codec}
+            onChange={(e) =>
+              setFormData({
+                ...formData,
+                storage_codec: e.target.value as StorageCodec,
+              })
+            }
+          />
+          <p className="text-xs text-muted-foreground">
+            Le registrazioni vengono convertite al termine; la trascrizione
+            funziona con tutti i formati
+          </p>
+          {formData.storage_codec !== "wav" && (
+            <Button
+              variant="outline"
+              size="sm"
+              onClick={handleArchive}
+              disabled={isArchiving}
+            >
+              {isArchiving ? (
+                <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+              ) : (
+                <Archive className="mr-2 h-4 w-4" />
+              )}
+              Converti le registrazioni esistenti
+            </Button>
+          )}
+        </div>
+
+        {/* Sidecar */}
+        <div className="space-y-2">
+          <label className="text-sm font-medium">Sidecar di Trascrizione</label>
+          <Input
+            placeholder="Interprete Python (automatico)"
+            value={formData.sidecar_python_path}
+            onChange={(e) =>
+              setFormData({ ...formData, sidecar_python_path: e.target.value })
+            }
+          />
+          <Input
+            placeholder="Script main.py (automatico)"
+            value={formData.sidecar_script_path}
+            onChange={(e) =>
+              setFormData({ ...formData, sidecar_script_path: e.target.value })
+            }
+          />
+          <p className="text-xs text-muted-foreground">
+            Se vuoti vengono cercati nelle variabili MEET_TRANSCRIBER_PYTHON e
+            MEET_TRANSCRIBER_SIDECAR, fra le risorse dell'app e nella cartella
+            python dei sorgenti
+          </p>
+          <Button
+            variant="outline"
+            size="sm"
+            onClick={handleCheckSidecar}
+            disabled={isChecking}
+          >
+            {isChecking ? (
+              <Loader2 className="mr-2 h-4 w-4 animate-spin" />
+            ) : (
+              <SearchCheck className="mr-2 h-4 w-4" />
+            )}
+            Verifica
+          </Button>
+          {sidecarStatus && (
+            <p
+              className={`text-xs whitespace-pre-wrap break-all ${
+                sidecarStatus.ok ? "text-muted-foreground" : "text-destructive"
+              }`}
+            >
+              {sidecarStatus.message}
+            </p>
+          )}
+        </div>
+
         <Button onClick={handleSave} className="w-full">
           <Save className="mr-2 h-4 w-4" />
           Salva Impostazioni
@@ -6,6 +6,32 @@ export interface AudioDevice {
   name: string;
   is_input: boolean;
   is_default: boolean;
+  is_loopback: boolean;
+}
+
+export interface InputConfig {
+  sample_rate: number;
+  channels: number;
+}
+
+export interface SupportedInputConfig {
+  channels: number;
+  min_sample_rate: number;
+  max_sample_rate: number;
+  sample_format: string;
+}
+
+export interface DeviceCapabilities {
+  device_id: string;
+  default_config: InputConfig | null;
+  supported_configs: SupportedInputConfig[];
+}
+
+export interface TrackLevel {
+  track: number;
+  rms: number;
+  peak: number;
+  clipping: boolean;
 }
 
 export interface Meeting {
@@ -27,16 +53,178 @@ export interface MeetingReport {
   created_at: string;
 }
 
+export interface MeetingEvent {
+  kind: string;
+  offset_seconds: number | null;
+  details: Record<string, unknown> | null;
+  created_at: string;
+}
+
+export interface SpeechSegment {
+  start_seconds: number;
+  end_seconds: number;
+}
+
+export interface SilenceMap {
+  total_seconds: number;
+  speech: SpeechSegment[];
+}
+
+export interface WaveformSlice {
+  start_seconds: number;
+  end_seconds: number;
+  duration_seconds: number;
+  seconds_per_bucket: number;
+  // Coppie [min, max] fra -1 e 1, una per bucket
+  peaks: [number, number][];
+}
+
+// Payload dell'evento "audio-waveform" durante la registrazione
+export interface WaveformUpdate {
+  offset_seconds: number;
+  seconds_per_peak: number;
+  peaks: [number, number][];
+}
+
+// Segmento di una registrazione divisa in più file
+export interface AudioSegment {
+  index: number;
+  path: string;
+  start_seconds: number;
+  duration_seconds: number;
+}
+
+export type QualityIssue =
+  | "clipping"
+  | "low_signal"
+  | "dc_offset"
+  | "dropout"
+  | "low_snr";
+
+export interface QualityWarning {
+  issue: QualityIssue;
+  offset_seconds: number;
+  // Valore che ha fatto scattare l'avviso (percentuale, dB o secondi)
+  value: number;
+}
+
+// Payload dell'evento "recording-quality-warning"
+export interface QualityWarningEvent {
+  type: "quality_warning";
+  track: number;
+  warning: QualityWarning;
+}
+
+export interface TrackQuality {
+  track: number;
+  device_id: string | null;
+  duration_seconds: number;
+  peak_db: number;
+  median_db: number | null;
+  clipped_samples: number;
+  clipping_percent: number;
+  dc_offset: number;
+  dropouts: number;
+  dropout_seconds: number;
+  longest_dropout_seconds: number;
+  noise_floor_db: number | null;
+  snr_db: number | null;
+  issues: QualityIssue[];
+}
+
+export interface QualityReport {
+  tracks: TrackQuality[];
+}
+
+// Registrazione programmata; gli orari sono stringhe ISO in UTC
+export interface ScheduledRecording {
+  id: string;
+  title: string;
+  device_ids: string[];
+  start_at: string;
+  end_at: string | null;
+  max_duration_minutes: number | null;
+  silence_stop_minutes: number | null;
+  status: "pending" | "running" | "completed" | "missed" | "failed";
+  meeting_id: string | null;
+  started_at: string | null;
+  error: string | null;
+  created_at: string;
+}
+
 export interface MeetingWithTranscript {
   meeting: Meeting;
   transcript: string | null;
   report: MeetingReport | null;
+  events: MeetingEvent[];
+  silence_map: SilenceMap | null;
+  audio_segments: AudioSegment[];
+  quality_report: QualityReport | null;
+}
+
+export interface TranscriptionSegment {
+  start: number;
+  end: number;
+  text: string;
 }
 
 export interface TranscriptionResult {
   text: string;
   language: string | null;
-  segments: { start: number; end: number; text: string }[];
+  segments: TranscriptionSegment[];
+}
+
+// Payload dell'evento "transcription-progress"; i segmenti sono quelli
+// trascritti dall'aggiornamento precedente
+export interface TranscriptionProgressEvent {
+  job_id: string;
+  meeting_id: string;
+  percent: number;
+  processed_seconds: number;
+  total_seconds: number;
+  eta_seconds: number | null;
+  segments: TranscriptionSegment[];
+}
+
+export type TranscriptionJobStatus = "completed" | "failed" | "cancelled";
+
+// Payload dell'evento "transcription-finished"
+export interface TranscriptionFinishedEvent {
+  job_id: string;
+  meeting_id: string;
+  status: TranscriptionJobStatus;
+  error: string | null;
+}
+
+// Job della coda di trascrizione; i job "pending" partono in ordine di
+// position, anche dopo un crash del sidecar (da next_attempt_at)
+export interface TranscriptionJob {
+  id: string;
+  meeting_id: string;
+  meeting_title: string | null;
+  model_size: string;
+  language: string | null;
+  status: "pending" | "running" | TranscriptionJobStatus;
+  position: number;
+  attempts: number;
+  next_attempt_at: string | null;
+  error: string | null;
+  created_at: string;
+  started_at: string | null;
+  finished_at: string | null;
+}
+
+export interface TimeRange {
+  start_seconds: number;
+  end_seconds: number;
+}
+
+export type RedactionMode = "silence" | "bleep";
+
+export interface TranscriptRedaction {
+  transcript_segments: number;
+  // Trascrizioni senza segmenti con i tempi, rimaste intatte
+  unredacted_transcriptions: number;
 }
 
 export interface LlmConfig {
@@ -59,8 +247,30 @@ export interface AppSettings {
   default_language: string | null;
   auto_transcribe: boolean;
   auto_generate_report: boolean;
+  keep_master_copy: boolean;
+  preferred_input_device?: string | null;
+  storage_codec: StorageCodec;
+  auto_stop_silence_minutes: number;
+  pre_roll_seconds: number;
+  high_pass_enabled: boolean;
+  high_pass_cutoff_hz: number;
+  noise_suppression_enabled: boolean;
+  noise_reduction_db: number;
+  agc_enabled: boolean;
+  agc_target_db: number;
+  sidecar_python_path?: string | null;
+  sidecar_script_path?: string | null;
+}
+
+// Come verrà avviato il sidecar di trascrizione
+export interface SidecarLaunch {
+  program: string;
+  script: string | null;
+  source: string;
 }
 
+export type StorageCodec = "wav" | "flac" | "opus";
+
 export interface WhisperModel {
   id: string;
   name: string;
@@ -73,8 +283,44 @@ export async function getAudioDevices(): Promise<AudioDevice[]> {
   return invoke("get_audio_devices");
 }
 
-export async function startRecording(deviceId?: string): Promise<string> {
-  return invoke("start_recording", { deviceId });
+export async function setPreferredAudioDevice(
+  deviceId: string | null,
+): Promise<void> {
+  return invoke("set_preferred_audio_device", { deviceId });
+}
+
+export async function getDeviceCapabilities(
+  deviceId: string | null,
+): Promise<DeviceCapabilities> {
+  return invoke("get_device_capabilities", { deviceId });
+}
+
+export type RecordingLayout = "mixed" | "multi_track";
+
+// Apre i dispositivi e conserva gli ultimi secondi di audio, che finiranno
+// all'inizio della prossima registrazione
+export async function armRecording(
+  deviceIds: string[],
+  streamConfigs?: Record<string, InputConfig>,
+): Promise<void> {
+  return invoke("arm_recording", { deviceIds, streamConfigs });
+}
+
+export async function disarmRecording(): Promise<void> {
+  return invoke("disarm_recording");
+}
+
+export async function isRecordingArmed(): Promise<boolean> {
+  return invoke("is_recording_armed");
+}
+
+export async function startRecording(
+  deviceIds: string[],
+  title?: string,
+  layout?: RecordingLayout,
+  streamConfigs?: Record<string, InputConfig>,
+): Promise<Meeting> {
+  return invoke("start_recording", { deviceIds, title, layout, streamConfigs });
 }
 
 export async function stopRecording(): Promise<string> {
@@ -85,6 +331,71 @@ export async function isRecording(): Promise<boolean> {
   return invoke("is_recording");
 }
 
+export async function pauseRecording(): Promise<void> {
+  return invoke("pause_recording");
+}
+
+export async function resumeRecording(): Promise<void> {
+  return invoke("resume_recording");
+}
+
+export async function isRecordingPaused(): Promise<boolean> {
+  return invoke("is_recording_paused");
+}
+
+export async function selectMediaFile(): Promise<string | null> {
+  return invoke("select_media_file");
+}
+
+export async function archiveRecordings(
+  codec?: StorageCodec,
+): Promise<number> {
+  return invoke("archive_recordings", { codec });
+}
+
+export async function enhanceMeetingAudio(meetingId: string): Promise<string> {
+  return invoke("enhance_meeting_audio", { meetingId });
+}
+
+export async function analyzeMeetingQuality(
+  meetingId: string,
+): Promise<QualityReport> {
+  return invoke("analyze_meeting_quality", { meetingId });
+}
+
+export async function getWaveform(
+  meetingId: string,
+  buckets: number,
+  start?: number,
+  end?: number,
+): Promise<WaveformSlice> {
+  return invoke("get_waveform", { meetingId, start, end, buckets });
+}
+
+// Restituisce null se l'utente annulla la scelta del file
+export async function exportMeetingClip(
+  meetingId: string,
+  startSeconds: number,
+  endSeconds: number,
+): Promise<string | null> {
+  return invoke("export_meeting_clip", { meetingId, startSeconds, endSeconds });
+}
+
+export async function redactMeetingAudio(
+  meetingId: string,
+  ranges: TimeRange[],
+  mode: RedactionMode,
+): Promise<TranscriptRedaction> {
+  return invoke("redact_meeting_audio", { meetingId, ranges, mode });
+}
+
+export async function importMedia(
+  path: string,
+  title?: string,
+): Promise<Meeting> {
+  return invoke("import_media", { path, title });
+}
+
 // Meeting commands
 export async function createMeeting(
   title: string,
@@ -119,17 +430,79 @@ export async function saveTranscription(
   meetingId: string,
   content: string,
   language?: string,
+  segments?: TranscriptionSegment[],
 ): Promise<string> {
-  return invoke("save_transcription", { meetingId, content, language });
+  return invoke("save_transcription", {
+    meetingId,
+    content,
+    language,
+    segments,
+  });
+}
+
+// Scheduled recording commands
+export async function createScheduledRecording(
+  title: string,
+  startAt: string,
+  options: {
+    deviceIds?: string[];
+    endAt?: string;
+    maxDurationMinutes?: number;
+    silenceStopMinutes?: number;
+  },
+): Promise<ScheduledRecording> {
+  return invoke("create_scheduled_recording", {
+    title,
+    startAt,
+    deviceIds: options.deviceIds,
+    endAt: options.endAt,
+    maxDurationMinutes: options.maxDurationMinutes,
+    silenceStopMinutes: options.silenceStopMinutes,
+  });
+}
+
+export async function getScheduledRecordings(): Promise<ScheduledRecording[]> {
+  return invoke("get_scheduled_recordings");
+}
+
+export async function cancelScheduledRecording(id: string): Promise<void> {
+  return invoke("cancel_scheduled_recording", { id });
 }
 
 // Transcription commands
+// Mette in coda la trascrizione e restituisce l'id del job; il risultato
+// viene salvato dal backend ("transcription-finished" alla fine)
 export async function transcribeMeeting(
-  audioPath: string,
+  meetingId: string,
   modelSize?: string,
   language?: string,
-): Promise<TranscriptionResult> {
-  return invoke("transcribe_meeting", { audioPath, modelSize, language });
+): Promise<string> {
+  return invoke("transcribe_meeting", {
+    meetingId,
+    modelSize,
+    language,
+  });
+}
+
+// Toglie dalla coda un job in attesa o interrompe quello in corso
+export async function cancelTranscription(jobId: string): Promise<void> {
+  return invoke("cancel_transcription", { jobId });
+}
+
+// Job in coda e in corso, seguiti dagli ultimi terminati
+export async function getTranscriptionJobs(): Promise<TranscriptionJob[]> {
+  return invoke("get_transcription_jobs");
+}
+
+// I job in attesa non elencati restano in fondo alla coda
+export async function reorderTranscriptionJobs(
+  jobIds: string[],
+): Promise<void> {
+  return invoke("reorder_transcription_jobs", { jobIds });
+}
+
+export async function retryTranscriptionJob(jobId: string): Promise<void> {
+  return invoke("retry_transcription_job", { jobId });
 }
 
 export async function getAvailableModels(): Promise<WhisperModel[]> {
@@ -152,6 +525,11 @@ export async function stopStreamingTranscription(): Promise<void> {
   return invoke("stop_streaming_transcription_command");
 }
 
+// Fallisce con l'elenco dei percorsi provati se il sidecar non si trova
+export async function checkTranscriptionSidecar(): Promise<SidecarLaunch> {
+  return invoke("check_transcription_sidecar");
+}
+
 // LLM commands
 export async function generateMeetingReport(
   meetingId: string,
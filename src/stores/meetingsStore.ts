@@ -1,17 +1,24 @@
 import { create } from "zustand";
+import { listen } from "@tauri-apps/api/event";
 import type {
   Meeting,
   MeetingWithTranscript,
   ReportContent,
+  TranscriptionFinishedEvent,
+  TranscriptionJobStatus,
+  TranscriptionProgressEvent,
+  TranscriptionSegment,
 } from "@/lib/tauri-commands";
 import {
   getMeetings,
   getMeeting,
   createMeeting,
+  importMedia,
+  selectMediaFile,
   updateMeeting,
   deleteMeeting,
-  saveTranscription,
   transcribeMeeting,
+  cancelTranscription as cancelTranscriptionJob,
   generateMeetingReport,
 } from "@/lib/tauri-commands";
 
@@ -19,7 +26,12 @@ interface MeetingsState {
   meetings: Meeting[];
   currentMeeting: MeetingWithTranscript | null;
   isLoading: boolean;
+  isImporting: boolean;
   isTranscribing: boolean;
+  transcriptionJobId: string | null;
+  // Avanzamento della trascrizione in corso e segmenti ricevuti finora
+  transcriptionProgress: TranscriptionProgressEvent | null;
+  partialSegments: TranscriptionSegment[];
   isGeneratingReport: boolean;
   error: string | null;
 
@@ -27,6 +39,7 @@ interface MeetingsState {
   loadMeetings: () => Promise<void>;
   loadMeeting: (id: string) => Promise<void>;
   create: (title: string, audioPath?: string) => Promise<Meeting>;
+  importFile: () => Promise<Meeting | null>;
   update: (
     id: string,
     title?: string,
@@ -37,10 +50,10 @@ interface MeetingsState {
   remove: (id: string) => Promise<void>;
   transcribe: (
     meetingId: string,
-    audioPath: string,
     modelSize?: string,
     language?: string,
-  ) => Promise<string>;
+  ) => Promise<TranscriptionJobStatus>;
+  cancelTranscription: () => Promise<void>;
   generateReport: (
     meetingId: string,
     transcript: string,
@@ -52,7 +65,11 @@ export const useMeetingsStore = create<MeetingsState>((set, get) => ({
   meetings: [],
   currentMeeting: null,
   isLoading: false,
+  isImporting: false,
   isTranscribing: false,
+  transcriptionJobId: null,
+  transcriptionProgress: null,
+  partialSegments: [],
   isGeneratingReport: false,
   error: null,
 
@@ -90,6 +107,24 @@ export const useMeetingsStore = create<MeetingsState>((set, get) => ({
     }
   },
 
+  importFile: async () => {
+    const path = await selectMediaFile();
+    if (!path) return null;
+
+    set({ isImporting: true, error: null });
+    try {
+      const meeting = await importMedia(path);
+      set((state) => ({
+        meetings: [meeting, ...state.meetings],
+        isImporting: false,
+      }));
+      return meeting;
+    } catch (e) {
+      set({ error: String(e), isImporting: false });
+      throw e;
+    }
+  },
+
   update: async (
     id: string,
     title?: string,
@@ -124,27 +159,80 @@ export const useMeetingsStore = create<MeetingsState>((set, get) => ({
 
   transcribe: async (
     meetingId: string,
-    audioPath: string,
     modelSize?: string,
     language?: string,
   ) => {
-    set({ isTranscribing: true, error: null });
+    set({
+      isTranscribing: true,
+      transcriptionJobId: null,
+      transcriptionProgress: null,
+      partialSegments: [],
+      error: null,
+    });
+    const unlisten = await listen<TranscriptionProgressEvent>(
+      "transcription-progress",
+      (event) => {
+        if (event.payload.meeting_id !== meetingId) return;
+        set((state) => ({
+          transcriptionProgress: event.payload,
+          partialSegments: [
+            ...state.partialSegments,
+            ...event.payload.segments,
+          ],
+        }));
+      },
+    );
+    // Il job gira in background: la fine arriva come evento, quindi il
+    // listener va registrato prima di avviarlo
+    let resolveFinished: (event: TranscriptionFinishedEvent) => void = () => {};
+    const finished = new Promise<TranscriptionFinishedEvent>((resolve) => {
+      resolveFinished = resolve;
+    });
+    const unlistenFinished = await listen<TranscriptionFinishedEvent>(
+      "transcription-finished",
+      (event) => {
+        if (event.payload.meeting_id === meetingId) {
+          resolveFinished(event.payload);
+        }
+      },
+    );
     try {
-      const result = await transcribeMeeting(audioPath, modelSize, language);
-      await saveTranscription(
-        meetingId,
-        result.text,
-        result.language || undefined,
-      );
+      const jobId = await transcribeMeeting(meetingId, modelSize, language);
+      set({ transcriptionJobId: jobId });
+
+      const outcome = await finished;
+      if (outcome.status === "failed") {
+        throw new Error(outcome.error ?? "Trascrizione non riuscita");
+      }
 
       // Reload current meeting to get updated transcript
-      await get().loadMeeting(meetingId);
+      if (outcome.status === "completed") {
+        await get().loadMeeting(meetingId);
+      }
 
       set({ isTranscribing: false });
-      return result.text;
+      return outcome.status;
     } catch (e) {
       set({ error: String(e), isTranscribing: false });
       throw e;
+    } finally {
+      unlisten();
+      unlistenFinished();
+      set({
+        transcriptionJobId: null,
+        transcriptionProgress: null,
+        partialSegments: [],
+      });
+    }
+  },
+
+  cancelTranscription: async () => {
+    const jobId = get().transcriptionJobId;
+    if (!jobId) return;
+    try {
+      await cancelTranscriptionJob(jobId);
+    } catch (e) {
+      set({ error: String(e) });
     }
   },
 
@@ -1,43 +1,73 @@
 import { create } from "zustand";
-import type { AudioDevice } from "@/lib/tauri-commands";
+import type { AudioDevice, InputConfig, Meeting } from "@/lib/tauri-commands";
 import {
+  armRecording,
+  disarmRecording,
+  getAppSettings,
   getAudioDevices,
+  pauseRecording,
+  resumeRecording,
+  setPreferredAudioDevice,
   startRecording,
   stopRecording,
 } from "@/lib/tauri-commands";
 
 interface RecordingState {
   isRecording: boolean;
+  isPaused: boolean;
+  isArmed: boolean;
   currentMeetingId: string | null;
   elapsedSeconds: number;
   devices: AudioDevice[];
   selectedDeviceId: string | null;
+  selectedLoopbackId: string | null;
+  streamConfigs: Record<string, InputConfig>;
   error: string | null;
 
   // Actions
   loadDevices: () => Promise<void>;
   setSelectedDevice: (deviceId: string | null) => void;
-  start: () => Promise<void>;
+  setSelectedLoopback: (deviceId: string | null) => void;
+  setStreamConfig: (deviceId: string, config: InputConfig | null) => void;
+  arm: () => Promise<void>;
+  disarm: () => Promise<void>;
+  start: (title: string) => Promise<Meeting>;
   stop: () => Promise<string>;
+  // Registrazione avviata dal backend (per esempio da una programmazione)
+  attach: (meeting: Meeting) => void;
+  pause: () => Promise<void>;
+  resume: () => Promise<void>;
   tick: () => void;
   reset: () => void;
 }
 
 export const useRecordingStore = create<RecordingState>((set, get) => ({
   isRecording: false,
+  isPaused: false,
+  isArmed: false,
   currentMeetingId: null,
   elapsedSeconds: 0,
   devices: [],
   selectedDeviceId: null,
+  selectedLoopbackId: null,
+  streamConfigs: {},
   error: null,
 
   loadDevices: async () => {
     try {
-      const devices = await getAudioDevices();
-      const defaultDevice = devices.find((d) => d.is_default);
+      const [devices, settings] = await Promise.all([
+        getAudioDevices(),
+        getAppSettings(),
+      ]);
+      const inputs = devices.filter((d) => !d.is_loopback);
+      const preferredDevice = inputs.find(
+        (d) => d.id === settings.preferred_input_device,
+      );
+      const defaultDevice = inputs.find((d) => d.is_default);
       set({
         devices,
-        selectedDeviceId: defaultDevice?.id || devices[0]?.id || null,
+        selectedDeviceId:
+          preferredDevice?.id || defaultDevice?.id || inputs[0]?.id || null,
         error: null,
       });
     } catch (e) {
@@ -47,29 +77,98 @@ export const useRecordingStore = create<RecordingState>((set, get) => ({
 
   setSelectedDevice: (deviceId) => {
     set({ selectedDeviceId: deviceId });
+    // Il pre-roll vale solo per i dispositivi armati
+    get().disarm();
+    setPreferredAudioDevice(deviceId).catch((e) =>
+      console.error("Errore salvataggio dispositivo preferito:", e),
+    );
   },
 
-  start: async () => {
+  setSelectedLoopback: (deviceId) => {
+    set({ selectedLoopbackId: deviceId });
+    get().disarm();
+  },
+
+  setStreamConfig: (deviceId, config) => {
+    set((state) => {
+      const streamConfigs = { ...state.streamConfigs };
+      if (config) {
+        streamConfigs[deviceId] = config;
+      } else {
+        delete streamConfigs[deviceId];
+      }
+      return { streamConfigs };
+    });
+    get().disarm();
+  },
+
+  arm: async () => {
+    try {
+      const { selectedDeviceId, selectedLoopbackId, streamConfigs } = get();
+      const deviceIds = [selectedDeviceId, selectedLoopbackId].filter(
+        (id): id is string => !!id,
+      );
+      await armRecording(deviceIds, streamConfigs);
+      set({ isArmed: true, error: null });
+    } catch (e) {
+      set({ isArmed: false, error: String(e) });
+      throw e;
+    }
+  },
+
+  disarm: async () => {
+    if (!get().isArmed) return;
     try {
-      const { selectedDeviceId } = get();
-      const meetingId = await startRecording(selectedDeviceId || undefined);
+      await disarmRecording();
+    } finally {
+      set({ isArmed: false });
+    }
+  },
+
+  start: async (title: string) => {
+    try {
+      const { selectedDeviceId, selectedLoopbackId, streamConfigs } = get();
+      const deviceIds = [selectedDeviceId, selectedLoopbackId].filter(
+        (id): id is string => !!id,
+      );
+      const meeting = await startRecording(
+        deviceIds,
+        title,
+        undefined,
+        streamConfigs,
+      );
       set({
         isRecording: true,
-        currentMeetingId: meetingId,
+        isPaused: false,
+        isArmed: false,
+        currentMeetingId: meeting.id,
         elapsedSeconds: 0,
         error: null,
       });
+      return meeting;
     } catch (e) {
       set({ error: String(e) });
       throw e;
     }
   },
 
+  attach: (meeting) => {
+    set({
+      isRecording: true,
+      isPaused: false,
+      isArmed: false,
+      currentMeetingId: meeting.id,
+      elapsedSeconds: 0,
+      error: null,
+    });
+  },
+
   stop: async () => {
     try {
       const audioPath = await stopRecording();
       set({
         isRecording: false,
+        isPaused: false,
         currentMeetingId: null,
         error: null,
       });
@@ -80,6 +179,26 @@ export const useRecordingStore = create<RecordingState>((set, get) => ({
     }
   },
 
+  pause: async () => {
+    try {
+      await pauseRecording();
+      set({ isPaused: true, error: null });
+    } catch (e) {
+      set({ error: String(e) });
+      throw e;
+    }
+  },
+
+  resume: async () => {
+    try {
+      await resumeRecording();
+      set({ isPaused: false, error: null });
+    } catch (e) {
+      set({ error: String(e) });
+      throw e;
+    }
+  },
+
   tick: () => {
     set((state) => ({ elapsedSeconds: state.elapsedSeconds + 1 }));
   },
@@ -87,6 +206,8 @@ export const useRecordingStore = create<RecordingState>((set, get) => ({
   reset: () => {
     set({
       isRecording: false,
+      isPaused: false,
+      isArmed: false,
       currentMeetingId: null,
       elapsedSeconds: 0,
       error: null,